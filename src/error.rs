@@ -0,0 +1,601 @@
+use std::{error::Error, fmt, io};
+
+use crate::lexer::Token;
+use crate::parser::JVal;
+use crate::reader::Position;
+
+/// Everything that can go wrong while reading, tokenizing or parsing a document.
+/// Every variant records the position in the input the problem was found at.
+#[derive(Debug)]
+pub enum ParseError {
+    Io {
+        source: io::Error,
+        position: Position,
+    },
+    InvalidUtf8 {
+        position: Position,
+    },
+    /// The input ended in the middle of a multi-byte UTF-8 sequence.
+    IncompleteUtf8 {
+        position: Position,
+    },
+    UnexpectedEof {
+        expected: &'static str,
+        position: Position,
+    },
+    InvalidCharacter {
+        found: char,
+        position: Position,
+    },
+    /// A character below U+0020 written into a string as is rather than escaped.
+    ControlCharacter {
+        found: char,
+        position: Position,
+    },
+    /// `sequence` is the escape without its leading backslash, e.g. `x` or `u12G`.
+    InvalidEscape {
+        sequence: String,
+        position: Position,
+    },
+    LoneSurrogate {
+        code_unit: u16,
+        position: Position,
+    },
+    InvalidNumber {
+        text: String,
+        reason: String,
+        position: Position,
+    },
+    /// `found` is the word that was read, including any character that illegally follows it.
+    InvalidLiteral {
+        found: String,
+        expected: &'static str,
+        position: Position,
+    },
+    /// `first` is where the key occurred before `position`.
+    DuplicateKey {
+        key: String,
+        first: Position,
+        position: Position,
+    },
+    MissingComma {
+        position: Position,
+    },
+    /// A comma directly after an opening bracket.
+    LeadingComma {
+        position: Position,
+    },
+    /// A comma directly after another comma.
+    DoubledComma {
+        position: Position,
+    },
+    /// A comma directly before a closing bracket.
+    TrailingComma {
+        position: Position,
+    },
+    /// Something other than whitespace followed the root value.
+    TrailingData {
+        position: Position,
+    },
+    UnexpectedToken {
+        expected: &'static str,
+        found: Token,
+        position: Position,
+    },
+    /// Objects and arrays nested deeper than `Limits::max_depth`, at the opening
+    /// bracket that went too deep.
+    DepthLimitExceeded {
+        limit: usize,
+        position: Position,
+    },
+    /// A string longer than `Limits::max_string_length`, at its opening quote.
+    StringTooLong {
+        limit: usize,
+        position: Position,
+    },
+    /// An object with more than `Limits::max_object_members`, at the first member too many.
+    TooManyMembers {
+        limit: usize,
+        position: Position,
+    },
+    /// An array with more than `Limits::max_array_elements`, at the first element too many.
+    TooManyElements {
+        limit: usize,
+        position: Position,
+    },
+    /// Input longer than `Limits::max_input_size`, at the byte where the limit was reached.
+    InputTooLarge {
+        limit: usize,
+        position: Position,
+    },
+    /// A number longer than `Limits::max_number_length`, at its first character.
+    NumberTooLong {
+        limit: usize,
+        position: Position,
+    },
+    /// The parser reached a state its own bookkeeping should have ruled out.
+    Internal {
+        message: &'static str,
+        position: Position,
+    },
+}
+
+impl ParseError {
+    pub fn position(&self) -> Position {
+        match self {
+            ParseError::Io { position, .. }
+            | ParseError::InvalidUtf8 { position }
+            | ParseError::IncompleteUtf8 { position }
+            | ParseError::UnexpectedEof { position, .. }
+            | ParseError::InvalidCharacter { position, .. }
+            | ParseError::ControlCharacter { position, .. }
+            | ParseError::InvalidEscape { position, .. }
+            | ParseError::LoneSurrogate { position, .. }
+            | ParseError::InvalidNumber { position, .. }
+            | ParseError::InvalidLiteral { position, .. }
+            | ParseError::DuplicateKey { position, .. }
+            | ParseError::MissingComma { position }
+            | ParseError::LeadingComma { position }
+            | ParseError::DoubledComma { position }
+            | ParseError::TrailingComma { position }
+            | ParseError::TrailingData { position }
+            | ParseError::UnexpectedToken { position, .. }
+            | ParseError::DepthLimitExceeded { position, .. }
+            | ParseError::StringTooLong { position, .. }
+            | ParseError::TooManyMembers { position, .. }
+            | ParseError::TooManyElements { position, .. }
+            | ParseError::InputTooLarge { position, .. }
+            | ParseError::NumberTooLong { position, .. }
+            | ParseError::Internal { position, .. } => *position,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io { .. } => write!(f, "Failed to read input"),
+            ParseError::InvalidUtf8 { position } => {
+                write!(
+                    f,
+                    "Invalid UTF-8 sequence at byte offset {}",
+                    position.offset
+                )
+            }
+            ParseError::IncompleteUtf8 { position } => write!(
+                f,
+                "Incomplete UTF-8 sequence at byte offset {}",
+                position.offset
+            ),
+            ParseError::UnexpectedEof { expected, .. } => {
+                write!(f, "Unexpected end of input, expected {}", expected)
+            }
+            ParseError::InvalidCharacter { found, .. } => {
+                write!(f, "Invalid character '{}' found when parsing", found)
+            }
+            ParseError::ControlCharacter { found, .. } => write!(
+                f,
+                "Unescaped control character U+{:04X} in string",
+                u32::from(*found)
+            ),
+            ParseError::InvalidEscape { sequence, .. } => {
+                write!(f, "Invalid escape sequence '\\{}'", sequence)
+            }
+            ParseError::LoneSurrogate { code_unit, .. } => {
+                write!(f, "Lone surrogate '\\u{:04X}' in string", code_unit)
+            }
+            ParseError::InvalidNumber { text, reason, .. } => {
+                write!(f, "Invalid number '{}': {}", text, reason)
+            }
+            ParseError::InvalidLiteral {
+                found, expected, ..
+            } => write!(f, "Invalid literal '{}', expected '{}'", found, expected),
+            ParseError::DuplicateKey { key, first, .. } => {
+                write!(f, "Duplicate key {:?}, first defined at {}", key, first)
+            }
+            ParseError::MissingComma { .. } => write!(f, "Missing comma between elements"),
+            ParseError::LeadingComma { .. } => write!(f, "Comma before the first element"),
+            ParseError::DoubledComma { .. } => write!(f, "Consecutive commas between elements"),
+            ParseError::TrailingComma { .. } => write!(f, "Trailing comma before closing bracket"),
+            ParseError::TrailingData { .. } => {
+                write!(f, "Unexpected trailing data after the root value")
+            }
+            ParseError::UnexpectedToken {
+                expected, found, ..
+            } => write!(f, "Expected {}, found {}", expected, found),
+            ParseError::DepthLimitExceeded { limit, .. } => {
+                write!(f, "Nesting exceeds the limit of {} levels", limit)
+            }
+            ParseError::StringTooLong { limit, .. } => {
+                write!(f, "String exceeds the limit of {} bytes", limit)
+            }
+            ParseError::TooManyMembers { limit, .. } => {
+                write!(f, "Object exceeds the limit of {} members", limit)
+            }
+            ParseError::TooManyElements { limit, .. } => {
+                write!(f, "Array exceeds the limit of {} elements", limit)
+            }
+            ParseError::InputTooLarge { limit, .. } => {
+                write!(f, "Input exceeds the limit of {} bytes", limit)
+            }
+            ParseError::NumberTooLong { limit, .. } => {
+                write!(f, "Number exceeds the limit of {} characters", limit)
+            }
+            ParseError::Internal { message, .. } => {
+                write!(f, "Internal parser error: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Why a JSON Pointer could not be resolved. `pointer` is the prefix of the pointer up
+/// to and including the reference token that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// The pointer is neither empty nor starts with `/`.
+    MissingSlash {
+        pointer: String,
+    },
+    /// A `~` not followed by `0` or `1`.
+    InvalidEscape {
+        pointer: String,
+    },
+    /// A token used on an array that is neither `-` nor an index without leading zeros.
+    InvalidIndex {
+        pointer: String,
+    },
+    IndexOutOfBounds {
+        index: usize,
+        len: usize,
+        pointer: String,
+    },
+    NotFound {
+        pointer: String,
+    },
+    /// A token applied to a string, number, boolean or null.
+    NotAContainer {
+        pointer: String,
+    },
+    /// The empty pointer names the whole document, which cannot be removed.
+    RemoveRoot,
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerError::MissingSlash { pointer } => {
+                write!(
+                    f,
+                    "JSON pointer '{}' must be empty or start with '/'",
+                    pointer
+                )
+            }
+            PointerError::InvalidEscape { pointer } => write!(
+                f,
+                "Invalid escape in JSON pointer '{}', expected '~0' or '~1'",
+                pointer
+            ),
+            PointerError::InvalidIndex { pointer } => {
+                write!(f, "Invalid array index at '{}'", pointer)
+            }
+            PointerError::IndexOutOfBounds {
+                index,
+                len,
+                pointer,
+            } => write!(
+                f,
+                "Index {} is out of bounds for an array of length {} at '{}'",
+                index, len, pointer
+            ),
+            PointerError::NotFound { pointer } => write!(f, "No value at '{}'", pointer),
+            PointerError::NotAContainer { pointer } => {
+                write!(f, "Cannot index into a scalar value at '{}'", pointer)
+            }
+            PointerError::RemoveRoot => write!(f, "Cannot remove the document root"),
+        }
+    }
+}
+
+impl Error for PointerError {}
+
+/// Why a JSONPath query could not be compiled. `offset` is the byte offset in the
+/// query the problem was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnexpectedEnd {
+        expected: &'static str,
+        offset: usize,
+    },
+    UnexpectedCharacter {
+        found: char,
+        expected: &'static str,
+        offset: usize,
+    },
+    /// An index, slice bound or integer literal outside ±(2^53 - 1), or an integer
+    /// with a leading zero.
+    InvalidInteger {
+        text: String,
+        offset: usize,
+    },
+    InvalidEscape {
+        offset: usize,
+    },
+    UnknownFunction {
+        name: String,
+        offset: usize,
+    },
+    WrongArgumentCount {
+        function: &'static str,
+        expected: usize,
+        offset: usize,
+    },
+    /// A well-formed expression used where its type is not allowed, such as a query
+    /// that can select several nodes on one side of a comparison.
+    TypeMismatch {
+        message: &'static str,
+        offset: usize,
+    },
+}
+
+impl QueryError {
+    pub fn offset(&self) -> usize {
+        match self {
+            QueryError::UnexpectedEnd { offset, .. }
+            | QueryError::UnexpectedCharacter { offset, .. }
+            | QueryError::InvalidInteger { offset, .. }
+            | QueryError::InvalidEscape { offset }
+            | QueryError::UnknownFunction { offset, .. }
+            | QueryError::WrongArgumentCount { offset, .. }
+            | QueryError::TypeMismatch { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnexpectedEnd { expected, .. } => {
+                write!(f, "Unexpected end of query, expected {}", expected)
+            }
+            QueryError::UnexpectedCharacter {
+                found, expected, ..
+            } => write!(f, "Expected {}, found '{}'", expected, found),
+            QueryError::InvalidInteger { text, .. } => write!(f, "Invalid integer '{}'", text),
+            QueryError::InvalidEscape { .. } => write!(f, "Invalid escape sequence in string"),
+            QueryError::UnknownFunction { name, .. } => write!(f, "Unknown function '{}'", name),
+            QueryError::WrongArgumentCount {
+                function, expected, ..
+            } => write!(
+                f,
+                "Function '{}' takes {} argument{}",
+                function,
+                expected,
+                if *expected == 1 { "" } else { "s" }
+            ),
+            QueryError::TypeMismatch { message, .. } => write!(f, "{}", message),
+        }?;
+        write!(f, " at offset {}", self.offset())
+    }
+}
+
+impl Error for QueryError {}
+
+/// Why a jq-style filter could not be compiled. `offset` is the byte offset in the
+/// filter the problem was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    UnexpectedEnd {
+        expected: &'static str,
+        offset: usize,
+    },
+    /// `found` is the character or keyword that was read.
+    UnexpectedToken {
+        found: String,
+        expected: &'static str,
+        offset: usize,
+    },
+    InvalidEscape {
+        offset: usize,
+    },
+    UnknownFunction {
+        name: String,
+        arity: usize,
+        offset: usize,
+    },
+    UnknownFormat {
+        name: String,
+        offset: usize,
+    },
+    UndefinedVariable {
+        name: String,
+        offset: usize,
+    },
+}
+
+impl FilterError {
+    pub fn offset(&self) -> usize {
+        match self {
+            FilterError::UnexpectedEnd { offset, .. }
+            | FilterError::UnexpectedToken { offset, .. }
+            | FilterError::InvalidEscape { offset }
+            | FilterError::UnknownFunction { offset, .. }
+            | FilterError::UnknownFormat { offset, .. }
+            | FilterError::UndefinedVariable { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::UnexpectedEnd { expected, .. } => {
+                write!(f, "Unexpected end of filter, expected {}", expected)
+            }
+            FilterError::UnexpectedToken {
+                found, expected, ..
+            } => write!(f, "Expected {}, found '{}'", expected, found),
+            FilterError::InvalidEscape { .. } => write!(f, "Invalid escape sequence in string"),
+            FilterError::UnknownFunction { name, arity, .. } => {
+                write!(f, "Unknown function '{}/{}'", name, arity)
+            }
+            FilterError::UnknownFormat { name, .. } => write!(f, "Unknown format '@{}'", name),
+            FilterError::UndefinedVariable { name, .. } => {
+                write!(f, "Variable '${}' is not defined", name)
+            }
+        }?;
+        write!(f, " at offset {}", self.offset())
+    }
+}
+
+impl Error for FilterError {}
+
+/// An error raised while running a filter, which `try` and `?` can catch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// An operation applied to values it does not support, e.g. indexing a number.
+    Invalid { message: String },
+    /// Raised by `error`, carrying its argument.
+    Raised { value: JVal },
+}
+
+impl EvalError {
+    /// The value a `catch` clause receives: the message, or the argument to `error`.
+    pub fn value(&self) -> JVal {
+        match self {
+            EvalError::Invalid { message } => JVal::JString(message.clone()),
+            EvalError::Raised { value } => value.clone(),
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Invalid { message } => write!(f, "{}", message),
+            EvalError::Raised {
+                value: JVal::JString(message),
+            } => write!(f, "{}", message),
+            EvalError::Raised { value } => write!(f, "{} (not a string)", value),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// Why a visitor that writes its output, such as `visit::Emitter`, stopped.
+#[derive(Debug)]
+pub enum EmitError {
+    Parse(ParseError),
+    Io(io::Error),
+}
+
+impl From<ParseError> for EmitError {
+    fn from(error: ParseError) -> Self {
+        EmitError::Parse(error)
+    }
+}
+
+impl From<io::Error> for EmitError {
+    fn from(error: io::Error) -> Self {
+        EmitError::Io(error)
+    }
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmitError::Parse(error) => write!(f, "{}", error),
+            EmitError::Io(_) => write!(f, "Failed to write output"),
+        }
+    }
+}
+
+impl Error for EmitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EmitError::Parse(error) => error.source(),
+            EmitError::Io(error) => Some(error),
+        }
+    }
+}
+
+/// Why converting between Rust types and JSON with serde failed.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum SerdeError {
+    /// The input is not valid JSON.
+    Parse(ParseError),
+    Io(io::Error),
+    /// Raised by a `Serialize` or `Deserialize` implementation, e.g. for a missing field
+    /// or a value of the wrong type. `position` is where in the input the value started.
+    Custom {
+        message: String,
+        position: Option<Position>,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl SerdeError {
+    /// Records where the value being deserialized started, unless already known.
+    pub(crate) fn at(self, at: Position) -> Self {
+        match self {
+            SerdeError::Custom {
+                message,
+                position: None,
+            } => SerdeError::Custom {
+                message,
+                position: Some(at),
+            },
+            error => error,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ParseError> for SerdeError {
+    fn from(error: ParseError) -> Self {
+        SerdeError::Parse(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<io::Error> for SerdeError {
+    fn from(error: io::Error) -> Self {
+        SerdeError::Io(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerdeError::Parse(error) => write!(f, "{}", error),
+            SerdeError::Io(_) => write!(f, "Failed to write output"),
+            SerdeError::Custom {
+                message,
+                position: Some(position),
+            } => write!(f, "{} at {}", message, position),
+            SerdeError::Custom { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for SerdeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SerdeError::Parse(error) => error.source(),
+            SerdeError::Io(error) => Some(error),
+            SerdeError::Custom { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::error::ParseError;
+use crate::lexer::{SpannedToken, Token, TokenReader};
+use crate::parser::JVal;
+use crate::reader::Position;
+use std::io::Read;
+
+#[cfg(test)]
+mod tests;
+
+/// One step through a document, in source order.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    StartObject,
+    /// An object key. The member's value follows as the next event, or events.
+    Key(String),
+    EndObject,
+    StartArray,
+    EndArray,
+    /// A string, number, boolean or null. Never an object or array.
+    Value(JVal),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// Where a container is in its comma-separated list of members.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Separator {
+    /// Just opened, so a member or the closing bracket may follow.
+    Open,
+    /// After the comma at this position, so a member must follow.
+    Comma(Position),
+    /// After a member, so a comma or the closing bracket must follow.
+    Member,
+}
+
+impl Separator {
+    pub(crate) fn expect_member(self, position: Position) -> Result<(), ParseError> {
+        match self {
+            Separator::Member => Err(ParseError::MissingComma { position }),
+            Separator::Open | Separator::Comma(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn comma(self, position: Position) -> Result<Separator, ParseError> {
+        match self {
+            Separator::Open => Err(ParseError::LeadingComma { position }),
+            Separator::Comma(_) => Err(ParseError::DoubledComma { position }),
+            Separator::Member => Ok(Separator::Comma(position)),
+        }
+    }
+
+    /// Checks the container may close here, which after a comma needs `trailing_commas`.
+    pub(crate) fn close(self, trailing_commas: bool) -> Result<(), ParseError> {
+        match self {
+            Separator::Comma(position) if !trailing_commas => {
+                Err(ParseError::TrailingComma { position })
+            }
+            Separator::Open | Separator::Comma(_) | Separator::Member => Ok(()),
+        }
+    }
+}
+
+/// A pull parser over the first value in a token stream, yielding it as a sequence of
+/// events instead of building a tree. Memory use grows with the nesting depth only,
+/// so documents far larger than memory can be processed.
+///
+/// Iteration ends after the event that completes the value, leaving the rest of the
+/// stream unread, or after the first error.
+pub struct EventReader<'a, R: Read> {
+    tokens: &'a mut TokenReader<R>,
+    // The open containers with how many members each has so far, innermost last
+    stack: Vec<(Container, Separator, usize)>,
+    // A key and its colon have been read, so the member's value comes next
+    after_key: bool,
+    started: bool,
+    done: bool,
+    position: Position,
+}
+
+impl<'a, R: Read> EventReader<'a, R> {
+    pub fn new(tokens: &'a mut TokenReader<R>) -> Self {
+        EventReader {
+            tokens,
+            stack: Vec::new(),
+            after_key: false,
+            started: false,
+            done: false,
+            position: Position::default(),
+        }
+    }
+
+    /// Where the most recent event started, e.g. the opening quote of a key.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// How many objects and arrays are currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, ParseError> {
+        if !self.started {
+            self.started = true;
+            let SpannedToken { token, span } = self.next_token("JSON value")?;
+            self.position = span.start;
+            return match token {
+                Token::OpenObject | Token::OpenArray => self.open(token, span.start).map(Some),
+                _ if self.tokens.options().container_root_only => {
+                    Err(ParseError::UnexpectedToken {
+                        expected: "'{' or '['",
+                        found: token,
+                        position: span.start,
+                    })
+                }
+                _ => Ok(Some(Event::Value(parse_atom(token, span.start)?))),
+            };
+        }
+
+        let Some(&(container, separator, _)) = self.stack.last() else {
+            return Ok(None);
+        };
+        if self.after_key {
+            self.after_key = false;
+            let SpannedToken { token, span } = self.next_token("object value")?;
+            self.position = span.start;
+            return self.member(token, span.start);
+        }
+
+        let SpannedToken { token, span } =
+            self.next_token("'}' or ']' to close the open object or array")?;
+        self.position = span.start;
+        match (container, token) {
+            (_, Token::Comma) => {
+                self.set_separator(separator.comma(span.start)?);
+                self.next_event()
+            }
+            (Container::Object, Token::CloseObject) | (Container::Array, Token::CloseArray) => {
+                separator.close(self.tokens.options().allow_trailing_commas)?;
+                self.stack.pop();
+                Ok(Some(if container == Container::Object {
+                    Event::EndObject
+                } else {
+                    Event::EndArray
+                }))
+            }
+            (Container::Object, Token::TString(key)) => {
+                separator.expect_member(span.start)?;
+                self.count_member(span.start)?;
+                let colon = self.next_token("':' after object key")?;
+                if colon.token != Token::Colon {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: "':' after object key",
+                        found: colon.token,
+                        position: colon.span.start,
+                    });
+                }
+                self.after_key = true;
+                Ok(Some(Event::Key(key)))
+            }
+            (Container::Object, token) => Err(ParseError::UnexpectedToken {
+                expected: match separator {
+                    Separator::Open => "string key or '}'",
+                    Separator::Comma(_) if self.tokens.options().allow_trailing_commas => {
+                        "string key or '}'"
+                    }
+                    Separator::Comma(_) => "string key",
+                    Separator::Member => "',' or '}'",
+                },
+                found: token,
+                position: span.start,
+            }),
+            (Container::Array, token) => {
+                separator.expect_member(span.start)?;
+                self.count_member(span.start)?;
+                self.member(token, span.start)
+            }
+        }
+    }
+
+    /// Starts the member `token` begins within the innermost container.
+    fn member(&mut self, token: Token, position: Position) -> Result<Option<Event>, ParseError> {
+        self.set_separator(Separator::Member);
+        match token {
+            Token::OpenObject | Token::OpenArray => self.open(token, position).map(Some),
+            token => Ok(Some(Event::Value(parse_atom(token, position)?))),
+        }
+    }
+
+    fn open(&mut self, token: Token, position: Position) -> Result<Event, ParseError> {
+        let limits = &self.tokens.options().limits;
+        limits.check_depth(self.stack.len() + 1, position)?;
+        Ok(if token == Token::OpenObject {
+            self.stack.push((Container::Object, Separator::Open, 0));
+            Event::StartObject
+        } else {
+            self.stack.push((Container::Array, Separator::Open, 0));
+            Event::StartArray
+        })
+    }
+
+    fn set_separator(&mut self, separator: Separator) {
+        if let Some(last) = self.stack.last_mut() {
+            last.1 = separator;
+        }
+    }
+
+    /// Counts the member of the innermost container starting at `position`.
+    fn count_member(&mut self, position: Position) -> Result<(), ParseError> {
+        let limits = self.tokens.options().limits;
+        if let Some((container, _, count)) = self.stack.last_mut() {
+            *count += 1;
+            limits.check_members(*container == Container::Array, *count, position)?;
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self, expected: &'static str) -> Result<SpannedToken, ParseError> {
+        self.tokens
+            .next()
+            .unwrap_or_else(|| Err(unexpected_eof(self.tokens, expected)))
+    }
+}
+
+impl<R: Read> Iterator for EventReader<'_, R> {
+    type Item = Result<Event, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event().transpose();
+        // Stop after an error, or once the root value is complete
+        self.done = !matches!(event, Some(Ok(_))) || self.stack.is_empty();
+        event
+    }
+}
+
+fn parse_atom(token: Token, position: Position) -> Result<JVal, ParseError> {
+    match token {
+        Token::TString(val) => Ok(JVal::JString(val)),
+        Token::TInt(val) => Ok(JVal::JInt(val)),
+        Token::TUint(val) => Ok(JVal::JUint(val)),
+        Token::TFloat(val) => Ok(JVal::JFloat(val)),
+        Token::TBool(val) => Ok(JVal::JBool(val)),
+        Token::TNull => Ok(JVal::JNull),
+        _ => Err(ParseError::UnexpectedToken {
+            expected: "string, number, boolean, or null",
+            found: token,
+            position,
+        }),
+    }
+}
+
+fn unexpected_eof<R: Read>(iter: &TokenReader<R>, expected: &'static str) -> ParseError {
+    ParseError::UnexpectedEof {
+        expected,
+        position: iter.current_position(),
+    }
+}
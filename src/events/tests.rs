@@ -0,0 +1,243 @@
+use super::*;
+use crate::options::{Limits, ParseOptions};
+use crate::reader::JsonReader;
+
+fn events(input: &str) -> Vec<Event> {
+    let mut tokens = TokenReader::new(JsonReader::from_str(input));
+    EventReader::new(&mut tokens)
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn event_err(input: &str) -> ParseError {
+    let mut tokens = TokenReader::new(JsonReader::from_str(input));
+    EventReader::new(&mut tokens)
+        .find_map(Result::err)
+        .expect("expected an error")
+}
+
+fn string(value: &str) -> Event {
+    Event::Value(JVal::JString(value.to_string()))
+}
+
+#[test]
+fn test_nested_events() {
+    assert_eq!(
+        events(r#"{"a": [1, {"b": null}], "c": "d"}"#),
+        vec![
+            Event::StartObject,
+            Event::Key("a".to_string()),
+            Event::StartArray,
+            Event::Value(JVal::from(1)),
+            Event::StartObject,
+            Event::Key("b".to_string()),
+            Event::Value(JVal::JNull),
+            Event::EndObject,
+            Event::EndArray,
+            Event::Key("c".to_string()),
+            string("d"),
+            Event::EndObject,
+        ]
+    );
+}
+
+#[test]
+fn test_empty_containers_and_scalar_roots() {
+    assert_eq!(
+        events("[[], {}]"),
+        vec![
+            Event::StartArray,
+            Event::StartArray,
+            Event::EndArray,
+            Event::StartObject,
+            Event::EndObject,
+            Event::EndArray,
+        ]
+    );
+    assert_eq!(events(" true "), vec![Event::Value(JVal::JBool(true))]);
+    assert_eq!(events(r#""s""#), vec![string("s")]);
+}
+
+#[test]
+fn test_stops_after_the_first_value() {
+    let mut tokens = TokenReader::new(JsonReader::from_str("[1] [2]"));
+    assert_eq!(EventReader::new(&mut tokens).count(), 3);
+    let rest: Vec<_> = EventReader::new(&mut tokens)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rest,
+        vec![
+            Event::StartArray,
+            Event::Value(JVal::from(2)),
+            Event::EndArray
+        ]
+    );
+    assert!(tokens.next().is_none());
+}
+
+#[test]
+fn test_position_and_depth() {
+    let mut tokens = TokenReader::new(JsonReader::from_str("{\n  \"a\": [true]\n}"));
+    let mut reader = EventReader::new(&mut tokens);
+    let mut seen = Vec::new();
+    while let Some(event) = reader.next() {
+        let position = reader.position();
+        seen.push((
+            event.unwrap(),
+            position.line,
+            position.column,
+            reader.depth(),
+        ));
+    }
+    assert_eq!(
+        seen,
+        vec![
+            (Event::StartObject, 1, 1, 1),
+            (Event::Key("a".to_string()), 2, 3, 1),
+            (Event::StartArray, 2, 8, 2),
+            (Event::Value(JVal::JBool(true)), 2, 9, 2),
+            (Event::EndArray, 2, 13, 1),
+            (Event::EndObject, 3, 1, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_errors_end_the_stream() {
+    let mut tokens = TokenReader::new(JsonReader::from_str("[1 2] [3]"));
+    let results: Vec<_> = EventReader::new(&mut tokens).collect();
+    assert_eq!(results.len(), 3);
+    assert!(matches!(results[2], Err(ParseError::MissingComma { .. })));
+
+    assert!(matches!(
+        event_err("[1,]"),
+        ParseError::TrailingComma { .. }
+    ));
+    assert!(matches!(
+        event_err(r#"{"a" 1}"#),
+        ParseError::UnexpectedToken {
+            expected: "':' after object key",
+            ..
+        }
+    ));
+    assert!(matches!(
+        event_err(r#"{"a": 1"#),
+        ParseError::UnexpectedEof { .. }
+    ));
+    assert!(matches!(
+        event_err("[}"),
+        ParseError::UnexpectedToken {
+            found: Token::CloseObject,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_trailing_commas() {
+    let options = ParseOptions::default().allow_trailing_commas(true);
+    let reader = JsonReader::from_reader_with_options(r#"{"a": [1, 2,],}"#.as_bytes(), options);
+    let events: Vec<Event> = EventReader::new(&mut TokenReader::new(reader))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![
+            Event::StartObject,
+            Event::Key("a".to_string()),
+            Event::StartArray,
+            Event::Value(JVal::from(1)),
+            Event::Value(JVal::from(2)),
+            Event::EndArray,
+            Event::EndObject,
+        ]
+    );
+
+    // Only a single comma before the closing bracket is allowed
+    for input in ["[,]", "[1,,]", "{,}"] {
+        let options = ParseOptions::default().allow_trailing_commas(true);
+        let reader = JsonReader::from_reader_with_options(input.as_bytes(), options);
+        let result: Result<Vec<Event>, _> =
+            EventReader::new(&mut TokenReader::new(reader)).collect();
+        assert!(
+            matches!(
+                result,
+                Err(ParseError::LeadingComma { .. } | ParseError::DoubledComma { .. })
+            ),
+            "{}: {:?}",
+            input,
+            result
+        );
+    }
+}
+
+#[test]
+fn test_container_root_only() {
+    let options = ParseOptions {
+        container_root_only: true,
+        ..ParseOptions::default()
+    };
+    let mut tokens = TokenReader::new(JsonReader::from_reader_with_options(
+        "1".as_bytes(),
+        options,
+    ));
+    assert!(matches!(
+        EventReader::new(&mut tokens).next(),
+        Some(Err(ParseError::UnexpectedToken {
+            expected: "'{' or '['",
+            ..
+        }))
+    ));
+}
+
+fn limited(input: &str, limits: Limits) -> Result<Vec<Event>, ParseError> {
+    let options = ParseOptions {
+        limits,
+        ..ParseOptions::default()
+    };
+    let reader = JsonReader::from_reader_with_options(input.as_bytes(), options);
+    EventReader::new(&mut TokenReader::new(reader)).collect()
+}
+
+#[test]
+fn test_structure_limits() {
+    let limits = Limits {
+        max_depth: Some(2),
+        max_object_members: Some(2),
+        max_array_elements: Some(3),
+        ..Limits::default()
+    };
+    assert!(limited(r#"{"a": [1, 2, 3], "b": {"c": "d"}}"#, limits).is_ok());
+    // Counts are per container, not per document
+    assert!(limited("[[1, 2, 3], [4, 5, 6], [7, 8, 9]]", limits).is_ok());
+
+    match limited("[[1], [[2]]]", limits) {
+        Err(ParseError::DepthLimitExceeded { limit, position }) => {
+            assert_eq!((limit, position.offset), (2, 7));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    match limited(r#"{"a": 1, "b": 2, "c": 3}"#, limits) {
+        Err(ParseError::TooManyMembers { limit, position }) => {
+            assert_eq!((limit, position.offset), (2, 17));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    match limited("[[1, 2, 3, 4]]", limits) {
+        Err(ParseError::TooManyElements { limit, position }) => {
+            assert_eq!((limit, position.offset), (3, 11));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let no_containers = Limits {
+        max_depth: Some(0),
+        ..Limits::default()
+    };
+    assert!(limited("1", no_containers).is_ok());
+    assert!(matches!(
+        limited("[]", no_containers),
+        Err(ParseError::DepthLimitExceeded { .. })
+    ));
+}
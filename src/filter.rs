@@ -0,0 +1,771 @@
+use std::str::FromStr;
+
+use crate::error::{EvalError, FilterError};
+use crate::map::JMap;
+use crate::parser::JVal;
+
+mod builtins;
+mod ops;
+mod syntax;
+#[cfg(test)]
+mod tests;
+
+/// A compiled jq-style filter, e.g. `.items[] | select(.price < 10) | {name, price}`.
+///
+/// Covers the commonly used part of jq: paths and iteration, pipes and commas,
+/// array and object construction, string interpolation and `@` formats,
+/// arithmetic and comparisons, `if`, `try`, `reduce`, `foreach`, variables,
+/// assignment operators and the usual builtins. Function definitions are not
+/// supported.
+///
+/// As in jq, outputs are passed along one at a time, so `limit` and `first` stop a
+/// generator once they have what they need: `first(range(1e19))` finishes at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Identity,
+    Literal(JVal),
+    /// A string literal containing `\(...)`, whose results are rendered with `Format`.
+    String(Format, Vec<StringPart>),
+    /// A format such as `@csv` used on its own, applied to the input.
+    Format(Format),
+    Variable(String),
+    /// `target[key]`, where `key` runs against the input of the whole expression.
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `target[]`
+    Iterate(Box<Expr>),
+    /// `try body catch handler`; `body?` has no handler.
+    Try(Box<Expr>, Option<Box<Expr>>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Arithmetic(ArithOp, Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// `a // b`
+    Alternative(Box<Expr>, Box<Expr>),
+    Assign(AssignOp, Box<Expr>, Box<Expr>),
+    Array(Option<Box<Expr>>),
+    /// Key and value expressions; shorthands like `{name}` are expanded when parsing.
+    Object(Vec<(Expr, Expr)>),
+    /// `elif` chains are nested in `otherwise`, which is `.` when there is no `else`.
+    If {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// `source as $name | body`
+    Bind {
+        source: Box<Expr>,
+        name: String,
+        body: Box<Expr>,
+    },
+    Reduce {
+        source: Box<Expr>,
+        name: String,
+        init: Box<Expr>,
+        update: Box<Expr>,
+    },
+    Foreach {
+        source: Box<Expr>,
+        name: String,
+        init: Box<Expr>,
+        update: Box<Expr>,
+        extract: Option<Box<Expr>>,
+    },
+    Call(builtins::Builtin, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StringPart {
+    Literal(String),
+    Interpolation(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AssignOp {
+    /// `=`
+    Set,
+    /// `|=`
+    Update,
+    /// `+=`, `-=` and so on.
+    Arithmetic(ArithOp),
+    /// `//=`
+    Alternative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+    Tsv,
+    Html,
+    Uri,
+    Sh,
+    Base64,
+    Base64d,
+}
+
+/// A path from a filter's input to one of its outputs, as `path(f)` reports it:
+/// object keys as strings and array indexes as numbers.
+type Path = Vec<JVal>;
+
+impl Filter {
+    pub fn parse(filter: &str) -> Result<Filter, FilterError> {
+        Filter::parse_with_variables(filter, &[])
+    }
+
+    /// Compiles a filter that may refer to the given variables, which are supplied
+    /// to `apply_with_variables`. Names are given without the `$`.
+    pub fn parse_with_variables(filter: &str, variables: &[&str]) -> Result<Filter, FilterError> {
+        Ok(Filter {
+            expr: syntax::parse_filter(filter, variables)?,
+        })
+    }
+
+    /// Runs the filter and returns every value it outputs, in order.
+    pub fn apply(&self, input: &JVal) -> Result<Vec<JVal>, EvalError> {
+        self.apply_with_variables(input, &[])
+    }
+
+    pub fn apply_with_variables(
+        &self,
+        input: &JVal,
+        variables: &[(String, JVal)],
+    ) -> Result<Vec<JVal>, EvalError> {
+        let mut evaluator = Evaluator {
+            variables: variables
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone()))
+                .collect(),
+            labels: 0,
+        };
+        evaluator.collect(&self.expr, input)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        Filter::parse(filter)
+    }
+}
+
+/// Holds the variables in scope, innermost last.
+struct Evaluator<'a> {
+    variables: Vec<(&'a str, JVal)>,
+    // How many labels `label` has handed out, each identifying one `Halt::Break`
+    labels: usize,
+}
+
+/// Receives the outputs of an expression one at a time, as they are produced. An
+/// error it returns stops the expression.
+type Sink<'s, 'a, T> = dyn FnMut(&mut Evaluator<'a>, T) -> Result<(), Halt> + 's;
+
+/// Why evaluation stopped before running out of outputs.
+#[derive(Debug)]
+enum Halt {
+    /// An error, which `try` and `//` can catch.
+    Error(EvalError),
+    /// Nothing more is wanted by whoever holds the label, such as a `limit` that has
+    /// all its values. Passes through everything else untouched.
+    Break(usize),
+}
+
+impl From<EvalError> for Halt {
+    fn from(error: EvalError) -> Self {
+        Halt::Error(error)
+    }
+}
+
+impl Halt {
+    /// The error of an evaluation whose sink never fails. Only sinks break, so there
+    /// is no `Break` to come out of it.
+    fn into_error(self) -> EvalError {
+        match self {
+            Halt::Error(error) => error,
+            Halt::Break(_) => unreachable!("a break escaped the sink that raised it"),
+        }
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    fn collect(&mut self, expr: &'a Expr, input: &JVal) -> Result<Vec<JVal>, EvalError> {
+        let mut values = Vec::new();
+        self.eval(expr, input, &mut |_, value| {
+            values.push(value);
+            Ok(())
+        })
+        .map_err(Halt::into_error)?;
+        Ok(values)
+    }
+
+    /// Passes the outputs of `expr` to `out` as they are produced, so a consumer such as
+    /// `limit` can stop a generator early. On error, the outputs produced before it have
+    /// already been passed on, which is what `try` relies on.
+    fn eval(
+        &mut self,
+        expr: &'a Expr,
+        input: &JVal,
+        out: &mut Sink<'_, 'a, JVal>,
+    ) -> Result<(), Halt> {
+        match expr {
+            Expr::Identity => out(self, input.clone())?,
+            Expr::Literal(value) => out(self, value.clone())?,
+            Expr::String(format, parts) => {
+                for string in self.interpolate(*format, parts, input)? {
+                    out(self, JVal::JString(string))?;
+                }
+            }
+            Expr::Format(format) => out(self, JVal::JString(ops::format(*format, input)?))?,
+            Expr::Variable(name) => {
+                let value = self.variable(name)?.clone();
+                out(self, value)?;
+            }
+            Expr::Index(target, key) => {
+                let keys = self.collect(key, input)?;
+                return self.each(target, input, out, |evaluator, value, out| {
+                    for key in &keys {
+                        out(evaluator, ops::index(&value, key)?)?;
+                    }
+                    Ok(())
+                });
+            }
+            Expr::Slice(target, start, end) => {
+                let starts = self.optional_bound(start.as_deref(), input)?;
+                let ends = self.optional_bound(end.as_deref(), input)?;
+                return self.each(target, input, out, |evaluator, value, out| {
+                    for end in &ends {
+                        for start in &starts {
+                            out(evaluator, ops::slice(&value, start, end)?)?;
+                        }
+                    }
+                    Ok(())
+                });
+            }
+            Expr::Iterate(target) => {
+                return self.each(target, input, out, |evaluator, value, out| {
+                    for item in ops::iterate(value)? {
+                        out(evaluator, item)?;
+                    }
+                    Ok(())
+                });
+            }
+            Expr::Try(body, handler) => {
+                let result =
+                    self.guarded(out, |evaluator, out| evaluator.eval(body, input, out))?;
+                if let Err(error) = result {
+                    if let Some(handler) = handler {
+                        return self.eval(handler, &error.value(), out);
+                    }
+                }
+            }
+            Expr::Pipe(lhs, rhs) => {
+                return self.each(lhs, input, out, |evaluator, value, out| {
+                    evaluator.eval(rhs, &value, out)
+                });
+            }
+            Expr::Comma(lhs, rhs) => {
+                self.eval(lhs, input, out)?;
+                self.eval(rhs, input, out)?;
+            }
+            Expr::Negate(operand) => {
+                return self.each(operand, input, out, |evaluator, value, out| {
+                    out(evaluator, ops::negate(value)?)
+                });
+            }
+            // As in jq, the right operand is evaluated first and varies slowest
+            Expr::Arithmetic(op, lhs, rhs) => {
+                return self.each(rhs, input, out, |evaluator, right, out| {
+                    evaluator.each(lhs, input, out, |evaluator, left, out| {
+                        out(evaluator, ops::arithmetic(*op, left, right.clone())?)
+                    })
+                });
+            }
+            Expr::Compare(op, lhs, rhs) => {
+                return self.each(rhs, input, out, |evaluator, right, out| {
+                    evaluator.each(lhs, input, out, |evaluator, left, out| {
+                        out(
+                            evaluator,
+                            JVal::JBool(ops::compare_with(*op, &left, &right)),
+                        )
+                    })
+                });
+            }
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let short_circuit = matches!(expr, Expr::Or(..));
+                return self.each(lhs, input, out, |evaluator, left, out| {
+                    if ops::is_truthy(&left) == short_circuit {
+                        return out(evaluator, JVal::JBool(short_circuit));
+                    }
+                    evaluator.each(rhs, input, out, |evaluator, right, out| {
+                        out(evaluator, JVal::JBool(ops::is_truthy(&right)))
+                    })
+                });
+            }
+            Expr::Alternative(lhs, rhs) => {
+                // Errors on the left count as producing nothing
+                let mut any_truthy = false;
+                let _ = self.guarded(out, |evaluator, out| {
+                    evaluator.eval(lhs, input, &mut |evaluator, left| {
+                        if !ops::is_truthy(&left) {
+                            return Ok(());
+                        }
+                        any_truthy = true;
+                        out(evaluator, left)
+                    })
+                })?;
+                if !any_truthy {
+                    self.eval(rhs, input, out)?;
+                }
+            }
+            Expr::Assign(op, lhs, rhs) => self.assign(*op, lhs, rhs, input, out)?,
+            Expr::Array(None) => out(self, JVal::JArray(Vec::new()))?,
+            Expr::Array(Some(items)) => {
+                let items = self.collect(items, input)?;
+                out(self, JVal::JArray(items))?;
+            }
+            Expr::Object(entries) => {
+                let mut objects = vec![JMap::new()];
+                for (key, value) in entries {
+                    let keys = self.collect(key, input)?;
+                    let values = self.collect(value, input)?;
+                    let mut next = Vec::with_capacity(objects.len() * keys.len() * values.len());
+                    for object in &objects {
+                        for key in &keys {
+                            let JVal::JString(key) = key else {
+                                return Err(ops::invalid(format!(
+                                    "Object keys must be strings, not {}",
+                                    ops::describe(key)
+                                ))
+                                .into());
+                            };
+                            for value in &values {
+                                let mut object = object.clone();
+                                object.insert(key.clone(), value.clone());
+                                next.push(object);
+                            }
+                        }
+                    }
+                    objects = next;
+                }
+                for object in objects {
+                    out(self, JVal::JObject(object))?;
+                }
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                return self.each(condition, input, out, |evaluator, condition, out| {
+                    let branch = if ops::is_truthy(&condition) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    evaluator.eval(branch, input, out)
+                });
+            }
+            Expr::Bind { source, name, body } => {
+                return self.each(source, input, out, |evaluator, value, out| {
+                    evaluator
+                        .with_variable(name, value, |evaluator| evaluator.eval(body, input, out))
+                });
+            }
+            Expr::Reduce {
+                source,
+                name,
+                init,
+                update,
+            } => {
+                for mut state in self.collect(init, input)? {
+                    self.eval(source, input, &mut |evaluator, item| {
+                        let mut updates = evaluator.with_variable(name, item, |evaluator| {
+                            evaluator.collect(update, &state)
+                        })?;
+                        state = updates.pop().unwrap_or(JVal::JNull);
+                        Ok(())
+                    })?;
+                    out(self, state)?;
+                }
+            }
+            Expr::Foreach {
+                source,
+                name,
+                init,
+                update,
+                extract,
+            } => {
+                for mut state in self.collect(init, input)? {
+                    self.each(source, input, out, |evaluator, item, out| {
+                        evaluator.with_variable(name, item, |evaluator| {
+                            for updated in evaluator.collect(update, &state)? {
+                                state = updated.clone();
+                                match extract {
+                                    Some(extract) => evaluator.eval(extract, &updated, out)?,
+                                    None => out(evaluator, updated)?,
+                                }
+                            }
+                            Ok(())
+                        })
+                    })?;
+                }
+            }
+            Expr::Call(builtin, args) => builtins::call(self, *builtin, args, input, out)?,
+        }
+        Ok(())
+    }
+
+    /// Runs `then` on each output of `expr` as it is produced.
+    fn each<T>(
+        &mut self,
+        expr: &'a Expr,
+        input: &JVal,
+        out: &mut Sink<'_, 'a, T>,
+        mut then: impl FnMut(&mut Self, JVal, &mut Sink<'_, 'a, T>) -> Result<(), Halt>,
+    ) -> Result<(), Halt> {
+        self.eval(expr, input, &mut |evaluator, value| {
+            then(evaluator, value, out)
+        })
+    }
+
+    /// Runs `run`, which passes outputs to `out`, and returns an error it raises itself
+    /// separately, for `try` and `//` to catch. What `out` fails with is not an error of
+    /// `run`'s, so it stops `run` and is returned as it is.
+    fn guarded<T>(
+        &mut self,
+        out: &mut Sink<'_, 'a, T>,
+        run: impl FnOnce(&mut Self, &mut Sink<'_, 'a, T>) -> Result<(), Halt>,
+    ) -> Result<Result<(), EvalError>, Halt> {
+        let label = self.label();
+        let mut passed_on = None;
+        let result = run(self, &mut |evaluator, value| {
+            out(evaluator, value).map_err(|halt| {
+                passed_on = Some(halt);
+                Halt::Break(label)
+            })
+        });
+        match (passed_on, result) {
+            (Some(halt), _) => Err(halt),
+            (None, Err(Halt::Error(error))) => Ok(Err(error)),
+            (None, result) => result.map(Ok),
+        }
+    }
+
+    /// Runs `run`, which passes outputs to `out`, until `n` of them have been passed on.
+    fn take<T>(
+        &mut self,
+        n: usize,
+        out: &mut Sink<'_, 'a, T>,
+        run: impl FnOnce(&mut Self, &mut Sink<'_, 'a, T>) -> Result<(), Halt>,
+    ) -> Result<(), Halt> {
+        if n == 0 {
+            return Ok(());
+        }
+        let label = self.label();
+        let mut taken = 0;
+        let result = run(self, &mut |evaluator, value| {
+            out(evaluator, value)?;
+            taken += 1;
+            if taken == n {
+                return Err(Halt::Break(label));
+            }
+            Ok(())
+        });
+        match result {
+            Err(Halt::Break(broken)) if broken == label => Ok(()),
+            result => result,
+        }
+    }
+
+    /// A label no other `Halt::Break` uses.
+    fn label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    fn with_variable<R>(
+        &mut self,
+        name: &'a str,
+        value: JVal,
+        body: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.variables.push((name, value));
+        let result = body(self);
+        self.variables.pop();
+        result
+    }
+
+    fn variable(&self, name: &str) -> Result<&JVal, EvalError> {
+        self.variables
+            .iter()
+            .rev()
+            .find(|(bound, _)| *bound == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| ops::invalid(format!("${} is not defined", name)))
+    }
+
+    fn optional_bound(
+        &mut self,
+        bound: Option<&'a Expr>,
+        input: &JVal,
+    ) -> Result<Vec<JVal>, EvalError> {
+        match bound {
+            Some(bound) => self.collect(bound, input),
+            None => Ok(vec![JVal::JNull]),
+        }
+    }
+
+    fn interpolate(
+        &mut self,
+        format: Format,
+        parts: &'a [StringPart],
+        input: &JVal,
+    ) -> Result<Vec<String>, EvalError> {
+        let mut strings = vec![String::new()];
+        for part in parts {
+            match part {
+                StringPart::Literal(text) => {
+                    for string in &mut strings {
+                        string.push_str(text);
+                    }
+                }
+                StringPart::Interpolation(expr) => {
+                    let values = self.collect(expr, input)?;
+                    let mut next = Vec::with_capacity(strings.len() * values.len());
+                    for string in &strings {
+                        for value in &values {
+                            next.push(string.clone() + &ops::format(format, value)?);
+                        }
+                    }
+                    strings = next;
+                }
+            }
+        }
+        Ok(strings)
+    }
+
+    fn collect_paths(
+        &mut self,
+        expr: &'a Expr,
+        input: &JVal,
+    ) -> Result<Vec<(Path, JVal)>, EvalError> {
+        let mut found = Vec::new();
+        self.paths(expr, input, &mut |_, entry| {
+            found.push(entry);
+            Ok(())
+        })
+        .map_err(Halt::into_error)?;
+        Ok(found)
+    }
+
+    /// Like `eval`, but for path expressions such as `.a[0]` or `.[] | select(.x)`:
+    /// passes on where each output was found in `input` along with the output itself.
+    fn paths(
+        &mut self,
+        expr: &'a Expr,
+        input: &JVal,
+        out: &mut Sink<'_, 'a, (Path, JVal)>,
+    ) -> Result<(), Halt> {
+        match expr {
+            Expr::Identity => out(self, (Vec::new(), input.clone()))?,
+            Expr::Index(target, key) => {
+                let keys = self.collect(key, input)?;
+                return self.each_path(target, input, out, |evaluator, (path, value), out| {
+                    for key in &keys {
+                        let found = ops::index(&value, key)?;
+                        out(evaluator, (extend_path(&path, [key.clone()]), found))?;
+                    }
+                    Ok(())
+                });
+            }
+            Expr::Slice(target, start, end) => {
+                let starts = self.optional_bound(start.as_deref(), input)?;
+                let ends = self.optional_bound(end.as_deref(), input)?;
+                return self.each_path(target, input, out, |evaluator, (path, value), out| {
+                    for end in &ends {
+                        for start in &starts {
+                            let found = ops::slice(&value, start, end)?;
+                            let key = ops::slice_key(start, end);
+                            out(evaluator, (extend_path(&path, [key]), found))?;
+                        }
+                    }
+                    Ok(())
+                });
+            }
+            Expr::Iterate(target) => {
+                return self.each_path(target, input, out, |evaluator, (path, value), out| {
+                    for (key, child) in ops::entries(value)? {
+                        out(evaluator, (extend_path(&path, [key]), child))?;
+                    }
+                    Ok(())
+                });
+            }
+            Expr::Try(body, handler) => {
+                let result =
+                    self.guarded(out, |evaluator, out| evaluator.paths(body, input, out))?;
+                if let Err(error) = result {
+                    if let Some(handler) = handler {
+                        return self.paths(handler, &error.value(), out);
+                    }
+                }
+            }
+            Expr::Pipe(lhs, rhs) => {
+                return self.each_path(lhs, input, out, |evaluator, (path, value), out| {
+                    evaluator.paths(rhs, &value, &mut |evaluator, (rest, found)| {
+                        out(evaluator, (extend_path(&path, rest), found))
+                    })
+                });
+            }
+            Expr::Comma(lhs, rhs) => {
+                self.paths(lhs, input, out)?;
+                self.paths(rhs, input, out)?;
+            }
+            Expr::Alternative(lhs, rhs) => {
+                let mut any_truthy = false;
+                let _ = self.guarded(out, |evaluator, out| {
+                    evaluator.paths(lhs, input, &mut |evaluator, (path, value)| {
+                        if !ops::is_truthy(&value) {
+                            return Ok(());
+                        }
+                        any_truthy = true;
+                        out(evaluator, (path, value))
+                    })
+                })?;
+                if !any_truthy {
+                    self.paths(rhs, input, out)?;
+                }
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                return self.each(condition, input, out, |evaluator, condition, out| {
+                    let branch = if ops::is_truthy(&condition) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    evaluator.paths(branch, input, out)
+                });
+            }
+            Expr::Bind { source, name, body } => {
+                return self.each(source, input, out, |evaluator, value, out| {
+                    evaluator
+                        .with_variable(name, value, |evaluator| evaluator.paths(body, input, out))
+                });
+            }
+            Expr::Call(builtin, args) if builtin.yields_paths() => {
+                builtins::call_paths(self, *builtin, args, input, out)?
+            }
+            _ => return Err(self.invalid_path(expr, input).into()),
+        }
+        Ok(())
+    }
+
+    /// Runs `then` on each path and value `expr` finds, as it finds them.
+    fn each_path(
+        &mut self,
+        expr: &'a Expr,
+        input: &JVal,
+        out: &mut Sink<'_, 'a, (Path, JVal)>,
+        mut then: impl FnMut(
+            &mut Self,
+            (Path, JVal),
+            &mut Sink<'_, 'a, (Path, JVal)>,
+        ) -> Result<(), Halt>,
+    ) -> Result<(), Halt> {
+        self.paths(expr, input, &mut |evaluator, entry| {
+            then(evaluator, entry, out)
+        })
+    }
+
+    /// The error for using something other than a path expression where one is needed.
+    fn invalid_path(&mut self, expr: &'a Expr, input: &JVal) -> EvalError {
+        match self.collect(expr, input) {
+            Ok(values) => ops::invalid(format!(
+                "Invalid path expression with result {}",
+                values.first().map_or("empty".to_string(), ops::describe)
+            )),
+            Err(error) => error,
+        }
+    }
+
+    fn assign(
+        &mut self,
+        op: AssignOp,
+        lhs: &'a Expr,
+        rhs: &'a Expr,
+        input: &JVal,
+        out: &mut Sink<'_, 'a, JVal>,
+    ) -> Result<(), Halt> {
+        let paths = self.collect_paths(lhs, input)?;
+        if let AssignOp::Update = op {
+            // `|= empty` deletes, which is done last so array indexes stay valid
+            let mut result = input.clone();
+            let mut deleted = Vec::new();
+            for (path, _) in paths {
+                let current = ops::get_path(&result, &path)?;
+                match self.collect(rhs, &current)?.into_iter().next() {
+                    Some(updated) => ops::set_path(&mut result, &path, updated)?,
+                    None => deleted.push(path),
+                }
+            }
+            ops::delete_paths(&mut result, deleted)?;
+            return out(self, result);
+        }
+
+        for value in self.collect(rhs, input)? {
+            let mut result = input.clone();
+            for (path, _) in &paths {
+                let updated = match op {
+                    AssignOp::Set => value.clone(),
+                    AssignOp::Arithmetic(op) => {
+                        ops::arithmetic(op, ops::get_path(&result, path)?, value.clone())?
+                    }
+                    AssignOp::Alternative => Some(ops::get_path(&result, path)?)
+                        .filter(ops::is_truthy)
+                        .unwrap_or_else(|| value.clone()),
+                    AssignOp::Update => unreachable!("handled above"),
+                };
+                ops::set_path(&mut result, path, updated)?;
+            }
+            out(self, result)?;
+        }
+        Ok(())
+    }
+}
+
+fn extend_path(path: &[JVal], rest: impl IntoIterator<Item = JVal>) -> Path {
+    let mut path = path.to_vec();
+    path.extend(rest);
+    path
+}
@@ -0,0 +1,1061 @@
+//! The functions a filter can call, such as `select`, `map` and `keys`.
+
+use regex::{Captures, Regex, RegexBuilder};
+
+use super::ops::{self, describe, invalid, is_truthy, type_name};
+use super::{ArithOp, AssignOp, CompareOp, Evaluator, Expr, Halt, Path, Sink};
+use crate::error::EvalError;
+use crate::map::JMap;
+use crate::parser::{self, JVal};
+use crate::ser;
+
+/// The functions implemented natively. Their arguments are filters, evaluated with
+/// the call's input unless noted otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Builtin {
+    Empty,
+    /// `error(message)`
+    Error,
+    Not,
+    Length,
+    Utf8ByteLength,
+    Type,
+    Keys,
+    KeysUnsorted,
+    Has,
+    Contains,
+    Add,
+    /// `any(generator; condition)`
+    Any,
+    /// `all(generator; condition)`
+    All,
+    /// `range(from; upto)`
+    Range,
+    Floor,
+    Ceil,
+    Round,
+    Sqrt,
+    Abs,
+    ToString,
+    ToNumber,
+    ToJson,
+    FromJson,
+    AsciiDowncase,
+    AsciiUpcase,
+    Explode,
+    Implode,
+    Trim,
+    Ltrim,
+    Rtrim,
+    Ltrimstr,
+    Rtrimstr,
+    StartsWith,
+    EndsWith,
+    Split,
+    Join,
+    /// `test(regex; flags)`
+    Test,
+    /// `capture(regex; flags)`
+    Capture,
+    /// `scan(regex; flags)`
+    Scan,
+    /// `sub(regex; replacement; flags)`, where `replacement` runs against an object
+    /// of the named captures.
+    Sub,
+    Reverse,
+    Sort,
+    SortBy,
+    GroupBy,
+    Unique,
+    UniqueBy,
+    Min,
+    Max,
+    MinBy,
+    MaxBy,
+    /// `flatten(depth)`
+    Flatten,
+    ToEntries,
+    FromEntries,
+    Select,
+    /// `recurse(f)`
+    Recurse,
+    Walk,
+    /// `paths(f)`
+    Paths,
+    Path,
+    GetPath,
+    SetPath,
+    DelPaths,
+    /// `limit(n; f)`
+    Limit,
+    /// `last(f)`
+    Last,
+    Env,
+}
+
+impl Builtin {
+    /// Whether the builtin can appear in a path expression, e.g. `del(.[] | select(.x))`.
+    pub(super) fn yields_paths(self) -> bool {
+        matches!(
+            self,
+            Builtin::Empty
+                | Builtin::Error
+                | Builtin::Select
+                | Builtin::Recurse
+                | Builtin::GetPath
+                | Builtin::Limit
+                | Builtin::Last
+        )
+    }
+}
+
+/// Looks up the function `name` taking `args.len()` arguments. Functions jq defines in
+/// terms of others are expanded here the same way.
+pub(super) fn resolve(name: &str, mut args: Vec<Expr>) -> Option<Expr> {
+    use Builtin::*;
+
+    let expanded = match (name, args.len()) {
+        ("map", 1) => Expr::Array(Some(Box::new(pipe(iterate(), args.remove(0))))),
+        ("map_values", 1) => Expr::Assign(
+            AssignOp::Update,
+            Box::new(iterate()),
+            Box::new(args.remove(0)),
+        ),
+        ("with_entries", 1) => pipe(
+            Expr::Call(ToEntries, Vec::new()),
+            pipe(resolve("map", args)?, Expr::Call(FromEntries, Vec::new())),
+        ),
+        ("del", 1) => Expr::Call(
+            DelPaths,
+            vec![Expr::Array(Some(Box::new(Expr::Call(Path, args))))],
+        ),
+        ("first", 0) => index(0.0),
+        ("last", 0) => index(-1.0),
+        ("first", 1) => Expr::Call(Limit, vec![number(1.0), args.remove(0)]),
+        ("recurse", 0) => Expr::Call(Recurse, vec![Expr::Try(Box::new(iterate()), None)]),
+        ("paths", 0) => pipe(
+            Expr::Call(Path, vec![resolve("recurse", args)?]),
+            select(Expr::Compare(
+                CompareOp::Gt,
+                Box::new(Expr::Call(Length, Vec::new())),
+                Box::new(number(0.0)),
+            )),
+        ),
+        ("leaf_paths", 0) => Expr::Call(Paths, vec![resolve("scalars", args)?]),
+        ("values", 0) => select(compare(CompareOp::Ne, Expr::Identity, JVal::JNull)),
+        ("nulls", 0) => select(compare(CompareOp::Eq, Expr::Identity, JVal::JNull)),
+        ("booleans", 0) => select(type_is(CompareOp::Eq, "boolean")),
+        ("numbers", 0) => select(type_is(CompareOp::Eq, "number")),
+        ("strings", 0) => select(type_is(CompareOp::Eq, "string")),
+        ("arrays", 0) => select(type_is(CompareOp::Eq, "array")),
+        ("objects", 0) => select(type_is(CompareOp::Eq, "object")),
+        ("iterables", 0) => select(Expr::Or(
+            Box::new(type_is(CompareOp::Eq, "array")),
+            Box::new(type_is(CompareOp::Eq, "object")),
+        )),
+        ("scalars", 0) => select(Expr::And(
+            Box::new(type_is(CompareOp::Ne, "array")),
+            Box::new(type_is(CompareOp::Ne, "object")),
+        )),
+        ("any" | "all", 0 | 1) => {
+            let condition = args.pop().unwrap_or(Expr::Identity);
+            let builtin = if name == "any" { Any } else { All };
+            Expr::Call(builtin, vec![iterate(), condition])
+        }
+        ("range", 1) => Expr::Call(Range, vec![number(0.0), args.remove(0)]),
+        ("test" | "capture" | "scan", 1) | ("sub", 2) => {
+            args.push(Expr::Literal(JVal::JNull));
+            return resolve(name, args);
+        }
+        ("gsub", 2) => {
+            args.push(Expr::Literal(JVal::JString("g".to_string())));
+            Expr::Call(Sub, args)
+        }
+        ("gsub", 3) => {
+            let flags = args.pop()?;
+            args.push(Expr::Arithmetic(
+                ArithOp::Add,
+                Box::new(flags),
+                Box::new(Expr::Literal(JVal::JString("g".to_string()))),
+            ));
+            Expr::Call(Sub, args)
+        }
+        ("flatten", 0) => Expr::Call(Flatten, vec![number(1e9)]),
+        ("error", 0) => Expr::Call(Error, vec![Expr::Identity]),
+        _ => {
+            let builtin = match (name, args.len()) {
+                ("empty", 0) => Empty,
+                ("error", 1) => Error,
+                ("not", 0) => Not,
+                ("length", 0) => Length,
+                ("utf8bytelength", 0) => Utf8ByteLength,
+                ("type", 0) => Type,
+                ("keys", 0) => Keys,
+                ("keys_unsorted", 0) => KeysUnsorted,
+                ("has", 1) => Has,
+                ("contains", 1) => Contains,
+                ("add", 0) => Add,
+                ("any", 2) => Any,
+                ("all", 2) => All,
+                ("range", 2) => Range,
+                ("floor", 0) => Floor,
+                ("ceil", 0) => Ceil,
+                ("round", 0) => Round,
+                ("sqrt", 0) => Sqrt,
+                ("abs", 0) => Abs,
+                ("tostring", 0) => ToString,
+                ("tonumber", 0) => ToNumber,
+                ("tojson", 0) => ToJson,
+                ("fromjson", 0) => FromJson,
+                ("ascii_downcase", 0) => AsciiDowncase,
+                ("ascii_upcase", 0) => AsciiUpcase,
+                ("explode", 0) => Explode,
+                ("implode", 0) => Implode,
+                ("trim", 0) => Trim,
+                ("ltrim", 0) => Ltrim,
+                ("rtrim", 0) => Rtrim,
+                ("ltrimstr", 1) => Ltrimstr,
+                ("rtrimstr", 1) => Rtrimstr,
+                ("startswith", 1) => StartsWith,
+                ("endswith", 1) => EndsWith,
+                ("split", 1) => Split,
+                ("join", 1) => Join,
+                ("test", 2) => Test,
+                ("capture", 2) => Capture,
+                ("scan", 2) => Scan,
+                ("sub", 3) => Sub,
+                ("reverse", 0) => Reverse,
+                ("sort", 0) => Sort,
+                ("sort_by", 1) => SortBy,
+                ("group_by", 1) => GroupBy,
+                ("unique", 0) => Unique,
+                ("unique_by", 1) => UniqueBy,
+                ("min", 0) => Min,
+                ("max", 0) => Max,
+                ("min_by", 1) => MinBy,
+                ("max_by", 1) => MaxBy,
+                ("flatten", 1) => Flatten,
+                ("to_entries", 0) => ToEntries,
+                ("from_entries", 0) => FromEntries,
+                ("select", 1) => Select,
+                ("recurse", 1) => Recurse,
+                ("walk", 1) => Walk,
+                ("paths", 1) => Paths,
+                ("path", 1) => Path,
+                ("getpath", 1) => GetPath,
+                ("setpath", 2) => SetPath,
+                ("delpaths", 1) => DelPaths,
+                ("limit", 2) => Limit,
+                ("last", 1) => Last,
+                ("env", 0) => Env,
+                _ => return None,
+            };
+            Expr::Call(builtin, args)
+        }
+    };
+    Some(expanded)
+}
+
+fn pipe(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Pipe(Box::new(lhs), Box::new(rhs))
+}
+
+fn iterate() -> Expr {
+    Expr::Iterate(Box::new(Expr::Identity))
+}
+
+fn number(n: f64) -> Expr {
+    Expr::Literal(JVal::from(n))
+}
+
+fn index(n: f64) -> Expr {
+    Expr::Index(Box::new(Expr::Identity), Box::new(number(n)))
+}
+
+fn select(condition: Expr) -> Expr {
+    Expr::Call(Builtin::Select, vec![condition])
+}
+
+fn compare(op: CompareOp, lhs: Expr, rhs: JVal) -> Expr {
+    Expr::Compare(op, Box::new(lhs), Box::new(Expr::Literal(rhs)))
+}
+
+fn type_is(op: CompareOp, name: &str) -> Expr {
+    compare(
+        op,
+        Expr::Call(Builtin::Type, Vec::new()),
+        JVal::JString(name.to_string()),
+    )
+}
+
+pub(super) fn call<'a>(
+    evaluator: &mut Evaluator<'a>,
+    builtin: Builtin,
+    args: &'a [Expr],
+    input: &JVal,
+    out: &mut Sink<'_, 'a, JVal>,
+) -> Result<(), Halt> {
+    use Builtin::*;
+
+    match builtin {
+        Empty => {}
+        Error => {
+            if let Some(value) = evaluator.collect(&args[0], input)?.into_iter().next() {
+                return Err(EvalError::Raised { value }.into());
+            }
+        }
+        Select => {
+            return evaluator.each(&args[0], input, out, |evaluator, condition, out| {
+                if is_truthy(&condition) {
+                    out(evaluator, input.clone())?;
+                }
+                Ok(())
+            });
+        }
+        Recurse => {
+            // Depth first, each value before its children
+            let mut stack = vec![input.clone()];
+            while let Some(value) = stack.pop() {
+                out(evaluator, value.clone())?;
+                let children = evaluator.collect(&args[0], &value)?;
+                stack.extend(children.into_iter().rev());
+            }
+        }
+        Walk => {
+            for walked in walk(evaluator, &args[0], input.clone())? {
+                out(evaluator, walked)?;
+            }
+        }
+        Any | All => {
+            let any = builtin == Any;
+            let mut result = !any;
+            'items: for item in evaluator.collect(&args[0], input)? {
+                for condition in evaluator.collect(&args[1], &item)? {
+                    if is_truthy(&condition) == any {
+                        result = any;
+                        break 'items;
+                    }
+                }
+            }
+            out(evaluator, JVal::JBool(result))?;
+        }
+        Range => {
+            let uptos = evaluator.collect(&args[1], input)?;
+            for from in evaluator.collect(&args[0], input)? {
+                for upto in &uptos {
+                    match (&from, upto) {
+                        (JVal::JInt(from), JVal::JInt(upto)) => {
+                            for n in *from..*upto {
+                                out(evaluator, JVal::JInt(n))?;
+                            }
+                        }
+                        (from, upto) if from.is_number() && upto.is_number() => {
+                            let (mut n, upto) = (ops::float(from), ops::float(upto));
+                            while n < upto {
+                                out(evaluator, JVal::from(n))?;
+                                n += 1.0;
+                            }
+                        }
+                        _ => return Err(invalid("Range bounds must be numeric".to_string()).into()),
+                    }
+                }
+            }
+        }
+        Limit => {
+            for limit in evaluator.collect(&args[0], input)? {
+                if !limit.is_number() {
+                    return Err(invalid(format!(
+                        "Invalid limit {}, expected a number",
+                        describe(&limit)
+                    ))
+                    .into());
+                }
+                // Anything after the last value taken is never evaluated, errors included
+                match limit_count(&limit) {
+                    Some(n) => evaluator.take(n, out, |evaluator, out| {
+                        evaluator.eval(&args[1], input, out)
+                    })?,
+                    None => evaluator.eval(&args[1], input, out)?,
+                }
+            }
+        }
+        Last => {
+            if let Some(last) = evaluator.collect(&args[0], input)?.pop() {
+                out(evaluator, last)?;
+            }
+        }
+        SortBy | GroupBy | UniqueBy | MinBy | MaxBy => {
+            let keyed = sorted_by_key(evaluator, &args[0], input)?;
+            let result = match builtin {
+                SortBy => JVal::JArray(keyed.into_iter().map(|(_, item)| item).collect()),
+                GroupBy => JVal::JArray(group(keyed).into_iter().map(JVal::JArray).collect()),
+                UniqueBy => JVal::JArray(
+                    group(keyed)
+                        .into_iter()
+                        .filter_map(|group| group.into_iter().next())
+                        .collect(),
+                ),
+                MinBy => keyed
+                    .into_iter()
+                    .next()
+                    .map_or(JVal::JNull, |(_, item)| item),
+                _ => keyed
+                    .into_iter()
+                    .last()
+                    .map_or(JVal::JNull, |(_, item)| item),
+            };
+            out(evaluator, result)?;
+        }
+        Test | Capture | Scan | Sub => {
+            let JVal::JString(string) = input else {
+                return Err(invalid(format!(
+                    "{} cannot be matched, as it is not a string",
+                    describe(input)
+                ))
+                .into());
+            };
+            let flags =
+                evaluator.collect(args.last().expect("regex builtins take flags"), input)?;
+            for pattern in evaluator.collect(&args[0], input)? {
+                for flags in &flags {
+                    let regex = RegexFlags::compile(&pattern, flags)?;
+                    match builtin {
+                        Test => out(evaluator, JVal::JBool(regex.regex.is_match(string)))?,
+                        Capture => {
+                            for captures in regex.matches(string) {
+                                out(evaluator, named_captures(&regex.regex, &captures))?;
+                            }
+                        }
+                        Scan => {
+                            for captures in regex.regex.captures_iter(string) {
+                                out(evaluator, scanned(&captures))?;
+                            }
+                        }
+                        _ => {
+                            for replaced in substitute(evaluator, &args[1], string, &regex)? {
+                                out(evaluator, JVal::JString(replaced))?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Path => {
+            return evaluator.paths(&args[0], input, &mut |evaluator, (path, _)| {
+                out(evaluator, JVal::JArray(path))
+            });
+        }
+        Paths => {
+            for (path, value) in descendants(input).into_iter().skip(1) {
+                for condition in evaluator.collect(&args[0], &value)? {
+                    if is_truthy(&condition) {
+                        out(evaluator, JVal::JArray(path.clone()))?;
+                    }
+                }
+            }
+        }
+        GetPath => {
+            for path in evaluator.collect(&args[0], input)? {
+                out(evaluator, ops::get_path(input, &path_array(path)?)?)?;
+            }
+        }
+        SetPath => {
+            let paths = evaluator.collect(&args[0], input)?;
+            for value in evaluator.collect(&args[1], input)? {
+                for path in &paths {
+                    let mut result = input.clone();
+                    ops::set_path(&mut result, &path_array(path.clone())?, value.clone())?;
+                    out(evaluator, result)?;
+                }
+            }
+        }
+        DelPaths => {
+            for paths in evaluator.collect(&args[0], input)? {
+                let paths = path_array(paths)?
+                    .into_iter()
+                    .map(path_array)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut result = input.clone();
+                ops::delete_paths(&mut result, paths)?;
+                out(evaluator, result)?;
+            }
+        }
+        _ if args.is_empty() => out(evaluator, call_simple(builtin, input)?)?,
+        _ => {
+            for arg in evaluator.collect(&args[0], input)? {
+                out(evaluator, call_with_value(builtin, input, &arg)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs a builtin listed in `Builtin::yields_paths` as a path expression.
+pub(super) fn call_paths<'a>(
+    evaluator: &mut Evaluator<'a>,
+    builtin: Builtin,
+    args: &'a [Expr],
+    input: &JVal,
+    out: &mut Sink<'_, 'a, (Path, JVal)>,
+) -> Result<(), Halt> {
+    match builtin {
+        Builtin::Select => {
+            return evaluator.each(&args[0], input, out, |evaluator, condition, out| {
+                if is_truthy(&condition) {
+                    out(evaluator, (Vec::new(), input.clone()))?;
+                }
+                Ok(())
+            });
+        }
+        Builtin::Recurse => {
+            let mut stack = vec![(Vec::new(), input.clone())];
+            while let Some((path, value)) = stack.pop() {
+                out(evaluator, (path.clone(), value.clone()))?;
+                let children = evaluator.collect_paths(&args[0], &value)?;
+                stack.extend(
+                    children
+                        .into_iter()
+                        .rev()
+                        .map(|(rest, child)| (super::extend_path(&path, rest), child)),
+                );
+            }
+        }
+        Builtin::GetPath => {
+            for path in evaluator.collect(&args[0], input)? {
+                let path = path_array(path)?;
+                let value = ops::get_path(input, &path)?;
+                out(evaluator, (path, value))?;
+            }
+        }
+        Builtin::Limit => {
+            for limit in evaluator.collect(&args[0], input)? {
+                match limit_count(&limit) {
+                    Some(n) => evaluator.take(n, out, |evaluator, out| {
+                        evaluator.paths(&args[1], input, out)
+                    })?,
+                    None => evaluator.paths(&args[1], input, out)?,
+                }
+            }
+        }
+        Builtin::Last => {
+            if let Some(last) = evaluator.collect_paths(&args[0], input)?.pop() {
+                out(evaluator, last)?;
+            }
+        }
+        // `empty` has no paths and `error` raises the same way it always does
+        _ => call(evaluator, builtin, args, input, &mut |_, _| Ok(()))?,
+    }
+    Ok(())
+}
+
+/// How many outputs `limit(n; f)` takes, or `None` for a negative `n`, which jq
+/// passes every output of `f` through for. A fractional `n` is rounded up, as jq
+/// stops once at least `n` outputs have been taken.
+fn limit_count(n: &JVal) -> Option<usize> {
+    let n = n.as_f64().unwrap_or(0.0);
+    if n < 0.0 {
+        return None;
+    }
+    Some(n.ceil() as usize)
+}
+
+/// Builtins taking no arguments.
+fn call_simple(builtin: Builtin, input: &JVal) -> Result<JVal, EvalError> {
+    use Builtin::*;
+
+    let value = match (builtin, input) {
+        (Not, _) => JVal::JBool(!is_truthy(input)),
+        (Type, _) => JVal::JString(type_name(input).to_string()),
+        (Length, JVal::JNull) => JVal::from(0),
+        (Length, n) if n.is_number() => abs(n),
+        (Length, JVal::JString(string)) => JVal::from(string.chars().count()),
+        (Length, JVal::JArray(items)) => JVal::from(items.len()),
+        (Length, JVal::JObject(map)) => JVal::from(map.len()),
+        (Utf8ByteLength, JVal::JString(string)) => JVal::from(string.len()),
+        (Keys, JVal::JObject(map)) => ops::sorted_keys(map)
+            .into_iter()
+            .map(|key| JVal::JString(key.clone()))
+            .collect(),
+        (KeysUnsorted, JVal::JObject(map)) => {
+            map.keys().map(|key| JVal::JString(key.clone())).collect()
+        }
+        (Keys | KeysUnsorted, JVal::JArray(items)) => (0..items.len()).map(JVal::from).collect(),
+        (Add, JVal::JNull) => JVal::JNull,
+        (Add, JVal::JArray(_) | JVal::JObject(_)) => ops::iterate(input.clone())?
+            .into_iter()
+            .try_fold(JVal::JNull, |sum, item| {
+                ops::arithmetic(ArithOp::Add, sum, item)
+            })?,
+        (Floor | Ceil | Round, JVal::JInt(_) | JVal::JUint(_)) => input.clone(),
+        (Floor, n) if n.is_number() => JVal::from(ops::float(n).floor()),
+        (Ceil, n) if n.is_number() => JVal::from(ops::float(n).ceil()),
+        (Round, n) if n.is_number() => JVal::from(ops::float(n).round()),
+        (Sqrt, n) if n.is_number() => JVal::from(ops::float(n).sqrt()),
+        (Abs, n) if n.is_number() => abs(n),
+        (ToString, _) => JVal::JString(ops::to_text(input)),
+        (ToJson, _) => JVal::JString(ser::to_string(input)),
+        (ToNumber, n) if n.is_number() => input.clone(),
+        (ToNumber, JVal::JString(string)) => match parser::from_str(string) {
+            Ok(number) if number.is_number() => number,
+            _ => {
+                return Err(invalid(format!(
+                    "{} cannot be parsed as a number",
+                    describe(input)
+                )))
+            }
+        },
+        (FromJson, JVal::JString(string)) => parser::from_str(string)
+            .map_err(|e| invalid(format!("{} (while parsing '{}')", e, string)))?,
+        (AsciiDowncase, JVal::JString(string)) => JVal::JString(string.to_ascii_lowercase()),
+        (AsciiUpcase, JVal::JString(string)) => JVal::JString(string.to_ascii_uppercase()),
+        (Trim, JVal::JString(string)) => JVal::JString(string.trim().to_string()),
+        (Ltrim, JVal::JString(string)) => JVal::JString(string.trim_start().to_string()),
+        (Rtrim, JVal::JString(string)) => JVal::JString(string.trim_end().to_string()),
+        (Explode, JVal::JString(string)) => {
+            string.chars().map(|ch| JVal::from(ch as u32)).collect()
+        }
+        (Implode, JVal::JArray(items)) => JVal::JString(
+            items
+                .iter()
+                .map(|item| {
+                    item.as_u64()
+                        .and_then(|n| u32::try_from(n).ok())
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| invalid(format!("Invalid codepoint {}", describe(item))))
+                })
+                .collect::<Result<String, _>>()?,
+        ),
+        (Reverse, JVal::JNull) => JVal::JArray(Vec::new()),
+        (Reverse, JVal::JString(string)) => JVal::JString(string.chars().rev().collect()),
+        (Reverse, JVal::JArray(items)) => items.iter().rev().cloned().collect(),
+        (Sort | Unique | Min | Max, JVal::JArray(items)) => {
+            let mut items = items.clone();
+            items.sort_by(ops::compare);
+            match builtin {
+                Sort => JVal::JArray(items),
+                Unique => {
+                    items.dedup_by(|a, b| ops::compare(a, b).is_eq());
+                    JVal::JArray(items)
+                }
+                Min => items.into_iter().next().unwrap_or(JVal::JNull),
+                _ => items.pop().unwrap_or(JVal::JNull),
+            }
+        }
+        (ToEntries, JVal::JObject(map)) => map
+            .iter()
+            .map(|(key, value)| {
+                JVal::from_iter([
+                    ("key", JVal::JString(key.clone())),
+                    ("value", value.clone()),
+                ])
+            })
+            .collect(),
+        (ToEntries, JVal::JArray(items)) => items
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                JVal::from_iter([("key", JVal::from(index)), ("value", value.clone())])
+            })
+            .collect(),
+        (FromEntries, JVal::JArray(items)) => {
+            JVal::JObject(items.iter().map(from_entry).collect::<Result<JMap, _>>()?)
+        }
+        (Env, _) => std::env::vars().collect(),
+        _ => return Err(unsupported(builtin, input)),
+    };
+    Ok(value)
+}
+
+/// Builtins taking one argument, called once for each value the argument outputs.
+fn call_with_value(builtin: Builtin, input: &JVal, arg: &JVal) -> Result<JVal, EvalError> {
+    use Builtin::*;
+
+    let value = match (builtin, input, arg) {
+        (Has, JVal::JObject(map), JVal::JString(key)) => JVal::JBool(map.contains_key(key)),
+        (Has, JVal::JArray(items), n) if n.is_number() => {
+            let n = ops::float(n);
+            JVal::JBool(n >= 0.0 && n < items.len() as f64)
+        }
+        (Has, _, _) => {
+            return Err(invalid(format!(
+                "Cannot check whether {} has a {} key",
+                type_name(input),
+                type_name(arg)
+            )))
+        }
+        (Contains, _, _) => JVal::JBool(contains(input, arg)?),
+        (Ltrimstr, JVal::JString(string), JVal::JString(prefix)) => JVal::JString(
+            string
+                .strip_prefix(prefix.as_str())
+                .unwrap_or(string)
+                .to_string(),
+        ),
+        (Rtrimstr, JVal::JString(string), JVal::JString(suffix)) => JVal::JString(
+            string
+                .strip_suffix(suffix.as_str())
+                .unwrap_or(string)
+                .to_string(),
+        ),
+        (Ltrimstr | Rtrimstr, _, _) => input.clone(),
+        (StartsWith, JVal::JString(string), JVal::JString(prefix)) => {
+            JVal::JBool(string.starts_with(prefix.as_str()))
+        }
+        (EndsWith, JVal::JString(string), JVal::JString(suffix)) => {
+            JVal::JBool(string.ends_with(suffix.as_str()))
+        }
+        (StartsWith | EndsWith, _, _) => {
+            return Err(invalid(format!(
+                "{}() requires string inputs",
+                if builtin == StartsWith {
+                    "startswith"
+                } else {
+                    "endswith"
+                }
+            )))
+        }
+        (Split, JVal::JString(string), JVal::JString(separator)) => ops::split(string, separator)
+            .into_iter()
+            .map(JVal::JString)
+            .collect(),
+        (Split, _, _) => {
+            return Err(invalid(
+                "split input and separator must be strings".to_string(),
+            ))
+        }
+        (Join, JVal::JArray(items), JVal::JString(separator)) => JVal::JString(
+            items
+                .iter()
+                .map(|item| match item {
+                    JVal::JNull => Ok(String::new()),
+                    JVal::JString(string) => Ok(string.clone()),
+                    JVal::JBool(_) | JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_) => {
+                        Ok(ser::to_string(item))
+                    }
+                    other => Err(invalid(format!("Cannot join with {}", describe(other)))),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(separator),
+        ),
+        (Flatten, JVal::JArray(items), depth) if depth.is_number() && ops::float(depth) >= 0.0 => {
+            let mut flattened = Vec::new();
+            flatten(items, ops::float(depth) as usize, &mut flattened);
+            JVal::JArray(flattened)
+        }
+        (Flatten, _, depth) if ops::float(depth) < 0.0 => {
+            return Err(invalid("flatten depth must not be negative".to_string()))
+        }
+        _ => return Err(unsupported(builtin, input)),
+    };
+    Ok(value)
+}
+
+fn unsupported(builtin: Builtin, input: &JVal) -> EvalError {
+    use Builtin::*;
+
+    let message = match builtin {
+        Length => "has no length",
+        Keys | KeysUnsorted | ToEntries => "has no keys",
+        Add => "cannot be added up",
+        Floor | Ceil | Round | Sqrt | Abs => "is not a number",
+        ToNumber | FromJson | AsciiDowncase | AsciiUpcase | Explode | Trim | Ltrim | Rtrim
+        | Utf8ByteLength => "is not a string",
+        Implode | Sort | Unique | Min | Max | FromEntries | Flatten => "is not an array",
+        Reverse => "cannot be reversed",
+        Join => "cannot be joined",
+        _ => "is not supported here",
+    };
+    invalid(format!("{} {}", describe(input), message))
+}
+
+/// Whether `a` contains `b`: substrings for strings, and recursively for arrays and
+/// objects, where every part of `b` must be contained in some part of `a`.
+fn contains(a: &JVal, b: &JVal) -> Result<bool, EvalError> {
+    match (a, b) {
+        (JVal::JObject(a), JVal::JObject(b)) => {
+            for (key, b) in b {
+                match a.get(key) {
+                    Some(a) if contains(a, b)? => {}
+                    _ => return Ok(false),
+                }
+            }
+            Ok(true)
+        }
+        (JVal::JArray(a), JVal::JArray(b)) => {
+            'outer: for b in b {
+                for a in a {
+                    if contains(a, b)? {
+                        continue 'outer;
+                    }
+                }
+                return Ok(false);
+            }
+            Ok(true)
+        }
+        (JVal::JString(a), JVal::JString(b)) => Ok(a.contains(b.as_str())),
+        _ if type_name(a) == type_name(b) => Ok(a == b),
+        _ => Err(invalid(format!(
+            "{} and {} cannot have their containment checked",
+            describe(a),
+            describe(b)
+        ))),
+    }
+}
+
+/// The absolute value of a number, keeping every digit of a float.
+fn abs(num: &JVal) -> JVal {
+    match num {
+        JVal::JInt(n) => JVal::from(i128::from(*n).abs()),
+        JVal::JFloat(n) if n.as_str().starts_with('-') => JVal::JFloat(-n.clone()),
+        _ => num.clone(),
+    }
+}
+
+fn flatten(items: &[JVal], depth: usize, out: &mut Vec<JVal>) {
+    for item in items {
+        match item {
+            JVal::JArray(inner) if depth > 0 => flatten(inner, depth - 1, out),
+            other => out.push(other.clone()),
+        }
+    }
+}
+
+/// Reads a `{"key", "value"}` entry, also accepting the `k`, `name`, `v` and other
+/// spellings `from_entries` has always taken.
+fn from_entry(entry: &JVal) -> Result<(String, JVal), EvalError> {
+    let JVal::JObject(map) = entry else {
+        return Err(invalid(format!(
+            "{} is not a valid entry, expected an object",
+            describe(entry)
+        )));
+    };
+    let key = ["key", "k", "name", "Name", "K", "Key"]
+        .iter()
+        .filter_map(|name| map.get(name))
+        .find(|key| !key.is_null())
+        .unwrap_or(&JVal::JNull);
+    let value = ["value", "v", "Value"]
+        .iter()
+        .find_map(|name| map.get(name))
+        .cloned()
+        .unwrap_or(JVal::JNull);
+    Ok((ops::to_text(key), value))
+}
+
+fn path_array(path: JVal) -> Result<Vec<JVal>, EvalError> {
+    match path {
+        JVal::JArray(path) => Ok(path),
+        other => Err(invalid(format!(
+            "Path must be specified as an array, not {}",
+            describe(&other)
+        ))),
+    }
+}
+
+/// Every value in `value` along with its path, depth first and starting with `value`.
+fn descendants(value: &JVal) -> Vec<(Path, JVal)> {
+    let mut found = Vec::new();
+    let mut stack = vec![(Vec::new(), value.clone())];
+    while let Some((path, value)) = stack.pop() {
+        if let Ok(children) = ops::entries(value.clone()) {
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|(key, child)| (super::extend_path(&path, [key]), child)),
+            );
+        }
+        found.push((path, value));
+    }
+    found
+}
+
+/// Applies `f` bottom up: to every element and member first, then to the result.
+fn walk<'a>(
+    evaluator: &mut Evaluator<'a>,
+    f: &'a Expr,
+    value: JVal,
+) -> Result<Vec<JVal>, EvalError> {
+    let value = match value {
+        JVal::JArray(items) => {
+            let mut walked = Vec::with_capacity(items.len());
+            for item in items {
+                walked.extend(walk(evaluator, f, item)?);
+            }
+            JVal::JArray(walked)
+        }
+        JVal::JObject(map) => {
+            let mut walked = JMap::with_capacity(map.len());
+            for (key, value) in map {
+                if let Some(value) = walk(evaluator, f, value)?.into_iter().next() {
+                    walked.insert(key, value);
+                }
+            }
+            JVal::JObject(walked)
+        }
+        other => other,
+    };
+    evaluator.collect(f, &value)
+}
+
+/// Pairs each element of the input array with the outputs of `f` for it, sorted by
+/// those keys. The sort is stable.
+fn sorted_by_key<'a>(
+    evaluator: &mut Evaluator<'a>,
+    f: &'a Expr,
+    input: &JVal,
+) -> Result<Vec<(JVal, JVal)>, EvalError> {
+    let JVal::JArray(items) = input else {
+        return Err(invalid(format!(
+            "{} cannot be sorted, as it is not an array",
+            describe(input)
+        )));
+    };
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+        let key = JVal::JArray(evaluator.collect(f, item)?);
+        keyed.push((key, item.clone()));
+    }
+    keyed.sort_by(|(a, _), (b, _)| ops::compare(a, b));
+    Ok(keyed)
+}
+
+/// Splits sorted `(key, item)` pairs into runs of items with equal keys.
+fn group(keyed: Vec<(JVal, JVal)>) -> Vec<Vec<JVal>> {
+    let mut groups: Vec<(JVal, Vec<JVal>)> = Vec::new();
+    for (key, item) in keyed {
+        match groups.last_mut() {
+            Some((last, group)) if ops::compare(last, &key).is_eq() => group.push(item),
+            _ => groups.push((key, vec![item])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// A compiled regex along with the jq flags that are not part of the pattern itself.
+struct RegexFlags {
+    regex: Regex,
+    global: bool,
+    skip_empty: bool,
+}
+
+impl RegexFlags {
+    fn compile(pattern: &JVal, flags: &JVal) -> Result<RegexFlags, EvalError> {
+        let JVal::JString(pattern) = pattern else {
+            return Err(invalid(format!(
+                "{} cannot be matched, as it is not a string",
+                describe(pattern)
+            )));
+        };
+        let flags = match flags {
+            JVal::JNull => "",
+            JVal::JString(flags) => flags.as_str(),
+            other => return Err(invalid(format!("{} is not a string", describe(other)))),
+        };
+
+        let mut builder = RegexBuilder::new(pattern);
+        let mut compiled = RegexFlags {
+            regex: Regex::new("").expect("the empty pattern is valid"),
+            global: false,
+            skip_empty: false,
+        };
+        for flag in flags.chars() {
+            match flag {
+                'g' => compiled.global = true,
+                'n' => compiled.skip_empty = true,
+                'i' => {
+                    builder.case_insensitive(true);
+                }
+                'x' => {
+                    builder.ignore_whitespace(true);
+                }
+                's' => {
+                    builder.dot_matches_new_line(true);
+                }
+                _ => return Err(invalid(format!("{} is not a valid modifier string", flags))),
+            }
+        }
+        compiled.regex = builder
+            .build()
+            .map_err(|e| invalid(format!("{} (at regex '{}')", e, pattern)))?;
+        Ok(compiled)
+    }
+
+    /// The first match, or every match with the `g` flag.
+    fn matches<'s>(&self, string: &'s str) -> Vec<Captures<'s>> {
+        let matches = self
+            .regex
+            .captures_iter(string)
+            .filter(|captures| !(self.skip_empty && captures[0].is_empty()));
+        if self.global {
+            matches.collect()
+        } else {
+            matches.take(1).collect()
+        }
+    }
+}
+
+fn named_captures(regex: &Regex, captures: &Captures) -> JVal {
+    JVal::JObject(
+        regex
+            .capture_names()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let value = captures.get(i).map_or(JVal::JNull, |found| {
+                    JVal::JString(found.as_str().to_string())
+                });
+                name.map(|name| (name.to_string(), value))
+            })
+            .collect(),
+    )
+}
+
+/// What `scan` outputs for a match: the matched text, or an array of the groups if
+/// the regex has any.
+fn scanned(captures: &Captures) -> JVal {
+    if captures.len() == 1 {
+        return JVal::JString(captures[0].to_string());
+    }
+    captures
+        .iter()
+        .skip(1)
+        .map(|group| {
+            group.map_or(JVal::JNull, |found| {
+                JVal::JString(found.as_str().to_string())
+            })
+        })
+        .collect()
+}
+
+/// Replaces the matches in `string`. A replacement that outputs several strings gives
+/// one result for each combination.
+fn substitute<'a>(
+    evaluator: &mut Evaluator<'a>,
+    replacement: &'a Expr,
+    string: &str,
+    regex: &RegexFlags,
+) -> Result<Vec<String>, EvalError> {
+    let mut results = vec![String::new()];
+    let mut last = 0;
+    for captures in regex.matches(string) {
+        let found = captures.get(0).expect("group 0 is the whole match");
+        let between = &string[last..found.start()];
+        let mut replaced = Vec::new();
+        for text in evaluator.collect(replacement, &named_captures(&regex.regex, &captures))? {
+            let JVal::JString(text) = text else {
+                return Err(invalid(format!(
+                    "{} cannot be added to a string",
+                    describe(&text)
+                )));
+            };
+            for result in &results {
+                replaced.push(format!("{}{}{}", result, between, text));
+            }
+        }
+        results = replaced;
+        last = found.end();
+    }
+    for result in &mut results {
+        result.push_str(&string[last..]);
+    }
+    Ok(results)
+}
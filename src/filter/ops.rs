@@ -0,0 +1,668 @@
+//! Operations on values shared by the evaluator and the builtins.
+
+use std::cmp::Ordering;
+
+use super::{ArithOp, CompareOp, Format};
+use crate::error::EvalError;
+use crate::map::JMap;
+use crate::parser::JVal;
+use crate::ser;
+
+/// How much of a value error messages quote before cutting it short.
+const DESCRIBE_LIMIT: usize = 30;
+
+/// The longest string, in bytes, that multiplying a string by a number may build.
+const REPEAT_LIMIT: usize = 1 << 28;
+
+pub(super) fn invalid(message: String) -> EvalError {
+    EvalError::Invalid { message }
+}
+
+/// Everything but `null` and `false` counts as true.
+pub(super) fn is_truthy(value: &JVal) -> bool {
+    !matches!(value, JVal::JNull | JVal::JBool(false))
+}
+
+pub(super) fn type_name(value: &JVal) -> &'static str {
+    match value {
+        JVal::JNull => "null",
+        JVal::JBool(_) => "boolean",
+        JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_) => "number",
+        JVal::JString(_) => "string",
+        JVal::JArray(_) => "array",
+        JVal::JObject(_) => "object",
+    }
+}
+
+/// Names a value for an error message, e.g. `number (1)`.
+pub(super) fn describe(value: &JVal) -> String {
+    let mut text = ser::to_string(value);
+    if let Some((cut, _)) = text.char_indices().nth(DESCRIBE_LIMIT) {
+        text.truncate(cut);
+        text.push_str("...");
+    }
+    format!("{} ({})", type_name(value), text)
+}
+
+pub(super) fn index(target: &JVal, key: &JVal) -> Result<JVal, EvalError> {
+    if let Some((start, end)) = slice_bounds(key) {
+        return slice(target, start, end);
+    }
+    match (target, key) {
+        (JVal::JObject(map), JVal::JString(key)) => {
+            Ok(map.get(key).cloned().unwrap_or(JVal::JNull))
+        }
+        (JVal::JArray(items), index @ (JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_))) => {
+            Ok(array_index(items.len(), float(index))
+                .and_then(|index| items.get(index))
+                .cloned()
+                .unwrap_or(JVal::JNull))
+        }
+        (
+            JVal::JNull,
+            JVal::JString(_) | JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_) | JVal::JNull,
+        ) => Ok(JVal::JNull),
+        _ => Err(cannot_index(target, key)),
+    }
+}
+
+pub(super) fn cannot_index(target: &JVal, key: &JVal) -> EvalError {
+    match key {
+        JVal::JString(key) => invalid(format!(
+            "Cannot index {} with \"{}\"",
+            type_name(target),
+            key
+        )),
+        key => invalid(format!(
+            "Cannot index {} with {}",
+            type_name(target),
+            type_name(key)
+        )),
+    }
+}
+
+/// Resolves a possibly negative, possibly fractional index. `None` means it falls
+/// before the start.
+fn array_index(len: usize, index: f64) -> Option<usize> {
+    let index = index.floor();
+    let index = if index < 0.0 {
+        index + len as f64
+    } else {
+        index
+    };
+    (index >= 0.0).then_some(index as usize)
+}
+
+pub(super) fn slice(target: &JVal, start: &JVal, end: &JVal) -> Result<JVal, EvalError> {
+    let len = match target {
+        JVal::JNull => return Ok(JVal::JNull),
+        JVal::JArray(items) => items.len(),
+        JVal::JString(string) => string.chars().count(),
+        _ => return Err(invalid(format!("Cannot slice {}", describe(target)))),
+    };
+    let (start, end) = slice_range(len, start, end)?;
+
+    Ok(match target {
+        JVal::JArray(items) => JVal::JArray(items[start..end].to_vec()),
+        JVal::JString(string) => {
+            JVal::JString(string.chars().skip(start).take(end - start).collect())
+        }
+        _ => unreachable!("other types returned early"),
+    })
+}
+
+/// The indexes `[start:end]` covers in something `len` items long.
+fn slice_range(len: usize, start: &JVal, end: &JVal) -> Result<(usize, usize), EvalError> {
+    let bound = |bound: &JVal, default: usize, round: fn(f64) -> f64| match bound {
+        JVal::JNull => Ok(default),
+        n if n.is_number() => {
+            let n = round(float(n));
+            let n = if n < 0.0 { n + len as f64 } else { n };
+            Ok(n.clamp(0.0, len as f64) as usize)
+        }
+        other => Err(invalid(format!(
+            "Slice bounds must be numbers, not {}",
+            describe(other)
+        ))),
+    };
+    let start = bound(start, 0, f64::floor)?;
+    let end = bound(end, len, f64::ceil)?.max(start);
+    Ok((start, end))
+}
+
+/// The path component for `.[start:end]`, written the way jq writes it.
+pub(super) fn slice_key(start: &JVal, end: &JVal) -> JVal {
+    JVal::from_iter([("start", start.clone()), ("end", end.clone())])
+}
+
+/// The bounds of a path component made by `slice_key`, or `None` for a plain key.
+fn slice_bounds(key: &JVal) -> Option<(&JVal, &JVal)> {
+    let JVal::JObject(bounds) = key else {
+        return None;
+    };
+    let bound = |name| bounds.get(name).unwrap_or(&JVal::JNull);
+    Some((bound("start"), bound("end")))
+}
+
+/// The keys and values of an array or object; array keys are numbers.
+pub(super) fn entries(value: JVal) -> Result<Vec<(JVal, JVal)>, EvalError> {
+    match value {
+        JVal::JArray(items) => Ok(items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| (JVal::from(i), item))
+            .collect()),
+        JVal::JObject(map) => Ok(map
+            .into_iter()
+            .map(|(key, value)| (JVal::JString(key), value))
+            .collect()),
+        other => Err(cannot_iterate(&other)),
+    }
+}
+
+pub(super) fn iterate(value: JVal) -> Result<Vec<JVal>, EvalError> {
+    match value {
+        JVal::JArray(items) => Ok(items),
+        JVal::JObject(map) => Ok(map.into_iter().map(|(_, value)| value).collect()),
+        other => Err(cannot_iterate(&other)),
+    }
+}
+
+fn cannot_iterate(value: &JVal) -> EvalError {
+    invalid(format!("Cannot iterate over {}", describe(value)))
+}
+
+pub(super) fn negate(value: JVal) -> Result<JVal, EvalError> {
+    match value {
+        JVal::JInt(n) => Ok(n
+            .checked_neg()
+            .map_or_else(|| JVal::from(-(n as i128)), JVal::JInt)),
+        JVal::JUint(n) => Ok(JVal::from(-(n as i128))),
+        JVal::JFloat(n) => Ok(JVal::JFloat(-n)),
+        other => Err(invalid(format!("{} cannot be negated", describe(&other)))),
+    }
+}
+
+pub(super) fn arithmetic(op: ArithOp, lhs: JVal, rhs: JVal) -> Result<JVal, EvalError> {
+    use JVal::*;
+    if let (JInt(a), JInt(b)) = (&lhs, &rhs) {
+        if let Some(result) = integer(op, *a, *b) {
+            return Ok(JInt(result));
+        }
+    }
+    if lhs.is_number() && rhs.is_number() {
+        return numeric(op, float(&lhs), float(&rhs));
+    }
+    let result = match (op, lhs, rhs) {
+        (ArithOp::Add, JNull, value) | (ArithOp::Add, value, JNull) => value,
+        (ArithOp::Add, JString(a), JString(b)) => JString(a + &b),
+        (ArithOp::Add, JArray(mut a), JArray(b)) => {
+            a.extend(b);
+            JArray(a)
+        }
+        (ArithOp::Add, JObject(mut a), JObject(b)) => {
+            a.extend(b);
+            JObject(a)
+        }
+        (ArithOp::Sub, JArray(a), JArray(b)) => {
+            JArray(a.into_iter().filter(|item| !b.contains(item)).collect())
+        }
+        (ArithOp::Mul, JString(s), n) | (ArithOp::Mul, n, JString(s)) if n.is_number() => {
+            let count = float(&n).ceil();
+            if count <= 0.0 {
+                JNull
+            } else if count * s.len() as f64 > REPEAT_LIMIT as f64 {
+                return Err(invalid(format!(
+                    "{} repeated {} times would exceed {} bytes",
+                    describe(&JString(s)),
+                    describe(&n),
+                    REPEAT_LIMIT
+                )));
+            } else {
+                JString(s.repeat(count as usize))
+            }
+        }
+        (ArithOp::Mul, JObject(a), JObject(b)) => JObject(deep_merge(a, b)),
+        (ArithOp::Div, JString(a), JString(b)) => {
+            JArray(split(&a, &b).into_iter().map(JString).collect())
+        }
+        (op, lhs, rhs) => {
+            let verb = match op {
+                ArithOp::Add => "added",
+                ArithOp::Sub => "subtracted",
+                ArithOp::Mul => "multiplied",
+                ArithOp::Div => "divided",
+                ArithOp::Rem => "divided (remainder)",
+            };
+            return Err(invalid(format!(
+                "{} and {} cannot be {}",
+                describe(&lhs),
+                describe(&rhs),
+                verb
+            )));
+        }
+    };
+    Ok(result)
+}
+
+/// Integer arithmetic, for as long as the result stays an exact integer.
+fn integer(op: ArithOp, a: i64, b: i64) -> Option<i64> {
+    match op {
+        ArithOp::Add => a.checked_add(b),
+        ArithOp::Sub => a.checked_sub(b),
+        ArithOp::Mul => a.checked_mul(b),
+        // Division and remainder by zero are reported by `numeric`
+        ArithOp::Div if b != 0 && a % b == 0 => a.checked_div(b),
+        ArithOp::Rem if b != 0 => a.checked_rem(b.checked_abs()?),
+        ArithOp::Div | ArithOp::Rem => None,
+    }
+}
+
+fn numeric(op: ArithOp, a: f64, b: f64) -> Result<JVal, EvalError> {
+    let result = match op {
+        ArithOp::Add => a + b,
+        ArithOp::Sub => a - b,
+        ArithOp::Mul => a * b,
+        ArithOp::Div if b == 0.0 => return Err(divide_by_zero(a, b)),
+        ArithOp::Rem if b as i64 == 0 => return Err(divide_by_zero(a, b)),
+        ArithOp::Div => a / b,
+        ArithOp::Rem => (a as i64).wrapping_rem((b as i64).wrapping_abs()) as f64,
+    };
+    Ok(JVal::from(result))
+}
+
+/// The value arithmetic works on, with numbers too large for an `f64` as infinities.
+/// Anything other than a number is NaN.
+pub(super) fn float(value: &JVal) -> f64 {
+    match value {
+        JVal::JInt(num) => *num as f64,
+        JVal::JUint(num) => *num as f64,
+        JVal::JFloat(num) => num.as_f64().unwrap_or(if num.as_str().starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        }),
+        _ => f64::NAN,
+    }
+}
+
+fn divide_by_zero(a: f64, b: f64) -> EvalError {
+    invalid(format!(
+        "{} and {} cannot be divided because the divisor is zero",
+        describe(&JVal::from(a)),
+        describe(&JVal::from(b))
+    ))
+}
+
+fn deep_merge(mut target: JMap, source: JMap) -> JMap {
+    for (key, value) in source {
+        let merged = match (target.get_mut(&key), value) {
+            (Some(JVal::JObject(existing)), JVal::JObject(value)) => {
+                JVal::JObject(deep_merge(std::mem::take(existing), value))
+            }
+            (_, value) => value,
+        };
+        target.insert(key, merged);
+    }
+    target
+}
+
+/// Splits on a literal separator; an empty separator splits into characters.
+pub(super) fn split(string: &str, separator: &str) -> Vec<String> {
+    if string.is_empty() {
+        Vec::new()
+    } else if separator.is_empty() {
+        string.chars().map(String::from).collect()
+    } else {
+        string.split(separator).map(String::from).collect()
+    }
+}
+
+/// jq's total order: `null < false < true < numbers < strings < arrays < objects`.
+/// Objects compare their sorted key lists first, then their values key by key.
+pub(super) fn compare(a: &JVal, b: &JVal) -> Ordering {
+    fn rank(value: &JVal) -> u8 {
+        match value {
+            JVal::JNull => 0,
+            JVal::JBool(false) => 1,
+            JVal::JBool(true) => 2,
+            JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_) => 3,
+            JVal::JString(_) => 4,
+            JVal::JArray(_) => 5,
+            JVal::JObject(_) => 6,
+        }
+    }
+
+    match (a, b) {
+        (JVal::JInt(a), JVal::JInt(b)) => a.cmp(b),
+        (a, b) if a.is_number() && b.is_number() => a.as_number().cmp(&b.as_number()),
+        (JVal::JString(a), JVal::JString(b)) => a.cmp(b),
+        (JVal::JArray(a), JVal::JArray(b)) => compare_all(a.iter(), b.iter()),
+        (JVal::JObject(a), JVal::JObject(b)) => {
+            let a_keys = sorted_keys(a);
+            let b_keys = sorted_keys(b);
+            a_keys.cmp(&b_keys).then_with(|| {
+                compare_all(
+                    a_keys.iter().filter_map(|key| a.get(key)),
+                    b_keys.iter().filter_map(|key| b.get(key)),
+                )
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn compare_all<'v>(
+    a: impl Iterator<Item = &'v JVal>,
+    b: impl Iterator<Item = &'v JVal>,
+) -> Ordering {
+    let mut b = b;
+    for a in a {
+        match b.next() {
+            Some(b) => match compare(a, b) {
+                Ordering::Equal => continue,
+                other => return other,
+            },
+            None => return Ordering::Greater,
+        }
+    }
+    if b.next().is_some() {
+        Ordering::Less
+    } else {
+        Ordering::Equal
+    }
+}
+
+pub(super) fn sorted_keys(map: &JMap) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+pub(super) fn compare_with(op: CompareOp, a: &JVal, b: &JVal) -> bool {
+    let ordering = compare(a, b);
+    match op {
+        CompareOp::Eq => ordering.is_eq(),
+        CompareOp::Ne => ordering.is_ne(),
+        CompareOp::Lt => ordering.is_lt(),
+        CompareOp::Le => ordering.is_le(),
+        CompareOp::Gt => ordering.is_gt(),
+        CompareOp::Ge => ordering.is_ge(),
+    }
+}
+
+/// Renders a value the way `tostring` does: strings as they are, anything else as JSON.
+pub(super) fn to_text(value: &JVal) -> String {
+    match value {
+        JVal::JString(string) => string.clone(),
+        other => ser::to_string(other),
+    }
+}
+
+pub(super) fn format(format: Format, value: &JVal) -> Result<String, EvalError> {
+    match format {
+        Format::Text => Ok(to_text(value)),
+        Format::Json => Ok(ser::to_string(value)),
+        Format::Csv | Format::Tsv => {
+            let JVal::JArray(items) = value else {
+                return Err(invalid(format!(
+                    "{} cannot be {}-formatted, only an array can be",
+                    describe(value),
+                    if format == Format::Csv { "csv" } else { "tsv" }
+                )));
+            };
+            let fields = items
+                .iter()
+                .map(|item| match item {
+                    JVal::JNull => Ok(String::new()),
+                    JVal::JBool(_) | JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_) => {
+                        Ok(ser::to_string(item))
+                    }
+                    JVal::JString(string) if format == Format::Csv => {
+                        Ok(format!("\"{}\"", string.replace('"', "\"\"")))
+                    }
+                    JVal::JString(string) => Ok(string
+                        .replace('\\', "\\\\")
+                        .replace('\t', "\\t")
+                        .replace('\n', "\\n")
+                        .replace('\r', "\\r")),
+                    other => Err(invalid(format!(
+                        "{} is not valid in a csv row",
+                        describe(other)
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(fields.join(if format == Format::Csv { "," } else { "\t" }))
+        }
+        Format::Html => Ok(to_text(value)
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('\'', "&#39;")
+            .replace('"', "&quot;")),
+        Format::Uri => Ok(to_text(value)
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{:02X}", byte),
+            })
+            .collect()),
+        Format::Sh => {
+            let quote = |item: &JVal| match item {
+                JVal::JString(string) => Ok(format!("'{}'", string.replace('\'', "'\\''"))),
+                JVal::JArray(_) | JVal::JObject(_) => Err(invalid(format!(
+                    "{} cannot be escaped for shell",
+                    describe(item)
+                ))),
+                other => Ok(ser::to_string(other)),
+            };
+            match value {
+                JVal::JArray(items) => Ok(items
+                    .iter()
+                    .map(quote)
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(" ")),
+                other => quote(other),
+            }
+        }
+        Format::Base64 => Ok(base64_encode(to_text(value).as_bytes())),
+        Format::Base64d => {
+            let text = to_text(value);
+            let bytes = base64_decode(&text)
+                .ok_or_else(|| invalid(format!("{} is not valid base64 data", describe(value))))?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes standard base64, with or without padding.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for byte in text.bytes() {
+        let value = BASE64_ALPHABET.iter().position(|&ch| ch == byte)? as u32;
+        bits = bits << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    // A single leftover character cannot encode a whole byte
+    (count < 6).then_some(bytes)
+}
+
+pub(super) fn get_path(value: &JVal, path: &[JVal]) -> Result<JVal, EvalError> {
+    let mut current = value.clone();
+    for key in path {
+        if current.is_null() {
+            return Ok(JVal::JNull);
+        }
+        current = index(&current, key)?;
+    }
+    Ok(current)
+}
+
+/// Sets the value at `path`, creating objects, arrays and `null` padding as needed.
+pub(super) fn set_path(target: &mut JVal, path: &[JVal], value: JVal) -> Result<(), EvalError> {
+    let mut current = target;
+    for (i, key) in path.iter().enumerate() {
+        if let Some((start, end)) = slice_bounds(key) {
+            return update_slice(current, start, end, |slice| {
+                set_path(slice, &path[i + 1..], value)
+            });
+        }
+        current = child_mut(current, key)?;
+    }
+    *current = value;
+    Ok(())
+}
+
+/// Replaces the slice `[start:end]` of an array with what `update` makes of it, which
+/// must still be an array. `null` counts as an empty array.
+fn update_slice(
+    target: &mut JVal,
+    start: &JVal,
+    end: &JVal,
+    update: impl FnOnce(&mut JVal) -> Result<(), EvalError>,
+) -> Result<(), EvalError> {
+    if target.is_null() {
+        *target = JVal::JArray(Vec::new());
+    }
+    let JVal::JArray(items) = target else {
+        return Err(invalid(format!(
+            "Cannot update a slice of {}",
+            describe(target)
+        )));
+    };
+    let (start, end) = slice_range(items.len(), start, end)?;
+    let mut slice = JVal::JArray(items[start..end].to_vec());
+    update(&mut slice)?;
+    let JVal::JArray(replacement) = slice else {
+        return Err(invalid(
+            "A slice of an array can only be assigned another array".to_string(),
+        ));
+    };
+    items.splice(start..end, replacement);
+    Ok(())
+}
+
+fn child_mut<'v>(target: &'v mut JVal, key: &JVal) -> Result<&'v mut JVal, EvalError> {
+    if target.is_null() {
+        *target = match key {
+            JVal::JString(_) => JVal::JObject(JMap::new()),
+            JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_) => JVal::JArray(Vec::new()),
+            _ => return Err(cannot_index(target, key)),
+        };
+    }
+    match (target, key) {
+        (JVal::JObject(map), JVal::JString(key)) => {
+            if !map.contains_key(key) {
+                map.insert(key.clone(), JVal::JNull);
+            }
+            Ok(map.get_mut(key).expect("member was just inserted"))
+        }
+        (JVal::JArray(items), n @ (JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_))) => {
+            let index = array_index(items.len(), float(n))
+                .ok_or_else(|| invalid("Out of bounds negative array index".to_string()))?;
+            if index >= items.len() {
+                items.resize(index + 1, JVal::JNull);
+            }
+            Ok(&mut items[index])
+        }
+        (target, key) => Err(cannot_index(target, key)),
+    }
+}
+
+/// Deletes every path, deepest and last first so earlier deletions do not shift the
+/// array indexes of later ones.
+pub(super) fn delete_paths(target: &mut JVal, mut paths: Vec<Vec<JVal>>) -> Result<(), EvalError> {
+    paths.sort_by(|a, b| compare_all(b.iter(), a.iter()));
+    paths.dedup();
+    for path in paths {
+        delete_path(target, &path)?;
+    }
+    Ok(())
+}
+
+fn delete_path(target: &mut JVal, path: &[JVal]) -> Result<(), EvalError> {
+    let Some((last, parents)) = path.split_last() else {
+        *target = JVal::JNull;
+        return Ok(());
+    };
+    let mut current = target;
+    for (i, key) in parents.iter().enumerate() {
+        if let Some((start, end)) = slice_bounds(key) {
+            if current.is_null() {
+                return Ok(());
+            }
+            return update_slice(current, start, end, |slice| {
+                delete_path(slice, &path[i + 1..])
+            });
+        }
+        current = match (current, key) {
+            (JVal::JNull, _) => return Ok(()),
+            (JVal::JObject(map), JVal::JString(key)) => match map.get_mut(key) {
+                Some(child) => child,
+                None => return Ok(()),
+            },
+            (JVal::JArray(items), n @ (JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_))) => {
+                match array_index(items.len(), float(n)).and_then(|index| items.get_mut(index)) {
+                    Some(child) => child,
+                    None => return Ok(()),
+                }
+            }
+            (current, key) => return Err(cannot_index(current, key)),
+        };
+    }
+    if let (Some((start, end)), false) = (slice_bounds(last), current.is_null()) {
+        return update_slice(current, start, end, |slice| {
+            *slice = JVal::JArray(Vec::new());
+            Ok(())
+        });
+    }
+    match (current, last) {
+        (JVal::JNull, _) => {}
+        (JVal::JObject(map), JVal::JString(key)) => {
+            map.remove(key);
+        }
+        (JVal::JArray(items), n @ (JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_))) => {
+            if let Some(index) = array_index(items.len(), float(n)).filter(|&i| i < items.len()) {
+                items.remove(index);
+            }
+        }
+        (current, key) => {
+            return Err(invalid(format!(
+                "Cannot delete field at {} index of {}",
+                type_name(key),
+                type_name(current)
+            )))
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,756 @@
+use super::{builtins, ops};
+use super::{ArithOp, AssignOp, CompareOp, Expr, Format, StringPart};
+use crate::error::FilterError;
+use crate::number::Number;
+use crate::parser::JVal;
+
+/// Words that cannot start a filter or name a function.
+const KEYWORDS: &[&str] = &[
+    "as", "and", "or", "if", "then", "elif", "else", "end", "try", "catch", "reduce", "foreach",
+    "def", "label", "import", "include",
+];
+
+/// Operators that combine two filters, from the loosest binding to the tightest.
+#[derive(Clone, Copy)]
+enum BinaryOp {
+    Comma,
+    Alternative,
+    Assign(AssignOp),
+    Or,
+    And,
+    Compare(CompareOp),
+    Arithmetic(ArithOp),
+}
+
+/// Longer operators come before their prefixes so `//=` is not read as `//`.
+const OPERATORS: &[(&str, BinaryOp)] = &[
+    ("|=", BinaryOp::Assign(AssignOp::Update)),
+    ("//=", BinaryOp::Assign(AssignOp::Alternative)),
+    ("+=", BinaryOp::Assign(AssignOp::Arithmetic(ArithOp::Add))),
+    ("-=", BinaryOp::Assign(AssignOp::Arithmetic(ArithOp::Sub))),
+    ("*=", BinaryOp::Assign(AssignOp::Arithmetic(ArithOp::Mul))),
+    ("/=", BinaryOp::Assign(AssignOp::Arithmetic(ArithOp::Div))),
+    ("%=", BinaryOp::Assign(AssignOp::Arithmetic(ArithOp::Rem))),
+    ("//", BinaryOp::Alternative),
+    ("==", BinaryOp::Compare(CompareOp::Eq)),
+    ("!=", BinaryOp::Compare(CompareOp::Ne)),
+    ("<=", BinaryOp::Compare(CompareOp::Le)),
+    (">=", BinaryOp::Compare(CompareOp::Ge)),
+    ("<", BinaryOp::Compare(CompareOp::Lt)),
+    (">", BinaryOp::Compare(CompareOp::Gt)),
+    ("=", BinaryOp::Assign(AssignOp::Set)),
+    ("+", BinaryOp::Arithmetic(ArithOp::Add)),
+    ("-", BinaryOp::Arithmetic(ArithOp::Sub)),
+    ("*", BinaryOp::Arithmetic(ArithOp::Mul)),
+    ("/", BinaryOp::Arithmetic(ArithOp::Div)),
+    ("%", BinaryOp::Arithmetic(ArithOp::Rem)),
+    (",", BinaryOp::Comma),
+];
+
+impl BinaryOp {
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Comma => 1,
+            BinaryOp::Alternative => 2,
+            BinaryOp::Assign(_) => 3,
+            BinaryOp::Or => 4,
+            BinaryOp::And => 5,
+            BinaryOp::Compare(_) => 6,
+            BinaryOp::Arithmetic(ArithOp::Add | ArithOp::Sub) => 7,
+            BinaryOp::Arithmetic(_) => 8,
+        }
+    }
+
+    fn build(self, lhs: Expr, rhs: Expr) -> Expr {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        match self {
+            BinaryOp::Comma => Expr::Comma(lhs, rhs),
+            BinaryOp::Alternative => Expr::Alternative(lhs, rhs),
+            BinaryOp::Assign(op) => Expr::Assign(op, lhs, rhs),
+            BinaryOp::Or => Expr::Or(lhs, rhs),
+            BinaryOp::And => Expr::And(lhs, rhs),
+            BinaryOp::Compare(op) => Expr::Compare(op, lhs, rhs),
+            BinaryOp::Arithmetic(op) => Expr::Arithmetic(op, lhs, rhs),
+        }
+    }
+}
+
+pub(super) fn parse_filter(filter: &str, variables: &[&str]) -> Result<Expr, FilterError> {
+    let mut parser = FilterParser {
+        filter,
+        offset: 0,
+        scope: variables.iter().map(|name| name.to_string()).collect(),
+    };
+    let expr = parser.pipe(true)?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(parser.unexpected("an operator or the end of the filter")),
+    }
+}
+
+struct FilterParser<'f> {
+    filter: &'f str,
+    offset: usize,
+    /// The variables bound around the current position, innermost last.
+    scope: Vec<String>,
+}
+
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_'
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+impl<'f> FilterParser<'f> {
+    fn rest(&self) -> &'f str {
+        &self.filter[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.offset += ch.len_utf8();
+        Some(ch)
+    }
+
+    /// Consumes `expected` if the rest of the filter starts with it.
+    fn eat(&mut self, expected: &str) -> bool {
+        let matches = self.rest().starts_with(expected);
+        if matches {
+            self.offset += expected.len();
+        }
+        matches
+    }
+
+    fn expect(&mut self, ch: char, expected: &'static str) -> Result<(), FilterError> {
+        self.skip_whitespace();
+        if self.peek() == Some(ch) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    /// Whether the next word is `keyword`, as opposed to a longer name starting with it.
+    fn at_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        self.rest().starts_with(keyword)
+            && !self.rest()[keyword.len()..].starts_with(is_identifier_char)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matches = self.at_keyword(keyword);
+        if matches {
+            self.offset += keyword.len();
+        }
+        matches
+    }
+
+    fn expect_keyword(&mut self, keyword: &str, expected: &'static str) -> Result<(), FilterError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    /// Skips whitespace and `#` comments.
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\n' | '\r') => self.offset += 1,
+                Some('#') => {
+                    let line_end = self.rest().find('\n').unwrap_or(self.rest().len());
+                    self.offset += line_end;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Describes whatever is at the current offset as not being `expected`, quoting
+    /// a whole word rather than its first letter.
+    fn unexpected(&self, expected: &'static str) -> FilterError {
+        let found = match self.peek() {
+            Some(ch) if is_identifier_start(ch) => self
+                .rest()
+                .split(|ch| !is_identifier_char(ch))
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            Some(ch) => ch.to_string(),
+            None => {
+                return FilterError::UnexpectedEnd {
+                    expected,
+                    offset: self.offset,
+                }
+            }
+        };
+        FilterError::UnexpectedToken {
+            found,
+            expected,
+            offset: self.offset,
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let len = self
+            .rest()
+            .find(|ch| !is_identifier_char(ch))
+            .unwrap_or(self.rest().len());
+        if len == 0 || !self.rest().starts_with(is_identifier_start) {
+            return None;
+        }
+        let name = self.rest()[..len].to_string();
+        self.offset += len;
+        Some(name)
+    }
+
+    /// Parses `$name` where a new variable is bound.
+    fn binding_name(&mut self) -> Result<String, FilterError> {
+        self.expect('$', "'$' and a variable name")?;
+        self.identifier()
+            .ok_or_else(|| self.unexpected("a variable name"))
+    }
+
+    /// Parses the body a variable is visible in.
+    fn with_binding<T>(
+        &mut self,
+        name: &str,
+        body: impl FnOnce(&mut Self) -> Result<T, FilterError>,
+    ) -> Result<T, FilterError> {
+        self.scope.push(name.to_string());
+        let result = body(self);
+        self.scope.pop();
+        result
+    }
+
+    /// Parses filters joined by `|`, the loosest operator. Object values may not
+    /// contain a bare comma, which separates members there instead.
+    fn pipe(&mut self, allow_comma: bool) -> Result<Expr, FilterError> {
+        let first = self.unary(allow_comma)?;
+        let lhs = self.binary(first, 0, allow_comma)?;
+        self.skip_whitespace();
+        if self.peek() == Some('|') {
+            self.bump();
+            let rhs = self.pipe(allow_comma)?;
+            Ok(Expr::Pipe(Box::new(lhs), Box::new(rhs)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    /// Precedence climbing over the binary operators, starting from `lhs`.
+    fn binary(
+        &mut self,
+        mut lhs: Expr,
+        min_precedence: u8,
+        allow_comma: bool,
+    ) -> Result<Expr, FilterError> {
+        while let Some((op, len)) = self.peek_operator(allow_comma) {
+            let precedence = op.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            self.offset += len;
+            let operand = self.unary(allow_comma)?;
+            // `//` groups to the right, everything else to the left
+            let next_min = match op {
+                BinaryOp::Alternative => precedence,
+                _ => precedence + 1,
+            };
+            let rhs = self.binary(operand, next_min, allow_comma)?;
+            lhs = op.build(lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn peek_operator(&mut self, allow_comma: bool) -> Option<(BinaryOp, usize)> {
+        if self.at_keyword("and") {
+            return Some((BinaryOp::And, 3));
+        }
+        if self.at_keyword("or") {
+            return Some((BinaryOp::Or, 2));
+        }
+        OPERATORS
+            .iter()
+            .find(|(text, _)| self.rest().starts_with(text))
+            .filter(|(_, op)| allow_comma || !matches!(op, BinaryOp::Comma))
+            .map(|(text, op)| (*op, text.len()))
+    }
+
+    /// A postfix term, optionally negated or bound with `as $name | ...`.
+    fn unary(&mut self, allow_comma: bool) -> Result<Expr, FilterError> {
+        self.skip_whitespace();
+        if self.eat("-") {
+            return Ok(match self.unary(allow_comma)? {
+                Expr::Literal(n) if n.is_number() => {
+                    Expr::Literal(ops::negate(n).expect("numbers can always be negated"))
+                }
+                operand => Expr::Negate(Box::new(operand)),
+            });
+        }
+
+        let term = self.postfix_term()?;
+        if !self.eat_keyword("as") {
+            return Ok(term);
+        }
+        let name = self.binding_name()?;
+        self.expect('|', "'|' after the variable")?;
+        let body = self.with_binding(&name, |parser| parser.pipe(allow_comma))?;
+        Ok(Expr::Bind {
+            source: Box::new(term),
+            name,
+            body: Box::new(body),
+        })
+    }
+
+    /// A term followed by any number of `.name`, `[...]` and `?` suffixes.
+    fn postfix_term(&mut self) -> Result<Expr, FilterError> {
+        let mut term = self.term()?;
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some('.'), Some('[')) => {
+                    self.bump();
+                }
+                (Some('.'), Some('"')) => {
+                    self.bump();
+                    let key = self.string(Format::Text)?;
+                    term = Expr::Index(Box::new(term), Box::new(key));
+                }
+                (Some('.'), Some(ch)) if is_identifier_start(ch) => {
+                    self.bump();
+                    let name = self.identifier().expect("checked the first character");
+                    term = field(term, name);
+                }
+                (Some('['), _) => term = self.bracket_suffix(term)?,
+                (Some('?'), _) => {
+                    self.bump();
+                    term = Expr::Try(Box::new(term), None);
+                }
+                _ => return Ok(term),
+            }
+        }
+    }
+
+    /// Parses `[]`, `[index]` or `[start:end]` after `target`.
+    fn bracket_suffix(&mut self, target: Expr) -> Result<Expr, FilterError> {
+        self.bump();
+        self.skip_whitespace();
+        let target = Box::new(target);
+        if self.eat("]") {
+            return Ok(Expr::Iterate(target));
+        }
+        if self.eat(":") {
+            let end = self.pipe(true)?;
+            self.expect(']', "']' after the slice")?;
+            return Ok(Expr::Slice(target, None, Some(Box::new(end))));
+        }
+
+        let index = self.pipe(true)?;
+        self.skip_whitespace();
+        if self.eat(":") {
+            self.skip_whitespace();
+            let end = if self.eat("]") {
+                None
+            } else {
+                let end = self.pipe(true)?;
+                self.expect(']', "']' after the slice")?;
+                Some(Box::new(end))
+            };
+            return Ok(Expr::Slice(target, Some(Box::new(index)), end));
+        }
+        self.expect(']', "']' or ':' after the index")?;
+        Ok(Expr::Index(target, Box::new(index)))
+    }
+
+    fn term(&mut self) -> Result<Expr, FilterError> {
+        self.skip_whitespace();
+        let start = self.offset;
+        match self.peek() {
+            Some('.') => {
+                self.bump();
+                if self.eat(".") {
+                    return Ok(
+                        builtins::resolve("recurse", Vec::new()).expect("recurse is a builtin")
+                    );
+                }
+                match self.peek() {
+                    Some('"') => {
+                        let key = self.string(Format::Text)?;
+                        Ok(Expr::Index(Box::new(Expr::Identity), Box::new(key)))
+                    }
+                    Some(ch) if is_identifier_start(ch) => {
+                        let name = self.identifier().expect("checked the first character");
+                        Ok(field(Expr::Identity, name))
+                    }
+                    _ => Ok(Expr::Identity),
+                }
+            }
+            Some('$') => {
+                self.bump();
+                let name = self
+                    .identifier()
+                    .ok_or_else(|| self.unexpected("a variable name after '$'"))?;
+                if self.scope.contains(&name) {
+                    Ok(Expr::Variable(name))
+                } else if name == "ENV" {
+                    Ok(builtins::resolve("env", Vec::new()).expect("env is a builtin"))
+                } else {
+                    Err(FilterError::UndefinedVariable {
+                        name,
+                        offset: start,
+                    })
+                }
+            }
+            Some('"') => self.string(Format::Text),
+            Some('@') => {
+                self.bump();
+                let name = self.identifier().unwrap_or_default();
+                let format = match name.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    "tsv" => Format::Tsv,
+                    "html" => Format::Html,
+                    "uri" => Format::Uri,
+                    "sh" => Format::Sh,
+                    "base64" => Format::Base64,
+                    "base64d" => Format::Base64d,
+                    _ => {
+                        return Err(FilterError::UnknownFormat {
+                            name,
+                            offset: start,
+                        })
+                    }
+                };
+                self.skip_whitespace();
+                if self.peek() == Some('"') {
+                    self.string(format)
+                } else {
+                    Ok(Expr::Format(format))
+                }
+            }
+            Some('0'..='9') => self.number(),
+            Some('(') => {
+                self.bump();
+                let expr = self.pipe(true)?;
+                self.expect(')', "')'")?;
+                Ok(expr)
+            }
+            Some('[') => {
+                self.bump();
+                self.skip_whitespace();
+                if self.eat("]") {
+                    return Ok(Expr::Array(None));
+                }
+                let items = self.pipe(true)?;
+                self.expect(']', "']' or ','")?;
+                Ok(Expr::Array(Some(Box::new(items))))
+            }
+            Some('{') => self.object(),
+            Some(ch) if is_identifier_start(ch) => {
+                let word = self.identifier().expect("checked the first character");
+                match word.as_str() {
+                    "true" => Ok(Expr::Literal(JVal::JBool(true))),
+                    "false" => Ok(Expr::Literal(JVal::JBool(false))),
+                    "null" => Ok(Expr::Literal(JVal::JNull)),
+                    "if" => self.if_then_else(),
+                    "try" => {
+                        let body = self.postfix_term()?;
+                        let handler = if self.eat_keyword("catch") {
+                            Some(Box::new(self.postfix_term()?))
+                        } else {
+                            None
+                        };
+                        Ok(Expr::Try(Box::new(body), handler))
+                    }
+                    "reduce" | "foreach" => self.fold(&word),
+                    _ if KEYWORDS.contains(&word.as_str()) => {
+                        self.offset = start;
+                        Err(self.unexpected("a filter"))
+                    }
+                    _ => self.call(word, start),
+                }
+            }
+            _ => Err(self.unexpected("a filter")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Expr, FilterError> {
+        let start = self.offset;
+        let digits = |parser: &mut Self| {
+            while matches!(parser.peek(), Some('0'..='9')) {
+                parser.bump();
+            }
+        };
+        digits(self);
+        if self.peek() == Some('.') && matches!(self.peek_second(), Some('0'..='9')) {
+            self.bump();
+            digits(self);
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let before = self.offset;
+            self.bump();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.bump();
+            }
+            if matches!(self.peek(), Some('0'..='9')) {
+                digits(self);
+            } else {
+                self.offset = before;
+            }
+        }
+        let text = &self.filter[start..self.offset];
+        // Forms JSON lacks, like `1.` or `.5`, are normalized through an `f64`
+        let number = match text.parse::<Number>() {
+            Ok(number) => JVal::from(number),
+            Err(_) => JVal::from(
+                text.parse::<f64>()
+                    .expect("digits with an optional fraction and exponent"),
+            ),
+        };
+        Ok(Expr::Literal(number))
+    }
+
+    /// Parses a string literal after its opening quote has been seen. Interpolated
+    /// values are rendered with `format`.
+    fn string(&mut self, format: Format) -> Result<Expr, FilterError> {
+        self.bump();
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        loop {
+            let escape_offset = self.offset;
+            let ch = self.bump().ok_or(FilterError::UnexpectedEnd {
+                expected: "closing '\"' of string",
+                offset: self.offset,
+            })?;
+            match ch {
+                '"' => break,
+                '\\' => {
+                    let unescaped = match self.bump() {
+                        Some('(') => {
+                            if !literal.is_empty() {
+                                parts.push(StringPart::Literal(std::mem::take(&mut literal)));
+                            }
+                            let expr = self.pipe(true)?;
+                            self.expect(')', "')' to close the interpolation")?;
+                            parts.push(StringPart::Interpolation(expr));
+                            continue;
+                        }
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{0008}',
+                        Some('f') => '\u{000C}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape(escape_offset)?,
+                        _ => {
+                            return Err(FilterError::InvalidEscape {
+                                offset: escape_offset,
+                            })
+                        }
+                    };
+                    literal.push(unescaped);
+                }
+                ch => literal.push(ch),
+            }
+        }
+
+        if parts.is_empty() {
+            return Ok(Expr::Literal(JVal::JString(literal)));
+        }
+        if !literal.is_empty() {
+            parts.push(StringPart::Literal(literal));
+        }
+        Ok(Expr::String(format, parts))
+    }
+
+    /// Parses the hex digits of a `\u` escape, combining a surrogate pair into one
+    /// character.
+    fn unicode_escape(&mut self, escape_offset: usize) -> Result<char, FilterError> {
+        let invalid = FilterError::InvalidEscape {
+            offset: escape_offset,
+        };
+        let high = self.hex_digits().ok_or_else(|| invalid.clone())?;
+        let code_point = match high {
+            0xD800..=0xDBFF => {
+                if !self.eat("\\u") {
+                    return Err(invalid);
+                }
+                match self.hex_digits() {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        0x10000 + ((high as u32 - 0xD800) << 10) + (low as u32 - 0xDC00)
+                    }
+                    _ => return Err(invalid),
+                }
+            }
+            _ => high as u32,
+        };
+        char::from_u32(code_point).ok_or(invalid)
+    }
+
+    fn hex_digits(&mut self) -> Option<u16> {
+        let digits = self.rest().get(..4)?;
+        if !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.offset += 4;
+        u16::from_str_radix(digits, 16).ok()
+    }
+
+    fn object(&mut self) -> Result<Expr, FilterError> {
+        self.bump();
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Expr::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let start = self.offset;
+            // The value a key stands for when it is written without one, as in `{name}`
+            let (key, shorthand) = match self.peek() {
+                Some('$') => {
+                    let variable = self.term()?;
+                    let name = self.filter[start + 1..self.offset].to_string();
+                    (Expr::Literal(JVal::JString(name)), Some(variable))
+                }
+                Some('"' | '@') => {
+                    let key = self.term()?;
+                    let value = Expr::Index(Box::new(Expr::Identity), Box::new(key.clone()));
+                    (key, Some(value))
+                }
+                Some('(') => {
+                    self.bump();
+                    let key = self.pipe(true)?;
+                    self.expect(')', "')' after the key")?;
+                    (key, None)
+                }
+                Some(ch) if is_identifier_start(ch) => {
+                    let name = self.identifier().expect("checked the first character");
+                    let value = field(Expr::Identity, name.clone());
+                    (Expr::Literal(JVal::JString(name)), Some(value))
+                }
+                _ => return Err(self.unexpected("an object key")),
+            };
+
+            self.skip_whitespace();
+            let value = if self.eat(":") {
+                self.pipe(false)?
+            } else {
+                shorthand.ok_or_else(|| self.unexpected("':' after the key"))?
+            };
+            entries.push((key, value));
+
+            self.skip_whitespace();
+            if !self.eat(",") {
+                self.expect('}', "',' or '}'")?;
+                return Ok(Expr::Object(entries));
+            }
+        }
+    }
+
+    /// Parses the rest of `if cond then a elif cond then b else c end`.
+    fn if_then_else(&mut self) -> Result<Expr, FilterError> {
+        let condition = self.pipe(true)?;
+        self.expect_keyword("then", "'then'")?;
+        let then = self.pipe(true)?;
+        let otherwise = if self.eat_keyword("elif") {
+            self.if_then_else()?
+        } else if self.eat_keyword("else") {
+            let otherwise = self.pipe(true)?;
+            self.expect_keyword("end", "'end'")?;
+            otherwise
+        } else {
+            self.expect_keyword("end", "'elif', 'else' or 'end'")?;
+            Expr::Identity
+        };
+        Ok(Expr::If {
+            condition: Box::new(condition),
+            then: Box::new(then),
+            otherwise: Box::new(otherwise),
+        })
+    }
+
+    /// Parses the rest of `reduce SOURCE as $x (INIT; UPDATE)` or
+    /// `foreach SOURCE as $x (INIT; UPDATE; EXTRACT)`, whose extract is optional.
+    fn fold(&mut self, keyword: &str) -> Result<Expr, FilterError> {
+        let source = Box::new(self.postfix_term()?);
+        self.expect_keyword("as", "'as'")?;
+        let name = self.binding_name()?;
+        self.expect('(', "'('")?;
+        let init = Box::new(self.pipe(true)?);
+        self.expect(';', "';'")?;
+        let (update, extract) = self.with_binding(&name, |parser| {
+            let update = Box::new(parser.pipe(true)?);
+            parser.skip_whitespace();
+            let extract = if keyword == "foreach" && parser.eat(";") {
+                Some(Box::new(parser.pipe(true)?))
+            } else {
+                None
+            };
+            Ok((update, extract))
+        })?;
+        self.expect(')', "')'")?;
+
+        Ok(if keyword == "reduce" {
+            Expr::Reduce {
+                source,
+                name,
+                init,
+                update,
+            }
+        } else {
+            Expr::Foreach {
+                source,
+                name,
+                init,
+                update,
+                extract,
+            }
+        })
+    }
+
+    /// Parses the arguments of a call to `name`, if any, and looks the function up.
+    fn call(&mut self, name: String, start: usize) -> Result<Expr, FilterError> {
+        let mut args = Vec::new();
+        if self.eat("(") {
+            loop {
+                args.push(self.pipe(true)?);
+                self.skip_whitespace();
+                if !self.eat(";") {
+                    self.expect(')', "';' or ')'")?;
+                    break;
+                }
+            }
+        }
+        let arity = args.len();
+        builtins::resolve(&name, args).ok_or(FilterError::UnknownFunction {
+            name,
+            arity,
+            offset: start,
+        })
+    }
+}
+
+fn field(target: Expr, name: String) -> Expr {
+    Expr::Index(
+        Box::new(target),
+        Box::new(Expr::Literal(JVal::JString(name))),
+    )
+}
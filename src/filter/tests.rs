@@ -0,0 +1,608 @@
+use super::*;
+use crate::parser::from_str;
+
+fn inventory() -> JVal {
+    from_str(
+        r#"{
+            "items": [
+              { "name": "apple", "price": 3, "tags": ["fruit", "red"] },
+              { "name": "bread", "price": 12, "tags": [] },
+              { "name": "cherry", "price": 8, "tags": ["fruit"] }
+            ],
+            "version": "1.4.2"
+          }"#,
+    )
+    .unwrap()
+}
+
+/// Runs `filter` and returns its outputs as an array.
+fn run(input: &JVal, filter: &str) -> JVal {
+    Filter::parse(filter)
+        .unwrap_or_else(|e| panic!("{}: {}", filter, e))
+        .apply(input)
+        .unwrap_or_else(|e| panic!("{}: {}", filter, e))
+        .into_iter()
+        .collect()
+}
+
+fn json(text: &str) -> JVal {
+    from_str(text).unwrap()
+}
+
+fn eval_err(input: &JVal, filter: &str) -> String {
+    Filter::parse(filter)
+        .unwrap()
+        .apply(input)
+        .expect_err(filter)
+        .to_string()
+}
+
+fn parse_err(filter: &str) -> FilterError {
+    Filter::parse(filter).expect_err(filter)
+}
+
+#[test]
+fn test_paths_and_iteration() {
+    let input = inventory();
+    assert_eq!(run(&input, "."), JVal::from_iter([input.clone()]));
+    assert_eq!(run(&input, ".version"), json(r#"["1.4.2"]"#));
+    assert_eq!(run(&input, r#"."version""#), json(r#"["1.4.2"]"#));
+    assert_eq!(run(&input, ".items[0].name"), json(r#"["apple"]"#));
+    assert_eq!(run(&input, ".items[-1].name"), json(r#"["cherry"]"#));
+    assert_eq!(run(&input, ".items[5]"), json("[null]"));
+    assert_eq!(
+        run(&input, ".items[].name"),
+        json(r#"["apple", "bread", "cherry"]"#)
+    );
+    assert_eq!(run(&input, ".items[1:].[].price"), json("[12, 8]"));
+    assert_eq!(run(&input, r#".version[:3]"#), json(r#"["1.4"]"#));
+    assert_eq!(run(&input, ".missing.deeper"), json("[null]"));
+    assert_eq!(run(&input, "[.. | numbers]"), json("[[3, 12, 8]]"));
+}
+
+#[test]
+fn test_pipes_commas_and_construction() {
+    let input = inventory();
+    assert_eq!(
+        run(&input, ".items[] | {name, cost: .price * 2}"),
+        json(
+            r#"[{"name": "apple", "cost": 6}, {"name": "bread", "cost": 24}, {"name": "cherry", "cost": 16}]"#
+        )
+    );
+    assert_eq!(
+        run(&input, "[.items[].price], .version"),
+        json(r#"[[3, 12, 8], "1.4.2"]"#)
+    );
+    assert_eq!(
+        run(&input, r#"{(.items[].name): 1} | keys[0]"#),
+        json(r#"["apple", "bread", "cherry"]"#)
+    );
+    assert_eq!(run(&input, "{a: (1, 2)}"), json(r#"[{"a": 1}, {"a": 2}]"#));
+    assert_eq!(
+        run(&input, r#""\(.items[0].name) costs \(.items[0].price)""#),
+        json(r#"["apple costs 3"]"#)
+    );
+}
+
+#[test]
+fn test_select_map_keys_and_length() {
+    let input = inventory();
+    assert_eq!(
+        run(&input, "[.items[] | select(.price < 10) | .name]"),
+        json(r#"[["apple", "cherry"]]"#)
+    );
+    assert_eq!(run(&input, ".items | map(.price)"), json("[[3, 12, 8]]"));
+    assert_eq!(run(&input, "keys"), json(r#"[["items", "version"]]"#));
+    assert_eq!(
+        run(&input, ".items[0] | keys_unsorted"),
+        json(r#"[["name", "price", "tags"]]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[(.items | length), (.items[0].tags | length), (.version | length)]"
+        ),
+        json("[[3, 2, 5]]")
+    );
+    assert_eq!(run(&input, "null | length"), json("[0]"));
+}
+
+#[test]
+fn test_arithmetic() {
+    let input = JVal::JNull;
+    assert_eq!(run(&input, "1 + 2 * 3 - 4 / 2"), json("[5]"));
+    assert_eq!(
+        run(&input, "(1 + 2) * 3, 7 % 3, -(2 + 1)"),
+        json("[9, 1, -3]")
+    );
+    assert_eq!(
+        run(&input, r#""ab" + "cd", null + 1"#),
+        json(r#"["abcd", 1]"#)
+    );
+    assert_eq!(run(&input, "[1, 2, 3, 2] - [2]"), json("[[1, 3]]"));
+    assert_eq!(
+        run(&input, r#"{"a": {"b": 1}} * {"a": {"c": 2}}"#),
+        json(r#"[{"a": {"b": 1, "c": 2}}]"#)
+    );
+    assert_eq!(run(&input, r#""a,b" / ",""#), json(r#"[["a", "b"]]"#));
+    assert_eq!(run(&input, "(1, 2) + (10, 20)"), json("[11, 12, 21, 22]"));
+    assert_eq!(
+        eval_err(&input, r#"1 + "a""#),
+        r#"number (1) and string ("a") cannot be added"#
+    );
+    assert_eq!(
+        run(&input, r#""ab" * 2.5, "ab" * 0, "" * 1e300"#),
+        json(r#"["ababab", null, ""]"#)
+    );
+    for filter in [r#""x" * 1e300"#, r#""x" * 1e10"#, r#"1e1000 * "x""#] {
+        assert!(
+            eval_err(&input, filter).ends_with("would exceed 268435456 bytes"),
+            "{}",
+            filter
+        );
+    }
+    assert_eq!(
+        eval_err(&input, "1 / 0"),
+        "number (1) and number (0) cannot be divided because the divisor is zero"
+    );
+    // Integers stay integers until the result overflows or has a fraction
+    assert_eq!(
+        format!(
+            "{:?}",
+            run(&input, "1 + 2, 6 / 3, 7 / 2, -7 % 3, 9223372036854775807 + 1, 1.5 + 1.5")
+        ),
+        "JArray([JInt(3), JInt(2), JFloat(3.5), JInt(-1), JFloat(9.223372036854776e18), JFloat(3)])"
+    );
+}
+
+#[test]
+fn test_comparisons_and_logic() {
+    let input = JVal::JNull;
+    assert_eq!(
+        run(&input, "1 < 2, 2 <= 1, \"a\" == \"a\", [1] != [1], {} > []"),
+        json("[true, false, true, false, true]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            "true and (true, false), false or false, (null | not)"
+        ),
+        json("[true, false, false, true]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#"[{"b": 1}, null, true, "x", 2, false, [0]] | sort"#
+        ),
+        json(r#"[[null, false, true, 2, "x", [0], {"b": 1}]]"#)
+    );
+    assert_eq!(run(&input, ".a // .b // 3"), json("[3]"));
+    assert_eq!(run(&input, "(false, 1, null, 2) // 3"), json("[1, 2]"));
+}
+
+#[test]
+fn test_conditionals_variables_and_reduce() {
+    let input = inventory();
+    assert_eq!(
+        run(
+            &input,
+            r#".items[] | if .price < 5 then "cheap" elif .price < 10 then "fair" else "dear" end"#
+        ),
+        json(r#"["cheap", "dear", "fair"]"#)
+    );
+    assert_eq!(
+        run(&input, "if false then 1 end"),
+        JVal::from_iter([input.clone()])
+    );
+    assert_eq!(
+        run(&input, ".version as $v | .items[0] | [.name, $v]"),
+        json(r#"[["apple", "1.4.2"]]"#)
+    );
+    assert_eq!(
+        run(&input, "reduce .items[] as $item (0; . + $item.price)"),
+        json("[23]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[foreach .items[] as $item (0; . + 1; [., $item.name])]"
+        ),
+        json(r#"[[[1, "apple"], [2, "bread"], [3, "cherry"]]]"#)
+    );
+}
+
+#[test]
+fn test_try_and_errors() {
+    let input = json(r#"[1, {"a": 2}, "x"]"#);
+    assert_eq!(run(&input, "[.[] | .a?]"), json("[[2]]"));
+    assert_eq!(
+        run(&input, "[.[] | try .a catch \"bad\"]"),
+        json(r#"[["bad", 2, "bad"]]"#)
+    );
+    assert_eq!(
+        run(&input, r#"try error("boom") catch ."#),
+        json(r#"["boom"]"#)
+    );
+    assert_eq!(run(&input, "[.[]?]"), json(r#"[[1, {"a": 2}, "x"]]"#));
+    assert_eq!(eval_err(&input, ".a"), r#"Cannot index array with "a""#);
+    assert_eq!(eval_err(&input, ".[0][]"), "Cannot iterate over number (1)");
+    assert_eq!(
+        eval_err(&input, r#"error({"code": 1})"#),
+        r#"{"code":1} (not a string)"#
+    );
+}
+
+#[test]
+fn test_assignment() {
+    let input = inventory();
+    assert_eq!(
+        run(&input, ".items[0].price = 4 | .items[0].price"),
+        json("[4]")
+    );
+    assert_eq!(
+        run(&input, ".items[].price |= . * 10 | [.items[].price]"),
+        json("[[30, 120, 80]]")
+    );
+    assert_eq!(
+        run(&input, ".items[] += {stock: 0} | .items[2]"),
+        json(r#"[{"name": "cherry", "price": 8, "tags": ["fruit"], "stock": 0}]"#)
+    );
+    assert_eq!(
+        run(&input, ".owner //= \"nobody\" | .owner"),
+        json(r#"["nobody"]"#)
+    );
+    assert_eq!(
+        run(&input, ".a.b[1] = true | .a"),
+        json(r#"[{"b": [null, true]}]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            "del(.items[] | select(.price > 5)) | [.items[].name]"
+        ),
+        json(r#"[["apple"]]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            ".items |= map(select(.tags | length > 0)) | .items | length"
+        ),
+        json("[2]")
+    );
+    assert_eq!(
+        eval_err(&input, "(.items | length) = 1"),
+        "Invalid path expression with result number (3)"
+    );
+}
+
+#[test]
+fn test_slice_paths() {
+    let input = json("[1, 2, 3, 4, 5]");
+    assert_eq!(
+        run(&input, "path(.[1:]), path(.[:-1][0])"),
+        json(r#"[[{"start": 1, "end": null}], [{"start": null, "end": -1}, 0]]"#)
+    );
+    assert_eq!(
+        run(&input, ".[2:4] |= map(. * 10)"),
+        json("[[1, 2, 30, 40, 5]]")
+    );
+    assert_eq!(run(&input, ".[1:] = [\"x\"]"), json(r#"[[1, "x"]]"#));
+    assert_eq!(run(&input, ".[1:3][0] = 9"), json("[[1, 9, 3, 4, 5]]"));
+    assert_eq!(
+        run(&input, "del(.[1:3]), (.[:2] |= empty), del(.[3:][0])"),
+        json("[[1, 4, 5], [3, 4, 5], [1, 2, 3, 5]]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#"getpath([{"start": -2, "end": null}]), (null | .[1:] = [1])"#
+        ),
+        json("[[4, 5], [1]]")
+    );
+    assert_eq!(
+        eval_err(&input, ".[1:] = 5"),
+        "A slice of an array can only be assigned another array"
+    );
+    assert_eq!(
+        eval_err(&input, "\"abc\" | .[1:] = \"x\""),
+        "Cannot update a slice of string (\"abc\")"
+    );
+}
+
+#[test]
+fn test_collection_builtins() {
+    let input = inventory();
+    assert_eq!(
+        run(&input, ".items | sort_by(.price) | map(.name)"),
+        json(r#"[["apple", "cherry", "bread"]]"#)
+    );
+    assert_eq!(
+        run(&input, ".items | group_by(.tags | length) | map(length)"),
+        json("[[1, 1, 1]]")
+    );
+    assert_eq!(
+        run(&input, ".items | min_by(.price).name, max_by(.price).name"),
+        json(r#"["apple", "bread"]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[.items[].tags[]] | unique, add, (map(ascii_upcase) | join(\"-\"))"
+        ),
+        json(r#"[["fruit", "red"], "fruitredfruit", "FRUIT-RED-FRUIT"]"#)
+    );
+    assert_eq!(
+        run(&input, ".items[0] | to_entries | map(.key)"),
+        json(r#"[["name", "price", "tags"]]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#".items[0] | with_entries(select(.key != "tags"))"#
+        ),
+        json(r#"[{"name": "apple", "price": 3}]"#)
+    );
+    assert_eq!(
+        run(&input, "[1, [2, [3]]] | flatten, flatten(1)"),
+        json("[[1, 2, 3], [1, 2, [3]]]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[range(5)] | any(. > 3), all(. > 3), [limit(2; .[])], first(.[]), last"
+        ),
+        json("[true, false, [0, 1], 0, 4]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[limit(-1; 1, 2, 3)], [limit(0; 1, 2)], [limit(1.5; 1, 2, 3)]"
+        ),
+        json("[[1, 2, 3], [], [1, 2]]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#"["a", "b"] | to_entries, with_entries(.value += "!")"#
+        ),
+        json(r#"[[{"key": 0, "value": "a"}, {"key": 1, "value": "b"}], {"0": "a!", "1": "b!"}]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#".items[0] | has("name"), contains({tags: ["red"]})"#
+        ),
+        json("[true, true]")
+    );
+}
+
+#[test]
+fn test_string_builtins() {
+    let input = inventory();
+    assert_eq!(
+        run(&input, ".version | split(\".\") | map(tonumber)"),
+        json("[[1, 4, 2]]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#".version | test("^1\\."), sub("\\."; "_"), gsub("\\."; "_")"#
+        ),
+        json(r#"[true, "1_4.2", "1_4_2"]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#".version | capture("(?<major>\\d+)\\.(?<minor>\\d+)")"#
+        ),
+        json(r#"[{"major": "1", "minor": "4"}]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#""a-b" | startswith("a"), endswith("a"), ltrimstr("a-"), ascii_upcase"#
+        ),
+        json(r#"[true, false, "b", "A-B"]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[.items[].price | tostring], (.items[0] | tojson | fromjson | .name)"
+        ),
+        json(r#"[["3", "12", "8"], "apple"]"#)
+    );
+}
+
+#[test]
+fn test_explode_and_implode() {
+    let input = JVal::JNull;
+    assert_eq!(
+        run(
+            &input,
+            r#""aé😀" | explode, (explode | implode), ([65.0] | implode)"#
+        ),
+        json(r#"[[97, 233, 128512], "aé😀", "A"]"#)
+    );
+    for codepoint in ["-1", "1.5", "55296", "1114112", "\"a\""] {
+        assert!(
+            eval_err(&input, &format!("[{}] | implode", codepoint))
+                .starts_with("Invalid codepoint"),
+            "{}",
+            codepoint
+        );
+    }
+}
+
+#[test]
+fn test_limit_stops_generators_early() {
+    let input = JVal::JNull;
+    assert_eq!(run(&input, "first(range(1; 1e9))"), json("[1]"));
+    assert_eq!(
+        run(&input, "[limit(5; 1 | recurse(. + 1))]"),
+        json("[[1, 2, 3, 4, 5]]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            "[limit(3; range(1e19) | select(. % 2 == 1) | . * 10)]"
+        ),
+        json("[[10, 30, 50]]")
+    );
+    assert_eq!(
+        run(&input, "[limit(2; foreach range(1e9) as $n (0; . + $n))]"),
+        json("[[0, 1]]")
+    );
+    assert_eq!(
+        run(&input, "[limit(2; limit(4; range(10)), 99)]"),
+        json("[[0, 1]]")
+    );
+    assert_eq!(
+        run(&input, "[limit(2; 1, 2, error(\"unused\"))]"),
+        json("[[1, 2]]")
+    );
+    assert_eq!(
+        run(
+            &input,
+            "first(range(1e9) | try (if . > 2 then . else error end))"
+        ),
+        json("[3]")
+    );
+    // Errors raised after `try` or `//` hand a value on are not theirs to catch
+    assert_eq!(eval_err(&input, "(try (1, 2)) | error(\"late\")"), "late");
+    assert_eq!(eval_err(&input, "(1 // 2) | error(\"late\")"), "late");
+    assert_eq!(
+        run(&input, "first((null, false, 3, error) // 0)"),
+        json("[3]")
+    );
+    assert_eq!(
+        run(&input, "{a: [1, 2, 3]} | del(first(.a[]))"),
+        json(r#"[{"a": [2, 3]}]"#)
+    );
+}
+
+#[test]
+fn test_paths_builtins() {
+    let input = json(r#"{"a": [1, {"b": 2}]}"#);
+    assert_eq!(
+        run(&input, "[paths]"),
+        json(r#"[[["a"], ["a", 0], ["a", 1], ["a", 1, "b"]]]"#)
+    );
+    assert_eq!(
+        run(&input, "[leaf_paths]"),
+        json(r#"[[["a", 0], ["a", 1, "b"]]]"#)
+    );
+    assert_eq!(
+        run(&input, "path(.a[1].b), ([path(..)] | length)"),
+        json(r#"[["a", 1, "b"], 5]"#)
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#"getpath(["a", 1, "b"]), setpath(["a", 0]; 9).a[0]"#
+        ),
+        json("[2, 9]")
+    );
+    assert_eq!(
+        run(&input, r#"delpaths([["a", 0]])"#),
+        json(r#"[{"a": [{"b": 2}]}]"#)
+    );
+    assert_eq!(
+        run(&input, "walk(if type == \"number\" then . + 1 else . end)"),
+        json(r#"[{"a": [2, {"b": 3}]}]"#)
+    );
+}
+
+#[test]
+fn test_formats() {
+    let input = json(r#"["a,b", 1, null, "it's"]"#);
+    assert_eq!(
+        run(&input, "@csv, @tsv, @sh, @json"),
+        json(
+            r#"["\"a,b\",1,,\"it's\"", "a,b\t1\t\tit's", "'a,b' 1 null 'it'\\''s'", "[\"a,b\",1,null,\"it's\"]"]"#
+        )
+    );
+    assert_eq!(
+        run(
+            &input,
+            r#".[0] | @base64, (@base64 | @base64d), @uri, @html "<\(.)>""#
+        ),
+        json(r#"["YSxi", "a,b", "a%2Cb", "<a,b>"]"#)
+    );
+    assert_eq!(
+        run(&input, r#""<&>" | @html"#),
+        json(r#"["&lt;&amp;&gt;"]"#)
+    );
+}
+
+#[test]
+fn test_variables_from_caller() {
+    let filter = Filter::parse_with_variables(".[] | select(. > $min)", &["min"]).unwrap();
+    let outputs = filter
+        .apply_with_variables(&json("[1, 5, 10]"), &[("min".to_string(), JVal::from(4))])
+        .unwrap();
+    assert_eq!(outputs, [JVal::from(5), JVal::from(10)]);
+    assert_eq!(run(&JVal::JNull, "$ENV | type"), json(r#"["object"]"#));
+}
+
+#[test]
+fn test_comments_and_whitespace() {
+    let filter = "
+        # keep the cheap ones
+        .items[]
+        | select(.price < 5)   # by price
+        | .name
+    ";
+    assert_eq!(run(&inventory(), filter), json(r#"["apple"]"#));
+}
+
+#[test]
+fn test_syntax_errors() {
+    assert_eq!(
+        parse_err(".a |"),
+        FilterError::UnexpectedEnd {
+            expected: "a filter",
+            offset: 4
+        }
+    );
+    assert_eq!(
+        parse_err("if . then 1"),
+        FilterError::UnexpectedEnd {
+            expected: "'elif', 'else' or 'end'",
+            offset: 11
+        }
+    );
+    assert_eq!(
+        parse_err("map(.)  | nope(1)"),
+        FilterError::UnknownFunction {
+            name: "nope".to_string(),
+            arity: 1,
+            offset: 10
+        }
+    );
+    assert_eq!(
+        parse_err("[.[] | $x]"),
+        FilterError::UndefinedVariable {
+            name: "x".to_string(),
+            offset: 7
+        }
+    );
+    assert_eq!(
+        parse_err("@yaml"),
+        FilterError::UnknownFormat {
+            name: "yaml".to_string(),
+            offset: 0
+        }
+    );
+    assert_eq!(
+        parse_err(r#""\q""#),
+        FilterError::InvalidEscape { offset: 1 }
+    );
+    assert_eq!(
+        parse_err(".a .b").to_string(),
+        "Expected an operator or the end of the filter, found '.' at offset 3"
+    );
+    assert_eq!(
+        parse_err("1 + then").to_string(),
+        "Expected a filter, found 'then' at offset 4"
+    );
+}
@@ -0,0 +1,439 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::error::QueryError;
+use crate::parser::JVal;
+
+mod syntax;
+#[cfg(test)]
+mod tests;
+
+/// A compiled JSONPath query (RFC 9535), e.g. `$.store.book[?@.price < 10].title`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+/// A node selected by a query, along with its normalized path, e.g. `$['items'][0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryMatch<'a> {
+    pub path: String,
+    pub value: &'a JVal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+    /// `..`, which applies its selectors to a node and every node below it.
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(LogicalExpr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LogicalExpr {
+    Or(Vec<LogicalExpr>),
+    And(Vec<LogicalExpr>),
+    Not(Box<LogicalExpr>),
+    Comparison(Comparable, CompareOp, Comparable),
+    /// A query used as a test, true when it selects at least one node.
+    Exists(FilterQuery),
+    /// A function returning a logical value, i.e. `match` or `search`.
+    Function(FunctionExpr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// One side of a comparison, which evaluates to a single value or to nothing.
+#[derive(Debug, Clone, PartialEq)]
+enum Comparable {
+    Literal(JVal),
+    /// Always singular, i.e. made of single name and index selectors only.
+    Query(FilterQuery),
+    Function(FunctionExpr),
+}
+
+/// A query inside a filter, relative to either the current node `@` or the root `$`.
+#[derive(Debug, Clone, PartialEq)]
+struct FilterQuery {
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FunctionExpr {
+    function: Function,
+    args: Vec<Comparable>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+/// One step of a normalized path.
+#[derive(Debug, Clone, Copy)]
+enum PathElement<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+type Node<'a> = (Vec<PathElement<'a>>, &'a JVal);
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath, QueryError> {
+        syntax::parse_query(query)
+    }
+
+    /// Returns every node the query selects, in the order RFC 9535 gives them.
+    pub fn query<'a>(&self, root: &'a JVal) -> Vec<QueryMatch<'a>> {
+        select(&self.segments, vec![(Vec::new(), root)], root)
+            .into_iter()
+            .map(|(path, value)| QueryMatch {
+                path: normalized_path(&path),
+                value,
+            })
+            .collect()
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = QueryError;
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(query)
+    }
+}
+
+impl JVal {
+    /// Compiles and runs a JSONPath query against this value.
+    pub fn query(&self, query: &str) -> Result<Vec<QueryMatch<'_>>, QueryError> {
+        Ok(JsonPath::parse(query)?.query(self))
+    }
+}
+
+fn select<'a>(segments: &[Segment], mut nodes: Vec<Node<'a>>, root: &'a JVal) -> Vec<Node<'a>> {
+    for segment in segments {
+        let mut selected = Vec::new();
+        for node in &nodes {
+            match segment {
+                Segment::Child(selectors) => {
+                    for selector in selectors {
+                        apply_selector(selector, node, root, &mut selected);
+                    }
+                }
+                Segment::Descendant(selectors) => {
+                    for descendant in descendants(node) {
+                        for selector in selectors {
+                            apply_selector(selector, &descendant, root, &mut selected);
+                        }
+                    }
+                }
+            }
+        }
+        nodes = selected;
+    }
+    nodes
+}
+
+/// The node itself followed by everything below it, in document order.
+fn descendants<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    let mut visited = Vec::new();
+    let mut stack = vec![node.clone()];
+    while let Some(node) = stack.pop() {
+        let start = stack.len();
+        stack.extend(children(&node));
+        // Reversed so the first child is visited next
+        stack[start..].reverse();
+        visited.push(node);
+    }
+    visited
+}
+
+fn children<'a>((path, value): &Node<'a>) -> Vec<Node<'a>> {
+    match value {
+        JVal::JObject(map) => map
+            .iter()
+            .map(|(key, child)| (child_path(path, PathElement::Key(key)), child))
+            .collect(),
+        JVal::JArray(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, child)| (child_path(path, PathElement::Index(i)), child))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn child_path<'a>(path: &[PathElement<'a>], element: PathElement<'a>) -> Vec<PathElement<'a>> {
+    let mut path = path.to_vec();
+    path.push(element);
+    path
+}
+
+fn apply_selector<'a>(
+    selector: &Selector,
+    node: &Node<'a>,
+    root: &'a JVal,
+    selected: &mut Vec<Node<'a>>,
+) {
+    let (path, value) = node;
+    match (selector, value) {
+        (Selector::Name(name), JVal::JObject(map)) => {
+            if let Some((key, child)) = map.get_key_value(name) {
+                selected.push((child_path(path, PathElement::Key(key)), child));
+            }
+        }
+        (Selector::Wildcard, _) => selected.extend(children(node)),
+        (Selector::Index(index), JVal::JArray(items)) => {
+            if let Some(i) = normalize_index(*index, items.len()) {
+                selected.push((child_path(path, PathElement::Index(i)), &items[i]));
+            }
+        }
+        (Selector::Slice { start, end, step }, JVal::JArray(items)) => {
+            for i in slice_indices(*start, *end, *step, items.len()) {
+                selected.push((child_path(path, PathElement::Index(i)), &items[i]));
+            }
+        }
+        (Selector::Filter(expr), JVal::JObject(_) | JVal::JArray(_)) => selected.extend(
+            children(node)
+                .into_iter()
+                .filter(|(_, child)| evaluate(expr, child, root)),
+        ),
+        _ => {}
+    }
+}
+
+/// Resolves a possibly negative index, counting back from the end.
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let index = if index < 0 { len + index } else { index };
+    (0..len).contains(&index).then_some(index as usize)
+}
+
+fn slice_indices(
+    start: Option<i64>,
+    end: Option<i64>,
+    step: Option<i64>,
+    len: usize,
+) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+fn evaluate(expr: &LogicalExpr, current: &JVal, root: &JVal) -> bool {
+    match expr {
+        LogicalExpr::Or(exprs) => exprs.iter().any(|expr| evaluate(expr, current, root)),
+        LogicalExpr::And(exprs) => exprs.iter().all(|expr| evaluate(expr, current, root)),
+        LogicalExpr::Not(expr) => !evaluate(expr, current, root),
+        LogicalExpr::Comparison(left, op, right) => {
+            let left = comparable_value(left, current, root);
+            let right = comparable_value(right, current, root);
+            compare(left.as_deref(), *op, right.as_deref())
+        }
+        LogicalExpr::Exists(query) => !filter_query(query, current, root).is_empty(),
+        LogicalExpr::Function(function) => call_logical(function, current, root),
+    }
+}
+
+/// Evaluates a comparison; a side that selected nothing only equals another such side.
+fn compare(left: Option<&JVal>, op: CompareOp, right: Option<&JVal>) -> bool {
+    match op {
+        CompareOp::Eq => left == right,
+        CompareOp::Ne => left != right,
+        CompareOp::Lt => less_than(left, right),
+        CompareOp::Le => less_than(left, right) || left == right,
+        CompareOp::Gt => less_than(right, left),
+        CompareOp::Ge => less_than(right, left) || left == right,
+    }
+}
+
+// Only numbers and strings are ordered; any other pair is neither less nor greater
+fn less_than(left: Option<&JVal>, right: Option<&JVal>) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) if left.is_number() && right.is_number() => {
+            left.as_number() < right.as_number()
+        }
+        (Some(JVal::JString(left)), Some(JVal::JString(right))) => left < right,
+        _ => false,
+    }
+}
+
+fn filter_query<'a>(query: &FilterQuery, current: &'a JVal, root: &'a JVal) -> Vec<Node<'a>> {
+    let start = if query.relative { current } else { root };
+    select(&query.segments, vec![(Vec::new(), start)], root)
+}
+
+fn comparable_value<'a>(
+    comparable: &'a Comparable,
+    current: &'a JVal,
+    root: &'a JVal,
+) -> Option<Cow<'a, JVal>> {
+    match comparable {
+        Comparable::Literal(value) => Some(Cow::Borrowed(value)),
+        Comparable::Query(query) => filter_query(query, current, root)
+            .first()
+            .map(|&(_, value)| Cow::Borrowed(value)),
+        Comparable::Function(function) => call_value(function, current, root),
+    }
+}
+
+/// Calls `length`, `count` or `value`, whose results can be compared.
+fn call_value<'a>(
+    function: &'a FunctionExpr,
+    current: &'a JVal,
+    root: &'a JVal,
+) -> Option<Cow<'a, JVal>> {
+    let arg = &function.args[0];
+    match function.function {
+        Function::Length => {
+            let len = match comparable_value(arg, current, root)?.as_ref() {
+                JVal::JString(string) => string.chars().count(),
+                JVal::JArray(items) => items.len(),
+                JVal::JObject(map) => map.len(),
+                _ => return None,
+            };
+            Some(Cow::Owned(JVal::from(len)))
+        }
+        Function::Count => {
+            let count = nodes_arg(arg, current, root).len();
+            Some(Cow::Owned(JVal::from(count)))
+        }
+        Function::Value => match nodes_arg(arg, current, root)[..] {
+            [(_, value)] => Some(Cow::Borrowed(value)),
+            _ => None,
+        },
+        Function::Match | Function::Search => None,
+    }
+}
+
+/// Calls `match` or `search`, which test a string against an I-Regexp (RFC 9485).
+fn call_logical(function: &FunctionExpr, current: &JVal, root: &JVal) -> bool {
+    let text = comparable_value(&function.args[0], current, root);
+    let pattern = comparable_value(&function.args[1], current, root);
+    let (Some(JVal::JString(text)), Some(JVal::JString(pattern))) =
+        (text.as_deref(), pattern.as_deref())
+    else {
+        return false;
+    };
+
+    let pattern = translate_regex(pattern);
+    let pattern = match function.function {
+        Function::Match => format!("^(?:{})$", pattern),
+        _ => pattern,
+    };
+    // An invalid pattern matches nothing rather than failing the whole query
+    Regex::new(&pattern).is_ok_and(|regex| regex.is_match(text))
+}
+
+fn nodes_arg<'a>(arg: &'a Comparable, current: &'a JVal, root: &'a JVal) -> Vec<Node<'a>> {
+    match arg {
+        Comparable::Query(query) => filter_query(query, current, root),
+        // The parser only accepts queries where a node list is expected
+        _ => Vec::new(),
+    }
+}
+
+/// Rewrites an I-Regexp for the `regex` crate, where `.` would otherwise match `\r`.
+fn translate_regex(pattern: &str) -> String {
+    let mut translated = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                translated.push(ch);
+                translated.extend(chars.next());
+            }
+            '[' => {
+                in_class = true;
+                translated.push(ch);
+            }
+            ']' => {
+                in_class = false;
+                translated.push(ch);
+            }
+            '.' if !in_class => translated.push_str("[^\\n\\r]"),
+            ch => translated.push(ch),
+        }
+    }
+    translated
+}
+
+/// Formats a path as RFC 9535 normalizes it, e.g. `$['a'][0]`.
+fn normalized_path(path: &[PathElement]) -> String {
+    let mut normalized = String::from("$");
+    for element in path {
+        match element {
+            PathElement::Index(i) => {
+                let _ = write!(normalized, "[{}]", i);
+            }
+            PathElement::Key(key) => {
+                normalized.push_str("['");
+                for ch in key.chars() {
+                    match ch {
+                        '\'' => normalized.push_str("\\'"),
+                        '\\' => normalized.push_str("\\\\"),
+                        '\u{8}' => normalized.push_str("\\b"),
+                        '\u{c}' => normalized.push_str("\\f"),
+                        '\n' => normalized.push_str("\\n"),
+                        '\r' => normalized.push_str("\\r"),
+                        '\t' => normalized.push_str("\\t"),
+                        ch if ch < ' ' => {
+                            let _ = write!(normalized, "\\u{:04x}", ch as u32);
+                        }
+                        ch => normalized.push(ch),
+                    }
+                }
+                normalized.push_str("']");
+            }
+        }
+    }
+    normalized
+}
@@ -0,0 +1,572 @@
+use super::{
+    Comparable, CompareOp, FilterQuery, Function, FunctionExpr, JsonPath, LogicalExpr, Segment,
+    Selector,
+};
+use crate::error::QueryError;
+use crate::number::Number;
+use crate::parser::JVal;
+
+// Indexes and integers are limited to the range an `f64` holds exactly, as in I-JSON
+const MAX_INTEGER: i64 = (1 << 53) - 1;
+
+/// What a function parameter accepts.
+#[derive(Clone, Copy)]
+enum Parameter {
+    /// A literal, singular query or `length`/`count`/`value` call.
+    Value,
+    /// Any query.
+    Nodes,
+}
+
+pub(super) fn parse_query(query: &str) -> Result<JsonPath, QueryError> {
+    let mut parser = QueryParser { query, offset: 0 };
+    parser.expect('$', "'$' at the start of the query")?;
+    let segments = parser.segments()?;
+    match parser.peek() {
+        None => Ok(JsonPath { segments }),
+        Some(_) => Err(parser.unexpected("'[', '.' or the end of the query")),
+    }
+}
+
+struct QueryParser<'q> {
+    query: &'q str,
+    offset: usize,
+}
+
+impl QueryParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.query[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.offset += ch.len_utf8();
+        Some(ch)
+    }
+
+    /// Consumes `expected` if the rest of the query starts with it.
+    fn eat(&mut self, expected: &str) -> bool {
+        let matches = self.query[self.offset..].starts_with(expected);
+        if matches {
+            self.offset += expected.len();
+        }
+        matches
+    }
+
+    fn expect(&mut self, ch: char, expected: &'static str) -> Result<(), QueryError> {
+        if self.peek() == Some(ch) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.offset += 1;
+        }
+    }
+
+    /// Describes whatever is at the current offset as not being `expected`.
+    fn unexpected(&self, expected: &'static str) -> QueryError {
+        match self.peek() {
+            Some(found) => QueryError::UnexpectedCharacter {
+                found,
+                expected,
+                offset: self.offset,
+            },
+            None => QueryError::UnexpectedEnd {
+                expected,
+                offset: self.offset,
+            },
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>, QueryError> {
+        let mut segments = Vec::new();
+        loop {
+            let before = self.offset;
+            self.skip_whitespace();
+            match self.peek() {
+                Some('[' | '.') => segments.push(self.segment()?),
+                _ => {
+                    // The whitespace may separate the query from an operator instead
+                    self.offset = before;
+                    return Ok(segments);
+                }
+            }
+        }
+    }
+
+    fn segment(&mut self) -> Result<Segment, QueryError> {
+        if self.eat("..") {
+            let selectors = match self.peek() {
+                Some('[') => self.bracketed_selection()?,
+                Some('*') => {
+                    self.bump();
+                    vec![Selector::Wildcard]
+                }
+                _ => vec![Selector::Name(
+                    self.member_name("'[', '*' or a member name after '..'")?,
+                )],
+            };
+            Ok(Segment::Descendant(selectors))
+        } else if self.eat(".") {
+            if self.eat("*") {
+                Ok(Segment::Child(vec![Selector::Wildcard]))
+            } else {
+                let name = self.member_name("'*' or a member name after '.'")?;
+                Ok(Segment::Child(vec![Selector::Name(name)]))
+            }
+        } else {
+            Ok(Segment::Child(self.bracketed_selection()?))
+        }
+    }
+
+    /// Reads the name in shorthand like `.name`, which may not start with a digit.
+    fn member_name(&mut self, expected: &'static str) -> Result<String, QueryError> {
+        let start = self.offset;
+        match self.peek() {
+            Some(ch) if is_name_first(ch) => {}
+            _ => return Err(self.unexpected(expected)),
+        }
+        while matches!(self.peek(), Some(ch) if is_name_first(ch) || ch.is_ascii_digit()) {
+            self.bump();
+        }
+        Ok(self.query[start..self.offset].to_string())
+    }
+
+    fn bracketed_selection(&mut self) -> Result<Vec<Selector>, QueryError> {
+        self.expect('[', "'['")?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            if !self.eat(",") {
+                return Err(self.unexpected("',' or ']'"));
+            }
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, QueryError> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string_literal()?)),
+            Some('*') => {
+                self.bump();
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.bump();
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.logical_or()?))
+            }
+            Some(':' | '-' | '0'..='9') => self.index_or_slice(),
+            _ => Err(self.unexpected("a name, '*', index, slice or filter")),
+        }
+    }
+
+    fn index_or_slice(&mut self) -> Result<Selector, QueryError> {
+        let start = match self.peek() {
+            Some(':') => None,
+            _ => Some(self.integer()?),
+        };
+        let before = self.offset;
+        self.skip_whitespace();
+        if !self.eat(":") {
+            self.offset = before;
+            if let Some(index) = start {
+                return Ok(Selector::Index(index));
+            }
+        }
+
+        self.skip_whitespace();
+        let end = self.optional_integer()?;
+        self.skip_whitespace();
+        let step = if self.eat(":") {
+            self.skip_whitespace();
+            self.optional_integer()?
+        } else {
+            None
+        };
+        Ok(Selector::Slice { start, end, step })
+    }
+
+    fn optional_integer(&mut self) -> Result<Option<i64>, QueryError> {
+        match self.peek() {
+            Some('-' | '0'..='9') => Ok(Some(self.integer()?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads an index or slice bound, which may not have leading zeros or be `-0`.
+    fn integer(&mut self) -> Result<i64, QueryError> {
+        let start = self.offset;
+        self.eat("-");
+        let digits_start = self.offset;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.bump();
+        }
+        let text = &self.query[start..self.offset];
+        let digits = &self.query[digits_start..self.offset];
+        if digits.is_empty() {
+            return Err(self.unexpected("a digit"));
+        }
+
+        let invalid = || QueryError::InvalidInteger {
+            text: text.to_string(),
+            offset: start,
+        };
+        if (digits.starts_with('0') && digits.len() > 1) || text == "-0" {
+            return Err(invalid());
+        }
+        text.parse::<i64>()
+            .ok()
+            .filter(|value| value.abs() <= MAX_INTEGER)
+            .ok_or_else(invalid)
+    }
+
+    fn string_literal(&mut self) -> Result<String, QueryError> {
+        let quote = match self.peek() {
+            Some(quote @ ('\'' | '"')) => quote,
+            _ => return Err(self.unexpected("a quoted string")),
+        };
+        self.bump();
+        let mut string = String::new();
+        loop {
+            let char_offset = self.offset;
+            match self.bump() {
+                None => return Err(self.unexpected("closing quote of string")),
+                Some(ch) if ch == quote => return Ok(string),
+                Some('\\') => string.push(self.escape(quote, char_offset)?),
+                Some(found) if found < ' ' => {
+                    return Err(QueryError::UnexpectedCharacter {
+                        found,
+                        expected: "an escape sequence for a control character",
+                        offset: char_offset,
+                    })
+                }
+                Some(ch) => string.push(ch),
+            }
+        }
+    }
+
+    /// Decodes the escape after a backslash at `start`.
+    fn escape(&mut self, quote: char, start: usize) -> Result<char, QueryError> {
+        let invalid = QueryError::InvalidEscape { offset: start };
+        let unescaped = match self.bump() {
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some(ch @ ('/' | '\\')) => ch,
+            Some(ch) if ch == quote => ch,
+            Some('u') => {
+                let unit = self.hex_code_unit().ok_or(invalid.clone())?;
+                let code_point = match unit {
+                    0xD800..=0xDBFF => {
+                        if !self.eat("\\u") {
+                            return Err(invalid);
+                        }
+                        match self.hex_code_unit().ok_or(invalid.clone())? {
+                            low @ 0xDC00..=0xDFFF => {
+                                0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+                            }
+                            _ => return Err(invalid),
+                        }
+                    }
+                    _ => unit,
+                };
+                return char::from_u32(code_point).ok_or(invalid);
+            }
+            _ => return Err(invalid),
+        };
+        Ok(unescaped)
+    }
+
+    fn hex_code_unit(&mut self) -> Option<u32> {
+        let digits = self.query.get(self.offset..self.offset + 4)?;
+        if !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.offset += 4;
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    fn logical_or(&mut self) -> Result<LogicalExpr, QueryError> {
+        let mut exprs = vec![self.logical_and()?];
+        while self.eat_operator("||") {
+            exprs.push(self.logical_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            LogicalExpr::Or(exprs)
+        })
+    }
+
+    fn logical_and(&mut self) -> Result<LogicalExpr, QueryError> {
+        let mut exprs = vec![self.basic_expr()?];
+        while self.eat_operator("&&") {
+            exprs.push(self.basic_expr()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            LogicalExpr::And(exprs)
+        })
+    }
+
+    /// Consumes `operator` and the whitespace around it, or nothing.
+    fn eat_operator(&mut self, operator: &str) -> bool {
+        let before = self.offset;
+        self.skip_whitespace();
+        if self.eat(operator) {
+            self.skip_whitespace();
+            true
+        } else {
+            self.offset = before;
+            false
+        }
+    }
+
+    fn basic_expr(&mut self) -> Result<LogicalExpr, QueryError> {
+        if self.eat("!") {
+            self.skip_whitespace();
+            let expr = match self.peek() {
+                Some('(') => self.paren_expr()?,
+                _ => {
+                    let start = self.offset;
+                    let operand = self.operand()?;
+                    test_expr(operand, start)?
+                }
+            };
+            return Ok(LogicalExpr::Not(Box::new(expr)));
+        }
+        if self.peek() == Some('(') {
+            return self.paren_expr();
+        }
+
+        let start = self.offset;
+        let left = self.operand()?;
+        let before = self.offset;
+        self.skip_whitespace();
+        let Some(op) = self.comparison_op() else {
+            self.offset = before;
+            return test_expr(left, start);
+        };
+        self.skip_whitespace();
+        let right_start = self.offset;
+        let right = self.operand()?;
+        Ok(LogicalExpr::Comparison(
+            comparable(left, start)?,
+            op,
+            comparable(right, right_start)?,
+        ))
+    }
+
+    fn paren_expr(&mut self) -> Result<LogicalExpr, QueryError> {
+        self.expect('(', "'('")?;
+        self.skip_whitespace();
+        let expr = self.logical_or()?;
+        self.skip_whitespace();
+        self.expect(')', "')'")?;
+        Ok(expr)
+    }
+
+    fn comparison_op(&mut self) -> Option<CompareOp> {
+        // Two-character operators first so `<=` is not read as `<`
+        [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ]
+        .into_iter()
+        .find(|(text, _)| self.eat(text))
+        .map(|(_, op)| op)
+    }
+
+    /// Reads a literal, query or function call, without checking where it may be used.
+    fn operand(&mut self) -> Result<Comparable, QueryError> {
+        match self.peek() {
+            Some(root @ ('@' | '$')) => {
+                self.bump();
+                Ok(Comparable::Query(FilterQuery {
+                    relative: root == '@',
+                    segments: self.segments()?,
+                }))
+            }
+            Some('\'' | '"') => Ok(Comparable::Literal(JVal::JString(self.string_literal()?))),
+            Some('-' | '0'..='9') => Ok(Comparable::Literal(self.number()?)),
+            Some('a'..='z') => {
+                let start = self.offset;
+                while matches!(self.peek(), Some('a'..='z' | '0'..='9' | '_')) {
+                    self.bump();
+                }
+                let name = &self.query[start..self.offset];
+                if self.peek() == Some('(') {
+                    return Ok(Comparable::Function(self.function_call(name, start)?));
+                }
+                match name {
+                    "true" => Ok(Comparable::Literal(JVal::JBool(true))),
+                    "false" => Ok(Comparable::Literal(JVal::JBool(false))),
+                    "null" => Ok(Comparable::Literal(JVal::JNull)),
+                    _ => {
+                        self.offset = start;
+                        Err(self.unexpected("a query, literal or function call"))
+                    }
+                }
+            }
+            _ => Err(self.unexpected("a query, literal or function call")),
+        }
+    }
+
+    fn number(&mut self) -> Result<JVal, QueryError> {
+        let start = self.offset;
+        self.eat("-");
+        if !self.eat("0") {
+            self.digits()?;
+        }
+        if self.eat(".") {
+            self.digits()?;
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.bump();
+            if !self.eat("+") {
+                self.eat("-");
+            }
+            self.digits()?;
+        }
+        let num: Number = self.query[start..self.offset]
+            .parse()
+            .expect("the number grammar was checked above");
+        Ok(JVal::from(num))
+    }
+
+    fn digits(&mut self) -> Result<(), QueryError> {
+        if !matches!(self.peek(), Some('0'..='9')) {
+            return Err(self.unexpected("a digit"));
+        }
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.bump();
+        }
+        Ok(())
+    }
+
+    fn function_call(&mut self, name: &str, start: usize) -> Result<FunctionExpr, QueryError> {
+        let (function, name, parameters): (_, _, &[Parameter]) = match name {
+            "length" => (Function::Length, "length", &[Parameter::Value]),
+            "count" => (Function::Count, "count", &[Parameter::Nodes]),
+            "match" => (Function::Match, "match", &[Parameter::Value; 2]),
+            "search" => (Function::Search, "search", &[Parameter::Value; 2]),
+            "value" => (Function::Value, "value", &[Parameter::Nodes]),
+            _ => {
+                return Err(QueryError::UnknownFunction {
+                    name: name.to_string(),
+                    offset: start,
+                })
+            }
+        };
+
+        self.expect('(', "'('")?;
+        self.skip_whitespace();
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                self.skip_whitespace();
+                args.push((self.offset, self.operand()?));
+                self.skip_whitespace();
+                if self.eat(")") {
+                    break;
+                }
+                if !self.eat(",") {
+                    return Err(self.unexpected("',' or ')'"));
+                }
+            }
+        }
+
+        if args.len() != parameters.len() {
+            return Err(QueryError::WrongArgumentCount {
+                function: name,
+                expected: parameters.len(),
+                offset: start,
+            });
+        }
+        let args = args
+            .into_iter()
+            .zip(parameters)
+            .map(|((offset, arg), parameter)| match (parameter, arg) {
+                (Parameter::Value, arg) => comparable(arg, offset),
+                (Parameter::Nodes, arg @ Comparable::Query(_)) => Ok(arg),
+                (Parameter::Nodes, _) => Err(QueryError::TypeMismatch {
+                    message: "count() and value() take a query",
+                    offset,
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FunctionExpr { function, args })
+    }
+}
+
+/// name-first in RFC 9535: letters, `_` and anything outside ASCII.
+fn is_name_first(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_' || !ch.is_ascii()
+}
+
+fn is_logical(function: Function) -> bool {
+    matches!(function, Function::Match | Function::Search)
+}
+
+/// Whether a query can select at most one node.
+fn is_singular(query: &FilterQuery) -> bool {
+    query.segments.iter().all(|segment| {
+        matches!(
+            segment,
+            Segment::Child(selectors)
+                if matches!(selectors[..], [Selector::Name(_) | Selector::Index(_)])
+        )
+    })
+}
+
+/// Checks that an operand can be one side of a comparison.
+fn comparable(operand: Comparable, offset: usize) -> Result<Comparable, QueryError> {
+    match &operand {
+        Comparable::Query(query) if !is_singular(query) => Err(QueryError::TypeMismatch {
+            message: "A query used as a value must select at most one node",
+            offset,
+        }),
+        Comparable::Function(function) if is_logical(function.function) => {
+            Err(QueryError::TypeMismatch {
+                message: "The result of match() or search() cannot be used as a value",
+                offset,
+            })
+        }
+        _ => Ok(operand),
+    }
+}
+
+/// Turns an operand that is not compared into a test of whether it holds.
+fn test_expr(operand: Comparable, offset: usize) -> Result<LogicalExpr, QueryError> {
+    match operand {
+        Comparable::Query(query) => Ok(LogicalExpr::Exists(query)),
+        Comparable::Function(function) if is_logical(function.function) => {
+            Ok(LogicalExpr::Function(function))
+        }
+        Comparable::Function(_) => Err(QueryError::TypeMismatch {
+            message: "The result of length(), count() or value() must be compared",
+            offset,
+        }),
+        Comparable::Literal(_) => Err(QueryError::TypeMismatch {
+            message: "A literal must be compared",
+            offset,
+        }),
+    }
+}
@@ -0,0 +1,392 @@
+use super::*;
+use crate::parser::from_str;
+
+fn bookstore() -> JVal {
+    from_str(
+        r#"{ "store": {
+            "book": [
+              { "category": "reference", "author": "Nigel Rees",
+                "title": "Sayings of the Century", "price": 8.95 },
+              { "category": "fiction", "author": "Evelyn Waugh",
+                "title": "Sword of Honour", "price": 12.99 },
+              { "category": "fiction", "author": "Herman Melville",
+                "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+              { "category": "fiction", "author": "J. R. R. Tolkien",
+                "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+            ],
+            "bicycle": { "color": "red", "price": 399 }
+          } }"#,
+    )
+    .unwrap()
+}
+
+/// Runs `query` and returns the normalized paths of the matches.
+fn paths(value: &JVal, query: &str) -> Vec<String> {
+    value
+        .query(query)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .into_iter()
+        .map(|found| found.path)
+        .collect()
+}
+
+fn values(value: &JVal, query: &str) -> JVal {
+    value
+        .query(query)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .into_iter()
+        .map(|found| found.value.clone())
+        .collect()
+}
+
+fn query_err(query: &str) -> QueryError {
+    JsonPath::parse(query).expect_err(query)
+}
+
+#[test]
+fn test_rfc_9535_bookstore_examples() {
+    let store = bookstore();
+    assert_eq!(
+        values(&store, "$.store.book[*].author"),
+        from_str(r#"["Nigel Rees", "Evelyn Waugh", "Herman Melville", "J. R. R. Tolkien"]"#)
+            .unwrap()
+    );
+    assert_eq!(
+        values(&store, "$..author"),
+        values(&store, "$.store.book[*].author")
+    );
+    assert_eq!(
+        paths(&store, "$.store.*"),
+        ["$['store']['book']", "$['store']['bicycle']"]
+    );
+    assert_eq!(
+        values(&store, "$.store..price"),
+        from_str("[8.95, 12.99, 8.99, 22.99, 399]").unwrap()
+    );
+    assert_eq!(paths(&store, "$..book[2]"), ["$['store']['book'][2]"]);
+    assert_eq!(paths(&store, "$..book[-1]"), ["$['store']['book'][3]"]);
+    assert_eq!(
+        paths(&store, "$..book[0,1]"),
+        ["$['store']['book'][0]", "$['store']['book'][1]"]
+    );
+    assert_eq!(paths(&store, "$..book[:2]"), paths(&store, "$..book[0,1]"));
+    assert_eq!(
+        values(&store, "$..book[?@.isbn].title"),
+        from_str(r#"["Moby Dick", "The Lord of the Rings"]"#).unwrap()
+    );
+    assert_eq!(
+        values(&store, "$..book[?@.price<10].title"),
+        from_str(r#"["Sayings of the Century", "Moby Dick"]"#).unwrap()
+    );
+    assert_eq!(paths(&store, "$..*").len(), 27);
+}
+
+#[test]
+fn test_filter_with_parentheses() {
+    let value = from_str(r#"{"items": [{"price": 5}, {"price": 15}, {"price": 7}]}"#).unwrap();
+    assert_eq!(
+        paths(&value, "$.items[?(@.price < 10)]"),
+        ["$['items'][0]", "$['items'][2]"]
+    );
+}
+
+#[test]
+fn test_normalized_path_escaping() {
+    let value = from_str(r#"{"it's": {"a\\b": {"\n\u0001": 1}}}"#).unwrap();
+    assert_eq!(
+        paths(&value, "$..*"),
+        [
+            r"$['it\'s']",
+            r"$['it\'s']['a\\b']",
+            r"$['it\'s']['a\\b']['\n\u0001']",
+        ]
+    );
+}
+
+#[test]
+fn test_root_and_scalar_documents() {
+    let value = from_str("42").unwrap();
+    assert_eq!(paths(&value, "$"), ["$"]);
+    assert!(paths(&value, "$.a").is_empty());
+    assert!(paths(&value, "$[0]").is_empty());
+    assert!(paths(&value, "$.*").is_empty());
+}
+
+#[test]
+fn test_name_selectors() {
+    let value = from_str(r#"{"a b": 1, "o": {"j j": {"k.k": 3}}, "'": 4, "é": 5}"#).unwrap();
+    assert_eq!(values(&value, "$['a b']"), from_str("[1]").unwrap());
+    assert_eq!(
+        values(&value, r#"$.o["j j"]['k.k']"#),
+        from_str("[3]").unwrap()
+    );
+    assert_eq!(values(&value, r"$['\'']"), from_str("[4]").unwrap());
+    assert_eq!(values(&value, "$.é"), from_str("[5]").unwrap());
+    assert_eq!(values(&value, r"$['é']"), from_str("[5]").unwrap());
+    assert_eq!(
+        values(&value, "$['a b', 'missing', '\\'']"),
+        from_str("[1, 4]").unwrap()
+    );
+}
+
+#[test]
+fn test_index_and_slice_selectors() {
+    let value = from_str(r#"["a", "b", "c", "d", "e", "f", "g"]"#).unwrap();
+    let letters = |query| values(&value, query);
+    assert_eq!(letters("$[1]"), from_str(r#"["b"]"#).unwrap());
+    assert_eq!(letters("$[-2]"), from_str(r#"["f"]"#).unwrap());
+    assert_eq!(letters("$[7]"), from_str("[]").unwrap());
+    assert_eq!(letters("$[-8]"), from_str("[]").unwrap());
+    assert_eq!(letters("$[1:3]"), from_str(r#"["b", "c"]"#).unwrap());
+    assert_eq!(letters("$[5:]"), from_str(r#"["f", "g"]"#).unwrap());
+    assert_eq!(letters("$[1:5:2]"), from_str(r#"["b", "d"]"#).unwrap());
+    assert_eq!(letters("$[5:1:-2]"), from_str(r#"["f", "d"]"#).unwrap());
+    assert_eq!(
+        letters("$[::-1]"),
+        from_str(r#"["g", "f", "e", "d", "c", "b", "a"]"#).unwrap()
+    );
+    assert_eq!(
+        letters("$[ -3 : ]"),
+        from_str(r#"["e", "f", "g"]"#).unwrap()
+    );
+    assert_eq!(letters("$[::0]"), from_str("[]").unwrap());
+    assert_eq!(
+        letters("$[0, 0, -1]"),
+        from_str(r#"["a", "a", "g"]"#).unwrap()
+    );
+}
+
+#[test]
+fn test_descendant_order() {
+    let value = from_str(r#"{"o": {"j": 1, "k": 2}, "a": [5, 3, [{"j": 4}, {"k": 6}]]}"#).unwrap();
+    assert_eq!(paths(&value, "$..j"), ["$['o']['j']", "$['a'][2][0]['j']"]);
+    assert_eq!(paths(&value, "$..[0]"), ["$['a'][0]", "$['a'][2][0]"]);
+}
+
+#[test]
+fn test_comparisons() {
+    let value = from_str(
+        r#"[{"a": 1}, {"a": "b"}, {"a": null}, {"a": true}, {"a": [1]}, {"a": {"x": 1}}, {}]"#,
+    )
+    .unwrap();
+    let indices = |query| {
+        value
+            .query(query)
+            .unwrap()
+            .into_iter()
+            .map(|found| found.path)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(indices("$[?@.a == 1]"), "$[0]");
+    assert_eq!(indices("$[?@.a == 1.0]"), "$[0]");
+    assert_eq!(indices("$[?@.a == 'b']"), "$[1]");
+    assert_eq!(indices("$[?@.a == null]"), "$[2]");
+    assert_eq!(indices("$[?@.a == true]"), "$[3]");
+    assert_eq!(indices("$[?@.a == $[4].a]"), "$[4]");
+    assert_eq!(indices("$[?@.a == $[5].a]"), "$[5]");
+    assert_eq!(indices("$[?@.a == @.missing]"), "$[6]");
+    assert_eq!(indices("$[?@.a != 1]"), "$[1] $[2] $[3] $[4] $[5] $[6]");
+    assert_eq!(indices("$[?@.a < 2]"), "$[0]");
+    assert_eq!(indices("$[?@.a >= 'a']"), "$[1]");
+    assert_eq!(indices("$[?@.a <= null]"), "$[2]");
+    assert_eq!(indices("$[?@.a > true]"), "");
+}
+
+#[test]
+fn test_logical_operators() {
+    let value = from_str(r#"[{"a": 1, "b": 2}, {"a": 1}, {"b": 2}, {}]"#).unwrap();
+    assert_eq!(paths(&value, "$[?@.a && @.b]"), ["$[0]"]);
+    assert_eq!(paths(&value, "$[?@.a || @.b]"), ["$[0]", "$[1]", "$[2]"]);
+    assert_eq!(paths(&value, "$[?!@.a]"), ["$[2]", "$[3]"]);
+    assert_eq!(paths(&value, "$[?!(@.a || @.b)]"), ["$[3]"]);
+    assert_eq!(
+        paths(&value, "$[?@.a == 1 && !(@.b == 2) || @.b == 2 && !@.a]"),
+        ["$[1]", "$[2]"]
+    );
+    assert_eq!(paths(&value, "$[? ( @.a ) ]"), ["$[0]", "$[1]"]);
+}
+
+#[test]
+fn test_filter_on_object_members_and_root() {
+    let value = from_str(r#"{"limit": 2, "xs": {"p": 1, "q": 3, "r": 2}}"#).unwrap();
+    assert_eq!(
+        paths(&value, "$.xs[?@ <= $.limit]"),
+        ["$['xs']['p']", "$['xs']['r']"]
+    );
+    assert_eq!(paths(&value, "$[?@.q]"), ["$['xs']"]);
+}
+
+#[test]
+fn test_functions() {
+    let value = from_str(
+        r#"[{"s": "abc", "l": [1, 2], "o": {"x": 1}}, {"s": "a\nb", "l": []}, {"s": "bab"}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        paths(&value, "$[?length(@.s) == 3]"),
+        ["$[0]", "$[1]", "$[2]"]
+    );
+    assert_eq!(paths(&value, "$[?length(@.l) > 1]"), ["$[0]"]);
+    assert_eq!(paths(&value, "$[?length(@.o) == 1]"), ["$[0]"]);
+    assert_eq!(paths(&value, "$[?count(@.*) == 2]"), ["$[1]"]);
+    assert_eq!(paths(&value, "$[?value(@..x) == 1]"), ["$[0]"]);
+    assert_eq!(paths(&value, "$[?match(@.s, 'a.c')]"), ["$[0]"]);
+    assert_eq!(paths(&value, "$[?match(@.s, 'a.b')]"), Vec::<String>::new());
+    assert_eq!(
+        paths(&value, "$[?search(@.s, 'b')]"),
+        ["$[0]", "$[1]", "$[2]"]
+    );
+    assert_eq!(paths(&value, "$[?search(@.s, '^b')]"), ["$[2]"]);
+    assert_eq!(paths(&value, "$[?!match(@.s, '[ab]+')]"), ["$[0]", "$[1]"]);
+    assert_eq!(paths(&value, "$[?match(@.s, '(')]"), Vec::<String>::new());
+}
+
+#[test]
+fn test_syntax_errors() {
+    assert_eq!(
+        query_err("a"),
+        QueryError::UnexpectedCharacter {
+            found: 'a',
+            expected: "'$' at the start of the query",
+            offset: 0
+        }
+    );
+    assert_eq!(
+        query_err("$.a["),
+        QueryError::UnexpectedEnd {
+            expected: "a name, '*', index, slice or filter",
+            offset: 4
+        }
+    );
+    assert_eq!(
+        query_err("$[1 2]"),
+        QueryError::UnexpectedCharacter {
+            found: '2',
+            expected: "',' or ']'",
+            offset: 4
+        }
+    );
+    assert!(matches!(
+        query_err("$.1"),
+        QueryError::UnexpectedCharacter { offset: 2, .. }
+    ));
+    assert!(matches!(
+        query_err("$. a"),
+        QueryError::UnexpectedCharacter { offset: 2, .. }
+    ));
+    assert!(matches!(
+        query_err("$['a'"),
+        QueryError::UnexpectedEnd { .. }
+    ));
+    assert!(matches!(
+        query_err("$['a]"),
+        QueryError::UnexpectedEnd { .. }
+    ));
+    assert_eq!(
+        query_err(r"$['\x']"),
+        QueryError::InvalidEscape { offset: 3 }
+    );
+    assert_eq!(
+        query_err(r"$['\ud800']"),
+        QueryError::InvalidEscape { offset: 3 }
+    );
+    assert!(matches!(
+        query_err("$[?@.a = 1]"),
+        QueryError::UnexpectedCharacter { .. }
+    ));
+    assert!(matches!(
+        query_err("$[?@.a == ]"),
+        QueryError::UnexpectedCharacter { .. }
+    ));
+}
+
+#[test]
+fn test_integer_errors() {
+    for (query, text) in [
+        ("$[01]", "01"),
+        ("$[-0]", "-0"),
+        ("$[9007199254740992]", "9007199254740992"),
+        ("$[0:-01]", "-01"),
+    ] {
+        assert!(
+            matches!(query_err(query), QueryError::InvalidInteger { text: found, .. } if found == text),
+            "{}",
+            query
+        );
+    }
+    assert!(JsonPath::parse("$[-9007199254740991]").is_ok());
+}
+
+#[test]
+fn test_type_errors() {
+    assert_eq!(
+        query_err("$[?@.* == 1]"),
+        QueryError::TypeMismatch {
+            message: "A query used as a value must select at most one node",
+            offset: 3
+        }
+    );
+    assert!(matches!(
+        query_err("$[?@..a == 1]"),
+        QueryError::TypeMismatch { .. }
+    ));
+    assert!(matches!(
+        query_err("$[?length(@.a)]"),
+        QueryError::TypeMismatch { .. }
+    ));
+    assert!(matches!(
+        query_err("$[?match(@.a, 'x') == true]"),
+        QueryError::TypeMismatch { .. }
+    ));
+    assert!(matches!(
+        query_err("$[?1]"),
+        QueryError::TypeMismatch { .. }
+    ));
+    assert!(matches!(
+        query_err("$[?count(1) == 1]"),
+        QueryError::TypeMismatch { .. }
+    ));
+    assert!(matches!(
+        query_err("$[?length(@.*) == 1]"),
+        QueryError::TypeMismatch { .. }
+    ));
+    assert_eq!(
+        query_err("$[?length(@.a, 1) == 1]"),
+        QueryError::WrongArgumentCount {
+            function: "length",
+            expected: 1,
+            offset: 3
+        }
+    );
+    assert_eq!(
+        query_err("$[?foo(@.a)]"),
+        QueryError::UnknownFunction {
+            name: "foo".to_string(),
+            offset: 3
+        }
+    );
+}
+
+#[test]
+fn test_error_message() {
+    assert_eq!(
+        query_err("$[1 2]").to_string(),
+        "Expected ',' or ']', found '2' at offset 4"
+    );
+}
+
+#[test]
+fn test_compiled_query_is_reusable() {
+    let query: JsonPath = "$[*].a".parse().unwrap();
+    let first = from_str(r#"[{"a": 1}, {"a": 2}]"#).unwrap();
+    let second = from_str(r#"[{"a": 3}]"#).unwrap();
+    assert_eq!(query.query(&first).len(), 2);
+    assert_eq!(
+        query.query(&second),
+        [QueryMatch {
+            path: "$[0]['a']".to_string(),
+            value: &JVal::from(3)
+        }]
+    );
+}
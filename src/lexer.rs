@@ -1,6 +1,10 @@
-use anyhow::Context;
+use std::fmt;
+use std::io::Read;
 
-use crate::reader::JsonReader;
+use crate::error::ParseError;
+use crate::number::{Kind, Number};
+use crate::options::ParseOptions;
+use crate::reader::{JsonReader, Position};
 
 #[cfg(test)]
 mod tests;
@@ -10,7 +14,12 @@ pub enum Token {
     OpenObject,
     CloseObject,
     Colon,
-    TNumber(f64),
+    /// An integer that fits in an `i64`.
+    TInt(i64),
+    /// A non-negative integer too large for an `i64` that fits in a `u64`.
+    TUint(u64),
+    /// Any other number, kept as written.
+    TFloat(Number),
     TString(String),
     TBool(bool),
     TNull,
@@ -19,120 +28,496 @@ pub enum Token {
     CloseArray,
 }
 
-pub struct TokenReader {
-    reader: JsonReader,
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::OpenObject => write!(f, "'{{'"),
+            Token::CloseObject => write!(f, "'}}'"),
+            Token::Colon => write!(f, "':'"),
+            Token::TInt(val) => write!(f, "number {}", val),
+            Token::TUint(val) => write!(f, "number {}", val),
+            Token::TFloat(val) => write!(f, "number {}", val),
+            Token::TString(val) => write!(f, "string {:?}", val),
+            Token::TBool(val) => write!(f, "'{}'", val),
+            Token::TNull => write!(f, "'null'"),
+            Token::Comma => write!(f, "','"),
+            Token::OpenArray => write!(f, "'['"),
+            Token::CloseArray => write!(f, "']'"),
+        }
+    }
+}
+
+/// The input range a token was read from, `end` being the position just past its last character.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
 }
 
-impl TokenReader {
-    pub fn new(reader: JsonReader) -> Self {
+pub struct TokenReader<R: Read> {
+    reader: JsonReader<R>,
+}
+
+impl<R: Read> TokenReader<R> {
+    pub fn new(reader: JsonReader<R>) -> Self {
         TokenReader { reader }
     }
+
+    pub fn options(&self) -> &ParseOptions {
+        self.reader.options()
+    }
+
+    /// Position of the next unread character, which is the end of input once the tokens run out.
+    pub fn current_position(&self) -> Position {
+        self.reader.current_position()
+    }
+
+    fn next_token(&mut self) -> Option<Result<SpannedToken, ParseError>> {
+        let unicode_whitespace = self.options().allow_unicode_whitespace;
+        let (start, ch) = loop {
+            while let Some(ch) = self.reader.peek() {
+                if !is_whitespace(ch, unicode_whitespace) {
+                    break;
+                }
+                self.reader.next();
+            }
+
+            let start = self.reader.current_position();
+            match self.reader.next()? {
+                '/' if self.options().allow_comments => {
+                    if let Err(e) = skip_comment(&mut self.reader, start) {
+                        return Some(Err(e));
+                    }
+                }
+                ch => break (start, ch),
+            }
+        };
+
+        let single_quotes = self.options().allow_single_quotes;
+        let nan_infinity = self.options().allow_nan_infinity;
+        let token = match ch {
+            '{' => Ok(Token::OpenObject),
+            '}' => Ok(Token::CloseObject),
+            '[' => Ok(Token::OpenArray),
+            ']' => Ok(Token::CloseArray),
+            ':' => Ok(Token::Colon),
+            ',' => Ok(Token::Comma),
+            '"' => parse_string(&mut self.reader, ch, start),
+            '\'' if single_quotes => parse_string(&mut self.reader, ch, start),
+            '-' if nan_infinity && self.reader.peek() == Some('I') => {
+                parse_keyword(&mut self.reader, ch, start)
+            }
+            '0'..='9' | '.' | '-' => parse_number(&mut self.reader, ch, start),
+            't' | 'f' | 'n' | 'T' | 'F' | 'N' => parse_keyword(&mut self.reader, ch, start),
+            'I' if nan_infinity => parse_keyword(&mut self.reader, ch, start),
+            _ => Err(ParseError::InvalidCharacter {
+                found: ch,
+                position: start,
+            }),
+        };
+
+        Some(token.map(|token| SpannedToken {
+            token,
+            span: Span {
+                start,
+                end: self.reader.current_position(),
+            },
+        }))
+    }
 }
 
-impl Iterator for TokenReader {
-    type Item = Result<Token, anyhow::Error>;
+impl<R: Read> Iterator for TokenReader<R> {
+    type Item = Result<SpannedToken, ParseError>;
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(ch) = self.reader.next() {
-            if !ch.is_whitespace() {
-                return Some(match ch {
-                    '{' => Ok(Token::OpenObject),
-                    '}' => Ok(Token::CloseObject),
-                    '[' => Ok(Token::OpenArray),
-                    ']' => Ok(Token::CloseArray),
-                    ':' => Ok(Token::Colon),
-                    ',' => Ok(Token::Comma),
-                    '"' => parse_string(&mut self.reader),
-                    '0'..='9' | '.' | '-' => parse_number(&mut self.reader, ch),
-                    't' | 'f' | 'T' | 'F' => parse_boolean(&mut self.reader, ch),
-                    'n' | 'N' => parse_null(&mut self.reader, ch),
-                    _ => Err(anyhow::anyhow!(
-                        "Invalid character '{}' found when parsing",
-                        ch
-                    )),
-                });
+        match self.next_token() {
+            Some(Ok(token)) => Some(Ok(token)),
+            // A reader failure looks like a premature end of input, so report it instead
+            other => match self.reader.take_error() {
+                Some(e) => Some(Err(e)),
+                None => other,
+            },
+        }
+    }
+}
+
+/// Whether `ch` may separate tokens: one of the four whitespace characters RFC 8259
+/// allows, or with `unicode` any Unicode whitespace.
+fn is_whitespace(ch: char, unicode: bool) -> bool {
+    matches!(ch, ' ' | '\t' | '\n' | '\r') || (unicode && ch.is_whitespace())
+}
+
+/// Skips the rest of a `//` or `/*` comment whose slash was at `start`.
+fn skip_comment<R: Read>(iter: &mut JsonReader<R>, start: Position) -> Result<(), ParseError> {
+    match iter.next() {
+        Some('/') => {
+            while iter.next().is_some_and(|ch| ch != '\n') {}
+            Ok(())
+        }
+        Some('*') => {
+            let mut star = false;
+            loop {
+                match iter.next() {
+                    Some('/') if star => return Ok(()),
+                    Some(ch) => star = ch == '*',
+                    None => {
+                        return Err(ParseError::UnexpectedEof {
+                            expected: "'*/' to close the comment",
+                            position: iter.current_position(),
+                        })
+                    }
+                }
             }
         }
-        None
+        _ => Err(ParseError::InvalidCharacter {
+            found: '/',
+            position: start,
+        }),
     }
 }
 
-fn parse_string(iter: &mut JsonReader) -> Result<Token, anyhow::Error> {
+/// Reads the rest of a string opened by `quote`, which is `"`, or `'` when single
+/// quotes are allowed.
+fn parse_string<R: Read>(
+    iter: &mut JsonReader<R>,
+    quote: char,
+    start: Position,
+) -> Result<Token, ParseError> {
+    let lossy = iter.options().replace_lone_surrogates;
+    let single_quotes = iter.options().allow_single_quotes;
+    let control_characters = iter.options().allow_control_characters;
+    let max_length = iter.options().limits.max_string_length;
     let mut string = String::new();
-    while let Some(next_ch) = iter.next() {
+    let mut high_surrogate: Option<(u16, Position)> = None;
+    loop {
+        if let Some(limit) = max_length.filter(|&limit| string.len() > limit) {
+            return Err(ParseError::StringTooLong {
+                limit,
+                position: start,
+            });
+        }
+        let position = iter.current_position();
+        let next_ch = iter.next().ok_or(ParseError::UnexpectedEof {
+            expected: if quote == '"' {
+                "closing '\"' of string"
+            } else {
+                "closing \"'\" of string"
+            },
+            position,
+        })?;
         match next_ch {
-            '"' => break,
+            _ if next_ch == quote => break,
             '\\' => {
-                let escaped_ch = iter.next().ok_or_else(|| {
-                    anyhow::anyhow!("Unexpected end of input after escape character")
+                let escaped_ch = iter.next().ok_or_else(|| ParseError::UnexpectedEof {
+                    expected: "escape character",
+                    position: iter.current_position(),
                 })?;
-                match escaped_ch {
-                    '"' => string.push('"'),
-                    '\\' => string.push('\\'),
-                    'n' => string.push('\n'),
-                    't' => string.push('\t'),
-                    'r' => string.push('\r'),
+                let unescaped = match escaped_ch {
+                    '"' => '"',
+                    '\'' if single_quotes => '\'',
+                    '\\' => '\\',
+                    '/' => '/',
+                    'b' => '\u{0008}',
+                    'f' => '\u{000C}',
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'u' => {
+                        let code_unit = parse_hex_escape(iter, position)?;
+                        push_code_unit(
+                            &mut string,
+                            &mut high_surrogate,
+                            (code_unit, position),
+                            lossy,
+                        )?;
+                        continue;
+                    }
                     _ => {
-                        return Err(anyhow::anyhow!(
-                            "Invalid escape sequence '\\{}'",
-                            escaped_ch
-                        ))
+                        return Err(ParseError::InvalidEscape {
+                            sequence: escaped_ch.to_string(),
+                            position,
+                        })
                     }
-                }
+                };
+                flush_high_surrogate(&mut string, &mut high_surrogate, lossy)?;
+                string.push(unescaped);
+            }
+            _ if next_ch < ' ' && !control_characters => {
+                return Err(ParseError::ControlCharacter {
+                    found: next_ch,
+                    position,
+                })
+            }
+            _ => {
+                flush_high_surrogate(&mut string, &mut high_surrogate, lossy)?;
+                string.push(next_ch);
             }
-            _ => string.push(next_ch),
         }
     }
+    flush_high_surrogate(&mut string, &mut high_surrogate, lossy)?;
+    if let Some(limit) = max_length.filter(|&limit| string.len() > limit) {
+        return Err(ParseError::StringTooLong {
+            limit,
+            position: start,
+        });
+    }
     Ok(Token::TString(string))
 }
 
-fn parse_number(iter: &mut JsonReader, num_start: char) -> Result<Token, anyhow::Error> {
-    let mut number = num_start.to_string();
+fn parse_hex_escape<R: Read>(
+    iter: &mut JsonReader<R>,
+    position: Position,
+) -> Result<u16, ParseError> {
+    let mut digits = String::with_capacity(4);
+    for _ in 0..4 {
+        match iter.next() {
+            Some(ch) if ch.is_ascii_hexdigit() => digits.push(ch),
+            Some(ch) => {
+                return Err(ParseError::InvalidEscape {
+                    sequence: format!("u{}{}", digits, ch),
+                    position,
+                })
+            }
+            None => {
+                return Err(ParseError::UnexpectedEof {
+                    expected: "4 hex digits in unicode escape",
+                    position: iter.current_position(),
+                })
+            }
+        }
+    }
+    Ok(u16::from_str_radix(&digits, 16).expect("4 hex digits fit in a u16"))
+}
 
-    while let Some(next_ch) = iter.peek() {
-        if next_ch.is_numeric() || next_ch == '.' {
-            number.push(next_ch);
-            iter.next();
-        } else {
-            break;
+// Decodes one UTF-16 code unit from a `\u` escape, pairing surrogates across consecutive escapes
+fn push_code_unit(
+    string: &mut String,
+    high_surrogate: &mut Option<(u16, Position)>,
+    (code_unit, position): (u16, Position),
+    lossy: bool,
+) -> Result<(), ParseError> {
+    match (high_surrogate.take(), code_unit) {
+        (Some((high, _)), 0xDC00..=0xDFFF) => {
+            let scalar =
+                0x10000 + ((u32::from(high) - 0xD800) << 10) + (u32::from(code_unit) - 0xDC00);
+            string.push(char::from_u32(scalar).expect("surrogate pair is a valid scalar"));
+        }
+        (pending, 0xD800..=0xDBFF) => {
+            *high_surrogate = pending;
+            flush_high_surrogate(string, high_surrogate, lossy)?;
+            *high_surrogate = Some((code_unit, position));
+        }
+        (pending, _) => {
+            *high_surrogate = pending;
+            flush_high_surrogate(string, high_surrogate, lossy)?;
+            match char::from_u32(u32::from(code_unit)) {
+                Some(ch) => string.push(ch),
+                None => push_lone_surrogate(string, code_unit, position, lossy)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn flush_high_surrogate(
+    string: &mut String,
+    high_surrogate: &mut Option<(u16, Position)>,
+    lossy: bool,
+) -> Result<(), ParseError> {
+    match high_surrogate.take() {
+        Some((high, position)) => push_lone_surrogate(string, high, position, lossy),
+        None => Ok(()),
+    }
+}
+
+fn push_lone_surrogate(
+    string: &mut String,
+    code_unit: u16,
+    position: Position,
+    lossy: bool,
+) -> Result<(), ParseError> {
+    if !lossy {
+        return Err(ParseError::LoneSurrogate {
+            code_unit,
+            position,
+        });
+    }
+    string.push(char::REPLACEMENT_CHARACTER);
+    Ok(())
+}
+
+/// Position within the RFC 8259 number grammar after consuming a character.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberState {
+    Start,
+    Sign,
+    Zero,
+    Integer,
+    Point,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl NumberState {
+    fn advance(self, ch: char) -> Result<NumberState, String> {
+        use NumberState::*;
+        match (self, ch) {
+            (Start, '-') => Ok(Sign),
+            (Start | Sign, '0') => Ok(Zero),
+            (Start | Sign | Integer, '1'..='9') | (Integer, '0') => Ok(Integer),
+            (Zero | Integer, '.') => Ok(Point),
+            (Point | Fraction, '0'..='9') => Ok(Fraction),
+            (Zero | Integer | Fraction, 'e' | 'E') => Ok(Exponent),
+            (Exponent, '+' | '-') => Ok(ExponentSign),
+            (Exponent | ExponentSign | ExponentDigits, '0'..='9') => Ok(ExponentDigits),
+            (Start, '.') => Err("missing integer part before '.'".to_string()),
+            (Sign, _) => Err(format!("expected digit after sign, found '{}'", ch)),
+            (Zero, '0'..='9') => Err("leading zeros are not allowed in integer part".to_string()),
+            (Point, _) => Err(format!("expected digit in fraction, found '{}'", ch)),
+            (Exponent | ExponentSign, _) => {
+                Err(format!("expected digit in exponent, found '{}'", ch))
+            }
+            (state, _) => Err(format!("unexpected '{}' in {}", ch, state.part())),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        use NumberState::*;
+        match self {
+            Zero | Integer | Fraction | ExponentDigits => Ok(()),
+            Start | Sign => Err("missing digits in integer part".to_string()),
+            Point => Err("missing digits in fraction".to_string()),
+            Exponent | ExponentSign => Err("missing digits in exponent".to_string()),
         }
     }
 
-    if num_start == '0' && number.len() > 1 && !number.contains('.') {
-        return Err(anyhow::anyhow!(
-            "Only decimal numbers and 0 can start with 0"
-        ));
+    fn part(self) -> &'static str {
+        use NumberState::*;
+        match self {
+            Start => "sign",
+            Sign | Zero | Integer => "integer part",
+            Point | Fraction => "fraction",
+            Exponent | ExponentSign | ExponentDigits => "exponent",
+        }
     }
+}
 
-    number
-        .parse::<f64>()
-        .map(Token::TNumber)
-        .with_context(|| format!("Invalid number: {}", number))
+/// Checks `text` against the RFC 8259 number grammar, explaining what is wrong if it does not match.
+pub(crate) fn validate_number(text: &str) -> Result<(), String> {
+    text.chars()
+        .try_fold(NumberState::Start, NumberState::advance)
+        .and_then(NumberState::finish)
 }
 
-fn parse_boolean(iter: &mut JsonReader, first_char: char) -> Result<Token, anyhow::Error> {
-    let expected = if first_char == 't' { "rue" } else { "alse" };
+fn parse_number<R: Read>(
+    iter: &mut JsonReader<R>,
+    num_start: char,
+    position: Position,
+) -> Result<Token, ParseError> {
+    let max_length = iter.options().limits.max_number_length;
+    let mut number = num_start.to_string();
+
+    // Consume everything that could belong to a number so malformed input is reported as a whole
+    loop {
+        if let Some(limit) = max_length.filter(|&limit| number.len() > limit) {
+            return Err(ParseError::NumberTooLong { limit, position });
+        }
+        match iter.peek() {
+            Some(next_ch)
+                if next_ch.is_ascii_digit() || matches!(next_ch, '.' | 'e' | 'E' | '+' | '-') =>
+            {
+                number.push(next_ch);
+                iter.next();
+            }
+            _ => break,
+        }
+    }
 
-    for expected_char in expected.chars() {
-        let ch = iter
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("Unexpected end of input while parsing boolean"))?;
-        if ch != expected_char {
-            return Err(anyhow::anyhow!("Invalid boolean literal"));
+    match validate_number(&number) {
+        Ok(()) => {
+            let number = Number::from_valid_text(number);
+            Ok(match number.kind() {
+                Kind::Int(num) => Token::TInt(num),
+                Kind::Uint(num) => Token::TUint(num),
+                Kind::Float => Token::TFloat(number),
+            })
         }
+        Err(reason) => Err(ParseError::InvalidNumber {
+            text: number,
+            reason,
+            position,
+        }),
     }
-    Ok(Token::TBool(first_char == 't'))
 }
 
-fn parse_null(iter: &mut JsonReader, first_char: char) -> Result<Token, anyhow::Error> {
-    let expected = if first_char == 'n' { "ull" } else { "ULL" };
+/// Reads `true`, `false` or `null`, or with `allow_nan_infinity` a non-finite
+/// number, whose sign the caller has already checked is followed by `I`.
+fn parse_keyword<R: Read>(
+    iter: &mut JsonReader<R>,
+    first_char: char,
+    position: Position,
+) -> Result<Token, ParseError> {
+    let mut word = first_char.to_string();
+    while let Some(ch) = iter.peek() {
+        if !ch.is_ascii_alphanumeric() && ch != '_' {
+            break;
+        }
+        word.push(ch);
+        iter.next();
+    }
+
+    let nan_infinity = iter.options().allow_nan_infinity;
+    let (expected, token) = match first_char.to_ascii_lowercase() {
+        't' => ("true", Token::TBool(true)),
+        'f' => ("false", Token::TBool(false)),
+        'n' if !(nan_infinity && word == "NaN") => ("null", Token::TNull),
+        _ => {
+            let expected = match first_char {
+                'N' => "NaN",
+                'I' => "Infinity",
+                _ => "-Infinity",
+            };
+            let number = Number::from_valid_text(expected.to_string());
+            (expected, Token::TFloat(number))
+        }
+    };
+
+    // Only the keywords proper may be written in another case
+    let lenient = iter.options().case_insensitive_keywords && !matches!(token, Token::TFloat(_));
+    if word != expected && !(lenient && word.eq_ignore_ascii_case(expected)) {
+        return Err(ParseError::InvalidLiteral {
+            found: word,
+            expected,
+            position,
+        });
+    }
 
-    for expected_char in expected.chars() {
-        let ch = iter
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("Unexpected end of input while parsing null"))?;
-        if ch != expected_char {
-            return Err(anyhow::anyhow!("Invalid null literal"));
+    // A keyword must end at a delimiter so that e.g. `true"x"` is not split into two tokens
+    let (comments, unicode_whitespace) = (
+        iter.options().allow_comments,
+        iter.options().allow_unicode_whitespace,
+    );
+    let ends_keyword = |ch: char| {
+        is_whitespace(ch, unicode_whitespace)
+            || matches!(ch, ',' | ':' | ']' | '}')
+            || (comments && ch == '/')
+    };
+    match iter.peek() {
+        Some(ch) if !ends_keyword(ch) => {
+            word.push(ch);
+            Err(ParseError::InvalidLiteral {
+                found: word,
+                expected,
+                position,
+            })
         }
+        _ => Ok(token),
     }
-    Ok(Token::TNull)
 }
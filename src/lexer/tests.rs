@@ -1,12 +1,20 @@
-use crate::lexer::{Token, TokenReader};
-use crate::reader::JsonReader;
+use crate::error::ParseError;
+use crate::lexer::{Span, SpannedToken, Token, TokenReader};
+use crate::number::Number;
+use crate::options::{Limits, ParseOptions};
+use crate::reader::{JsonReader, Position};
 use tempfile;
 
+fn float(text: &str) -> Token {
+    Token::TFloat(text.parse().unwrap())
+}
+
 fn tokenize(input: &str) -> Vec<Token> {
     let temp_file = tempfile::NamedTempFile::new().unwrap();
     std::fs::write(&temp_file, input).unwrap();
     let reader = JsonReader::new(temp_file.path().to_path_buf()).unwrap();
     TokenReader::new(reader)
+        .map(|token| token.map(|spanned| spanned.token))
         .collect::<Result<Vec<Token>, _>>()
         .unwrap()
 }
@@ -49,10 +57,10 @@ fn test_number_tokens() {
     assert_eq!(
         tokens,
         vec![
-            Token::TNumber(123.0),
-            Token::TNumber(-456.789),
-            Token::TNumber(0.123),
-            Token::TNumber(-0.0),
+            Token::TInt(123),
+            float("-456.789"),
+            float("0.123"),
+            float("-0.0"),
         ]
     );
 }
@@ -85,7 +93,7 @@ fn test_complex_structure() {
             Token::TString("key".to_string()),
             Token::Colon,
             Token::OpenArray,
-            Token::TNumber(1.0),
+            Token::TInt(1),
             Token::Comma,
             Token::TBool(true),
             Token::Comma,
@@ -105,3 +113,539 @@ fn test_escaped_characters() {
         vec![Token::TString("Hello\nWorld\t\"\\r".to_string()),]
     );
 }
+
+#[test]
+fn test_multi_byte_string() {
+    let tokens = tokenize(r#""Zoë" "東京" "🦀""#);
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::TString("Zoë".to_string()),
+            Token::TString("東京".to_string()),
+            Token::TString("🦀".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_utf8_is_an_error() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(&temp_file, b"[\"a\xffb\"]").unwrap();
+    let reader = JsonReader::new(temp_file.path().to_path_buf()).unwrap();
+    let err = TokenReader::new(reader)
+        .collect::<Result<Vec<SpannedToken>, _>>()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Invalid UTF-8 sequence at byte offset 3");
+    assert!(matches!(err, ParseError::InvalidUtf8 { .. }));
+    assert_eq!(
+        err.position(),
+        Position {
+            line: 1,
+            column: 4,
+            offset: 3
+        }
+    );
+}
+
+fn tokenize_err(input: &str, options: ParseOptions) -> String {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(&temp_file, input).unwrap();
+    let reader = JsonReader::with_options(temp_file.path().to_path_buf(), options).unwrap();
+    TokenReader::new(reader)
+        .collect::<Result<Vec<SpannedToken>, _>>()
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn test_full_escape_set() {
+    let tokens = tokenize(r#""\/\b\f\u0041\u00e9\u4E2D""#);
+
+    assert_eq!(tokens, vec![Token::TString("/\u{8}\u{c}Aé中".to_string())]);
+}
+
+#[test]
+fn test_surrogate_pair() {
+    let tokens = tokenize(r#""\ud83e\udd80 \uD83D\uDE00""#);
+
+    assert_eq!(tokens, vec![Token::TString("🦀 😀".to_string())]);
+}
+
+#[test]
+fn test_lone_surrogates() {
+    for input in [
+        r#""\ud83e""#,
+        r#""\ud83ex""#,
+        r#""\udd80""#,
+        r#""\ud83e\n""#,
+        r#""\ud83e\ud83e""#,
+    ] {
+        assert!(
+            tokenize_err(input, ParseOptions::default()).starts_with("Lone surrogate"),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_lone_surrogates_lossy() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(&temp_file, r#""a\ud83eb" "\udd80\ud83e\ud83e\udd80""#).unwrap();
+    let options = ParseOptions {
+        replace_lone_surrogates: true,
+        ..ParseOptions::default()
+    };
+    let reader = JsonReader::with_options(temp_file.path().to_path_buf(), options).unwrap();
+    let tokens = TokenReader::new(reader)
+        .map(|token| token.map(|spanned| spanned.token))
+        .collect::<Result<Vec<Token>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::TString("a\u{FFFD}b".to_string()),
+            Token::TString("\u{FFFD}\u{FFFD}🦀".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_unicode_escape() {
+    let err = tokenize_err(r#""\u12G4""#, ParseOptions::default());
+    assert_eq!(err, "Invalid escape sequence '\\u12G'");
+}
+
+#[test]
+fn test_exponent_numbers() {
+    let tokens = tokenize("1e10 -2.5E-3 6.02e+23 0e0 10E2");
+
+    assert_eq!(
+        tokens,
+        vec![
+            float("1e10"),
+            float("-2.5E-3"),
+            float("6.02e+23"),
+            float("0e0"),
+            float("10E2"),
+        ]
+    );
+}
+
+#[test]
+fn test_integer_and_float_tokens() {
+    let tokens = tokenize(
+        "42 42.0 -0 9223372036854775807 9223372036854775808 18446744073709551615 \
+         18446744073709551616 -9223372036854775808 -9223372036854775809",
+    );
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::TInt(42),
+            float("42.0"),
+            float("-0"),
+            Token::TInt(i64::MAX),
+            Token::TUint(9223372036854775808),
+            Token::TUint(u64::MAX),
+            float("18446744073709551616"),
+            Token::TInt(i64::MIN),
+            float("-9223372036854775809"),
+        ]
+    );
+    assert_ne!(Token::TInt(42), float("42"));
+}
+
+#[test]
+fn test_invalid_numbers() {
+    let cases = [
+        (".5", "Invalid number '.5': missing integer part before '.'"),
+        ("1.", "Invalid number '1.': missing digits in fraction"),
+        ("-", "Invalid number '-': missing digits in integer part"),
+        ("-a", "Invalid number '-': missing digits in integer part"),
+        (
+            "--1",
+            "Invalid number '--1': expected digit after sign, found '-'",
+        ),
+        (
+            "1.2.3",
+            "Invalid number '1.2.3': unexpected '.' in fraction",
+        ),
+        (
+            "01",
+            "Invalid number '01': leading zeros are not allowed in integer part",
+        ),
+        (
+            "-00.5",
+            "Invalid number '-00.5': leading zeros are not allowed in integer part",
+        ),
+        (
+            "1.e5",
+            "Invalid number '1.e5': expected digit in fraction, found 'e'",
+        ),
+        ("1e", "Invalid number '1e': missing digits in exponent"),
+        ("1e+", "Invalid number '1e+': missing digits in exponent"),
+        (
+            "1e+-2",
+            "Invalid number '1e+-2': expected digit in exponent, found '-'",
+        ),
+        (
+            "1e5.0",
+            "Invalid number '1e5.0': unexpected '.' in exponent",
+        ),
+        (
+            "1-2",
+            "Invalid number '1-2': unexpected '-' in integer part",
+        ),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(tokenize_err(input, ParseOptions::default()), expected);
+    }
+}
+
+fn pos(line: usize, column: usize, offset: usize) -> Position {
+    Position {
+        line,
+        column,
+        offset,
+    }
+}
+
+#[test]
+fn test_token_spans() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(&temp_file, "{\n  \"é\": -1.5\n}").unwrap();
+    let reader = JsonReader::new(temp_file.path().to_path_buf()).unwrap();
+    let tokens = TokenReader::new(reader)
+        .collect::<Result<Vec<SpannedToken>, _>>()
+        .unwrap();
+
+    let spans: Vec<Span> = tokens.into_iter().map(|token| token.span).collect();
+    assert_eq!(
+        spans,
+        vec![
+            Span {
+                start: pos(1, 1, 0),
+                end: pos(1, 2, 1)
+            },
+            Span {
+                start: pos(2, 3, 4),
+                end: pos(2, 6, 8)
+            },
+            Span {
+                start: pos(2, 6, 8),
+                end: pos(2, 7, 9)
+            },
+            Span {
+                start: pos(2, 8, 10),
+                end: pos(2, 12, 14)
+            },
+            Span {
+                start: pos(3, 1, 15),
+                end: pos(3, 2, 16)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_error_position() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(&temp_file, "[1,\n 2, @]").unwrap();
+    let reader = JsonReader::new(temp_file.path().to_path_buf()).unwrap();
+    let err = TokenReader::new(reader)
+        .collect::<Result<Vec<SpannedToken>, _>>()
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        ParseError::InvalidCharacter { found: '@', .. }
+    ));
+    assert_eq!(err.position(), pos(2, 5, 8));
+}
+
+#[test]
+fn test_invalid_keywords_are_rejected() {
+    let cases = [
+        ("True", "Invalid literal 'True', expected 'true'"),
+        ("Talse", "Invalid literal 'Talse', expected 'true'"),
+        ("FALSE", "Invalid literal 'FALSE', expected 'false'"),
+        ("NULL", "Invalid literal 'NULL', expected 'null'"),
+        ("truex", "Invalid literal 'truex', expected 'true'"),
+        ("nul", "Invalid literal 'nul', expected 'null'"),
+        ("fals", "Invalid literal 'fals', expected 'false'"),
+        ("true\"x\"", "Invalid literal 'true\"', expected 'true'"),
+        ("null-1", "Invalid literal 'null-', expected 'null'"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(tokenize_err(input, ParseOptions::default()), expected);
+    }
+}
+
+#[test]
+fn test_keywords_end_at_delimiters() {
+    let tokens = tokenize("[true,false]{\"a\":null}");
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::OpenArray,
+            Token::TBool(true),
+            Token::Comma,
+            Token::TBool(false),
+            Token::CloseArray,
+            Token::OpenObject,
+            Token::TString("a".to_string()),
+            Token::Colon,
+            Token::TNull,
+            Token::CloseObject,
+        ]
+    );
+}
+
+#[test]
+fn test_case_insensitive_keywords() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(&temp_file, "True FALSE Null nULL").unwrap();
+    let options = ParseOptions {
+        case_insensitive_keywords: true,
+        ..ParseOptions::default()
+    };
+    let reader = JsonReader::with_options(temp_file.path().to_path_buf(), options).unwrap();
+    let tokens = TokenReader::new(reader)
+        .map(|token| token.map(|spanned| spanned.token))
+        .collect::<Result<Vec<Token>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::TBool(true),
+            Token::TBool(false),
+            Token::TNull,
+            Token::TNull,
+        ]
+    );
+    assert_eq!(
+        tokenize_err(
+            "Talse",
+            ParseOptions {
+                case_insensitive_keywords: true,
+                ..ParseOptions::default()
+            }
+        ),
+        "Invalid literal 'Talse', expected 'true'"
+    );
+}
+
+#[test]
+fn test_string_and_number_limits() {
+    let options = ParseOptions {
+        limits: Limits {
+            max_string_length: Some(3),
+            max_number_length: Some(4),
+            ..Limits::default()
+        },
+        ..ParseOptions::default()
+    };
+    let tokenize_limited = |input: &str| {
+        let reader = JsonReader::from_reader_with_options(input.as_bytes(), options.clone());
+        TokenReader::new(reader)
+            .map(|token| token.map(|spanned| spanned.token))
+            .collect::<Result<Vec<Token>, _>>()
+    };
+
+    // Lengths count unescaped bytes and the characters a number is written with
+    assert!(tokenize_limited(r#""abc" "\u00e9x" -1.5 1e10"#).is_ok());
+    match tokenize_limited(r#"["ab", "abcd"]"#) {
+        Err(ParseError::StringTooLong { limit, position }) => {
+            assert_eq!((limit, position.offset), (3, 7));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    match tokenize_limited(r#""é\u00e9""#) {
+        Err(ParseError::StringTooLong { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match tokenize_limited("[1, 12345]") {
+        Err(ParseError::NumberTooLong { limit, position }) => {
+            assert_eq!((limit, position.offset), (4, 4));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(
+        tokenize_limited("-0.001").unwrap_err().to_string(),
+        "Number exceeds the limit of 4 characters"
+    );
+}
+
+fn tokenize_with(input: &str, options: ParseOptions) -> Result<Vec<Token>, ParseError> {
+    let reader = JsonReader::from_reader_with_options(input.as_bytes(), options);
+    TokenReader::new(reader)
+        .map(|token| token.map(|spanned| spanned.token))
+        .collect()
+}
+
+#[test]
+fn test_comments() {
+    let options = ParseOptions::default().allow_comments(true);
+    let input = "// leading\n[1, /* inline * / */ 2 // trailing\n]/**/";
+    assert_eq!(
+        tokenize_with(input, options.clone()).unwrap(),
+        vec![
+            Token::OpenArray,
+            Token::TInt(1),
+            Token::Comma,
+            Token::TInt(2),
+            Token::CloseArray,
+        ]
+    );
+    // A comment marker inside a string is just text
+    assert_eq!(
+        tokenize_with(r#""/* not a comment */""#, options.clone()).unwrap(),
+        vec![Token::TString("/* not a comment */".to_string())]
+    );
+
+    assert_eq!(
+        tokenize_err("[1 /* open", options.clone()),
+        "Unexpected end of input, expected '*/' to close the comment"
+    );
+    assert_eq!(
+        tokenize_err("[1 / 2]", options.clone()),
+        "Invalid character \'/\' found when parsing"
+    );
+    // A comment can follow a keyword or number directly
+    assert_eq!(
+        tokenize_with(
+            "[true/*c*/,null//c\n,1//c\n]",
+            options.clone().allow_nan_infinity(true)
+        )
+        .unwrap()
+        .len(),
+        7
+    );
+    assert_eq!(
+        tokenize_with("[NaN/**/,-Infinity//c\n]", ParseOptions::json5()).unwrap(),
+        vec![
+            Token::OpenArray,
+            non_finite("NaN"),
+            Token::Comma,
+            non_finite("-Infinity"),
+            Token::CloseArray,
+        ]
+    );
+    assert_eq!(
+        tokenize_err("true/", options),
+        "Invalid character '/' found when parsing"
+    );
+    assert_eq!(
+        tokenize_err("true//", ParseOptions::default()),
+        "Invalid literal 'true/', expected 'true'"
+    );
+    assert_eq!(
+        tokenize_err("[1 // no", ParseOptions::default()),
+        "Invalid character \'/\' found when parsing"
+    );
+}
+
+#[test]
+fn test_single_quotes() {
+    let options = ParseOptions::default().allow_single_quotes(true);
+    assert_eq!(
+        tokenize_with(r#"'it\'s' 'say "hi"' "\'""#, options.clone()).unwrap(),
+        vec![
+            Token::TString("it's".to_string()),
+            Token::TString("say \"hi\"".to_string()),
+            Token::TString("'".to_string()),
+        ]
+    );
+    assert_eq!(
+        tokenize_err("'open", options),
+        "Unexpected end of input, expected closing \"'\" of string"
+    );
+
+    assert_eq!(
+        tokenize_err("'a'", ParseOptions::default()),
+        "Invalid character \''\' found when parsing"
+    );
+    assert!(tokenize_err(r#""\'""#, ParseOptions::default()).starts_with("Invalid escape"));
+}
+
+fn non_finite(text: &str) -> Token {
+    Token::TFloat(Number::from_valid_text(text.to_string()))
+}
+
+#[test]
+fn test_nan_and_infinity() {
+    let options = ParseOptions::default().allow_nan_infinity(true);
+    assert_eq!(
+        tokenize_with("[NaN, Infinity, -Infinity, -1, null]", options.clone()).unwrap(),
+        vec![
+            Token::OpenArray,
+            non_finite("NaN"),
+            Token::Comma,
+            non_finite("Infinity"),
+            Token::Comma,
+            non_finite("-Infinity"),
+            Token::Comma,
+            Token::TInt(-1),
+            Token::Comma,
+            Token::TNull,
+            Token::CloseArray,
+        ]
+    );
+    assert_eq!(
+        tokenize_err("Infinit", options.clone()),
+        "Invalid literal 'Infinit', expected 'Infinity'"
+    );
+    // Keyword case folding does not extend to the numbers
+    assert_eq!(
+        tokenize_err("nan", options.case_insensitive_keywords(true)),
+        "Invalid literal 'nan', expected 'null'"
+    );
+
+    assert_eq!(
+        tokenize_err("NaN", ParseOptions::default()),
+        "Invalid literal 'NaN', expected 'null'"
+    );
+    assert_eq!(
+        tokenize_err("Infinity", ParseOptions::default()),
+        "Invalid character \'I\' found when parsing"
+    );
+    assert!(tokenize_err("-Infinity", ParseOptions::default()).starts_with("Invalid number"));
+}
+
+#[test]
+fn test_unicode_whitespace() {
+    for input in ["[\u{a0}1]", "[1,\u{2028}2]", "[1\u{3000}]"] {
+        assert!(tokenize_err(input, ParseOptions::strict()).starts_with("Invalid character"));
+        assert!(tokenize_with(input, ParseOptions::json5()).is_ok());
+    }
+    assert!(tokenize_err("[true\u{a0}]", ParseOptions::strict()).starts_with("Invalid literal"));
+    assert_eq!(
+        tokenize_with("[true\u{a0}]", ParseOptions::json5()).unwrap(),
+        vec![Token::OpenArray, Token::TBool(true), Token::CloseArray]
+    );
+}
+
+#[test]
+fn test_control_characters() {
+    assert_eq!(
+        tokenize_err("\"a\nb\"", ParseOptions::strict()),
+        "Unescaped control character U+000A in string"
+    );
+    assert_eq!(
+        tokenize_err("\"\0\"", ParseOptions::json5()),
+        "Unescaped control character U+0000 in string"
+    );
+    // DEL is not a control character as far as JSON is concerned
+    assert!(tokenize_with("\"\u{7f}\"", ParseOptions::strict()).is_ok());
+    assert_eq!(
+        tokenize_with("\"a\tb\"", ParseOptions::lenient()).unwrap(),
+        vec![Token::TString("a\tb".to_string())]
+    );
+}
@@ -0,0 +1,16 @@
+pub mod error;
+pub mod events;
+pub mod filter;
+pub mod jsonpath;
+pub mod lexer;
+pub mod map;
+pub mod number;
+pub mod options;
+pub mod parser;
+pub mod pointer;
+pub mod reader;
+pub mod ser;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod value;
+pub mod visit;
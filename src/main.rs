@@ -1,29 +1,572 @@
-use anyhow::Result;
-use clap::Parser;
-use parser::JVal;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use jsonp::options::{DuplicateKeys, Limits, ParseOptions, WriteOptions};
+use jsonp::{
+    error::ParseError, filter::Filter, jsonpath::JsonPath, lexer, map::JMap, parser, parser::JVal,
+    reader, reader::Position, ser,
+};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
-pub mod lexer;
-pub mod parser;
-pub mod reader;
-
 #[derive(Parser)]
 struct Args {
-    path: std::path::PathBuf,
+    #[command(subcommand)]
+    command: Command,
+    /// How to handle a key that appears more than once in the same object
+    #[arg(long, global = true, value_enum, default_value_t = DuplicateKeysArg::LastWins)]
+    duplicate_keys: DuplicateKeysArg,
+    /// Which extensions to standard JSON the input may use
+    #[arg(long, global = true, value_enum, default_value_t = SyntaxArg::Strict)]
+    syntax: SyntaxArg,
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+/// Caps for untrusted input; a document exceeding one is rejected as invalid.
+#[derive(clap::Args)]
+struct LimitArgs {
+    /// Maximum nesting depth of objects and arrays. Values are cloned and dropped
+    /// recursively, so a far deeper document would overflow the stack
+    #[arg(long, global = true, value_name = "N", default_value_t = 512)]
+    max_depth: usize,
+    /// Maximum length of a string or key, in bytes
+    #[arg(long, global = true, value_name = "BYTES")]
+    max_string_length: Option<usize>,
+    /// Maximum number of members in one object
+    #[arg(long, global = true, value_name = "N")]
+    max_object_members: Option<usize>,
+    /// Maximum number of elements in one array
+    #[arg(long, global = true, value_name = "N")]
+    max_array_elements: Option<usize>,
+    /// Maximum size of each input, in bytes
+    #[arg(long, global = true, value_name = "BYTES")]
+    max_input_size: Option<usize>,
+    /// Maximum length of a number, in characters
+    #[arg(long, global = true, value_name = "CHARS")]
+    max_number_length: Option<usize>,
+}
+
+impl From<LimitArgs> for Limits {
+    fn from(args: LimitArgs) -> Self {
+        Limits {
+            max_depth: Some(args.max_depth),
+            max_string_length: args.max_string_length,
+            max_object_members: args.max_object_members,
+            max_array_elements: args.max_array_elements,
+            max_input_size: args.max_input_size,
+            max_number_length: args.max_number_length,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Pretty-print JSON documents
+    Fmt {
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Write each document on a single line
+        #[arg(long)]
+        compact: bool,
+        /// Spaces per nesting level
+        #[arg(long, default_value_t = 2, conflicts_with = "compact")]
+        indent: usize,
+    },
+    /// Print JSON documents on a single line without whitespace
+    Minify {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print the value a JSON Pointer (RFC 6901) selects, e.g. `/users/0/name`
+    Get {
+        /// JSON file to read; `-` reads stdin
+        path: PathBuf,
+        /// The pointer to look up; the empty string selects the whole document
+        pointer: String,
+        /// Write the value on a single line
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Print the values a JSONPath query (RFC 9535) selects, e.g. `$..book[?@.price < 10]`
+    Query {
+        /// JSON file to read; `-` reads stdin
+        path: PathBuf,
+        /// The query to run
+        query: String,
+        /// Print `{"path", "value"}` objects with the normalized path of each value
+        #[arg(long)]
+        with_paths: bool,
+        /// Write the result on a single line
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Run a jq-style filter, e.g. `.items[] | select(.price < 10) | .name`, and print
+    /// each value it outputs
+    Eval {
+        /// The filter to run
+        filter: String,
+        /// JSON file to read; `-` reads stdin
+        #[arg(default_value = "-")]
+        path: PathBuf,
+        /// Write each value on a single line
+        #[arg(short, long)]
+        compact: bool,
+        /// Print strings without quotes or escapes
+        #[arg(short, long)]
+        raw_output: bool,
+        /// Bind `$NAME` to the string VALUE
+        #[arg(long, num_args = 2, value_names = ["NAME", "VALUE"])]
+        arg: Vec<String>,
+        /// Bind `$NAME` to the JSON value VALUE
+        #[arg(long, num_args = 2, value_names = ["NAME", "VALUE"])]
+        argjson: Vec<String>,
+    },
+    /// Validate JSON files
+    ///
+    /// Exits with 0 when every file is valid, 1 when any is invalid and 2 when any
+    /// could not be read.
+    Check {
+        /// JSON files to validate; `-` reads stdin
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// How to print the result for each file
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        report: ReportFormat,
+    },
+}
+
+#[derive(clap::Args)]
+struct OutputArgs {
+    /// JSON files to read; reads stdin when none are given or for `-`
+    paths: Vec<PathBuf>,
+    /// Overwrite each file with its formatted contents instead of printing them
+    #[arg(long, requires = "paths")]
+    write: bool,
+    /// Escape every non-ASCII character as `\uXXXX`
+    #[arg(long)]
+    ascii: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DuplicateKeysArg {
+    LastWins,
+    FirstWins,
+    Error,
+    /// Every member's value becomes an array of the values given for its key
+    Collect,
+}
+
+impl From<DuplicateKeysArg> for DuplicateKeys {
+    fn from(arg: DuplicateKeysArg) -> Self {
+        match arg {
+            DuplicateKeysArg::LastWins => DuplicateKeys::LastWins,
+            DuplicateKeysArg::FirstWins => DuplicateKeys::FirstWins,
+            DuplicateKeysArg::Error => DuplicateKeys::Error,
+            DuplicateKeysArg::Collect => DuplicateKeys::CollectAll,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SyntaxArg {
+    /// Exactly RFC 8259
+    Strict,
+    /// Comments, trailing commas, single-quoted strings, NaN, Infinity and Unicode whitespace
+    Json5,
+    /// Everything json5 allows, plus keywords in any case, raw control characters in strings
+    /// and invalid text replaced
+    Lenient,
+}
+
+impl From<SyntaxArg> for ParseOptions {
+    fn from(arg: SyntaxArg) -> Self {
+        match arg {
+            SyntaxArg::Strict => ParseOptions::strict(),
+            SyntaxArg::Json5 => ParseOptions::json5(),
+            SyntaxArg::Lenient => ParseOptions::lenient(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// One line per file
+    Text,
+    /// A JSON array with one object per file
+    Json,
+}
+
+/// The outcome of checking one file, ordered from best to worst.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CheckStatus {
+    Valid,
+    Invalid,
+    Unreadable,
+}
+
+impl CheckStatus {
+    fn name(self) -> &'static str {
+        match self {
+            CheckStatus::Valid => "valid",
+            CheckStatus::Invalid => "invalid",
+            CheckStatus::Unreadable => "unreadable",
+        }
+    }
+
+    fn exit_code(self) -> ExitCode {
+        match self {
+            CheckStatus::Valid => ExitCode::SUCCESS,
+            CheckStatus::Invalid => ExitCode::from(1),
+            CheckStatus::Unreadable => ExitCode::from(2),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let Args {
+        command,
+        duplicate_keys,
+        syntax,
+        limits,
+    } = Args::parse();
+    let options = ParseOptions::from(syntax)
+        .duplicate_keys(duplicate_keys.into())
+        .limits(limits.into());
+
+    match command {
+        Command::Fmt {
+            output,
+            compact,
+            indent,
+        } => format(&output, (!compact).then_some(indent), &options),
+        Command::Minify { output } => format(&output, None, &options),
+        Command::Get {
+            path,
+            pointer,
+            compact,
+        } => {
+            let path = input_path(&path);
+            finish(path, get(path, &pointer, compact, &options))
+        }
+        Command::Query {
+            path,
+            query,
+            with_paths,
+            compact,
+        } => {
+            let path = input_path(&path);
+            finish(path, run_query(path, &query, with_paths, compact, &options))
+        }
+        Command::Eval {
+            filter,
+            path,
+            compact,
+            raw_output,
+            arg,
+            argjson,
+        } => {
+            let path = input_path(&path);
+            let result = variables(&arg, &argjson).and_then(|variables| {
+                run_filter(path, &filter, &variables, compact, raw_output, &options)
+            });
+            finish(path, result)
+        }
+        Command::Check { paths, report } => check(&paths, report, &options),
+    }
+}
+
+/// Maps `-` to `None`, meaning stdin.
+fn input_path(path: &Path) -> Option<&Path> {
+    Some(path).filter(|path| path.as_os_str() != "-")
+}
+
+fn read_document(path: Option<&Path>, options: &ParseOptions) -> Result<JVal> {
+    let options = options.clone();
+    let value = match path {
+        Some(path) => {
+            let file_reader = reader::JsonReader::with_options(path.to_path_buf(), options)
+                .with_context(|| format!("Could not read file `{}`", path.display()))?;
+            parser::parse(&mut lexer::TokenReader::new(file_reader))?
+        }
+        None => {
+            let stdin_reader =
+                reader::JsonReader::from_reader_with_options(io::stdin().lock(), options);
+            parser::parse(&mut lexer::TokenReader::new(stdin_reader))?
+        }
+    };
+    Ok(value)
+}
+
+fn format(output: &OutputArgs, indent: Option<usize>, options: &ParseOptions) -> ExitCode {
+    let write_options = WriteOptions {
+        indent,
+        ascii_only: output.ascii,
+    };
+    let paths: Vec<Option<&Path>> = if output.paths.is_empty() {
+        vec![None]
+    } else {
+        output.paths.iter().map(|path| input_path(path)).collect()
+    };
+
+    let mut exit_code = ExitCode::SUCCESS;
+    for path in paths {
+        match format_document(path, output.write, write_options, options) {
+            Ok(()) => {}
+            // Nothing is reading any more, so the rest would go nowhere
+            Err(e) if is_broken_pipe(&e) => break,
+            Err(e) => {
+                report_error(path, &e);
+                exit_code = ExitCode::FAILURE;
+            }
+        }
+    }
+    exit_code
 }
 
-fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
-    let Args { path } = Args::parse();
+/// Reformats one document, writing it back to its file when `write` is set. Stdin is
+/// always written to stdout.
+fn format_document(
+    path: Option<&Path>,
+    write: bool,
+    write_options: WriteOptions,
+    options: &ParseOptions,
+) -> Result<()> {
+    let value = read_document(path, options)?;
+    let mut formatted = ser::to_string_with_options(&value, write_options);
+    formatted.push('\n');
 
-    let file_reader = reader::JsonReader::new(path)?;
-    let mut token_reader = lexer::TokenReader::new(file_reader);
+    match path {
+        Some(path) if write => fs::write(path, formatted)
+            .with_context(|| format!("Could not write file `{}`", path.display()))?,
+        _ => io::stdout().lock().write_all(formatted.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Reports the outcome of a command that reads a single document.
+fn finish(path: Option<&Path>, result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if is_broken_pipe(&e) => ExitCode::SUCCESS,
+        Err(e) => {
+            report_error(path, &e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Whether `error` is stdout having been closed, as by `jsonp ... | head -1`, which
+/// ends the command quietly rather than as a failure.
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<io::Error>(),
+        Some(e) if e.kind() == io::ErrorKind::BrokenPipe
+    )
+}
 
-    match parser::parse(&mut token_reader) {
-        Ok(JVal::JArray(array)) => println!("{:#?}", array),
-        Ok(JVal::JObject(obj)) => println!("{:#?}", obj),
-        Ok(_) => return Err(anyhow::anyhow!("JSON document must be an array or object").into()),
-        Err(e) => return Err(e.into()),
+fn print_value(out: &mut impl Write, value: &JVal, compact: bool) -> io::Result<()> {
+    let write_options = WriteOptions {
+        indent: (!compact).then_some(2),
+        ..WriteOptions::default()
     };
+    writeln!(out, "{}", ser::to_string_with_options(value, write_options))
+}
+
+fn get(path: Option<&Path>, pointer: &str, compact: bool, options: &ParseOptions) -> Result<()> {
+    let value = read_document(path, options)?;
+    print_value(
+        &mut io::stdout().lock(),
+        value.try_pointer(pointer)?,
+        compact,
+    )?;
+    Ok(())
+}
+
+fn run_query(
+    path: Option<&Path>,
+    query: &str,
+    with_paths: bool,
+    compact: bool,
+    options: &ParseOptions,
+) -> Result<()> {
+    // Compile first so a bad query is reported before waiting on stdin
+    let query = JsonPath::parse(query)?;
+    let value = read_document(path, options)?;
+    let results = query
+        .query(&value)
+        .into_iter()
+        .map(|found| {
+            if with_paths {
+                JVal::from_iter([
+                    ("path", JVal::JString(found.path)),
+                    ("value", found.value.clone()),
+                ])
+            } else {
+                found.value.clone()
+            }
+        })
+        .collect();
+    print_value(&mut io::stdout().lock(), &results, compact)?;
+    Ok(())
+}
+
+/// Pairs up `--arg` and `--argjson` values, which clap collects flat.
+fn variables(args: &[String], json_args: &[String]) -> Result<Vec<(String, JVal)>> {
+    let strings = args
+        .chunks(2)
+        .map(|pair| Ok((pair[0].clone(), JVal::JString(pair[1].clone()))));
+    let json = json_args.chunks(2).map(|pair| {
+        let value = parser::from_str(&pair[1])
+            .with_context(|| format!("Invalid JSON for --argjson {}", pair[0]))?;
+        Ok((pair[0].clone(), value))
+    });
+    strings.chain(json).collect()
+}
+
+fn run_filter(
+    path: Option<&Path>,
+    filter: &str,
+    variables: &[(String, JVal)],
+    compact: bool,
+    raw_output: bool,
+    options: &ParseOptions,
+) -> Result<()> {
+    let names: Vec<&str> = variables.iter().map(|(name, _)| name.as_str()).collect();
+    let filter = Filter::parse_with_variables(filter, &names)?;
+    let value = read_document(path, options)?;
+    let mut out = io::stdout().lock();
+    for result in filter.apply_with_variables(&value, variables)? {
+        match result {
+            JVal::JString(string) if raw_output => writeln!(out, "{}", string)?,
+            result => print_value(&mut out, &result, compact)?,
+        }
+    }
+    Ok(())
+}
+
+fn check(paths: &[PathBuf], report: ReportFormat, options: &ParseOptions) -> ExitCode {
+    let mut worst = CheckStatus::Valid;
+    match write_check_report(paths, report, options, &mut worst) {
+        Ok(()) => worst.exit_code(),
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => worst.exit_code(),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            CheckStatus::Unreadable.exit_code()
+        }
+    }
+}
+
+/// Checks each file in turn, raising `worst` to the worst status seen, and writes
+/// the report to stdout.
+fn write_check_report(
+    paths: &[PathBuf],
+    report: ReportFormat,
+    options: &ParseOptions,
+    worst: &mut CheckStatus,
+) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    let mut entries = Vec::new();
+
+    for path in paths {
+        let result = read_document(input_path(path), options);
+        let status = match &result {
+            Ok(_) => CheckStatus::Valid,
+            Err(e) => match e.downcast_ref::<ParseError>() {
+                Some(ParseError::Io { .. }) | None => CheckStatus::Unreadable,
+                Some(_) => CheckStatus::Invalid,
+            },
+        };
+        *worst = (*worst).max(status);
+
+        let name = path.to_string_lossy();
+        match report {
+            ReportFormat::Text => match &result {
+                Ok(_) => writeln!(out, "{}: ok", name)?,
+                Err(e) => match e.downcast_ref::<ParseError>() {
+                    Some(parse_error) if status == CheckStatus::Invalid => {
+                        let position = parse_error.position();
+                        writeln!(
+                            out,
+                            "{}:{}:{}: {}",
+                            name, position.line, position.column, parse_error
+                        )?;
+                    }
+                    _ => writeln!(out, "{}: {:#}", name, e)?,
+                },
+            },
+            ReportFormat::Json => entries.push(report_entry(&name, status, result.err())),
+        }
+    }
+
+    if let ReportFormat::Json = report {
+        writeln!(out, "{}", ser::to_string_pretty(&JVal::JArray(entries), 2))?;
+    }
+    Ok(())
+}
+
+/// Describes one checked file as `{"path", "status"}`, plus `"error"` and, for
+/// invalid documents, the `"line"`, `"column"` and `"offset"` the error was found at.
+fn report_entry(path: &str, status: CheckStatus, error: Option<anyhow::Error>) -> JVal {
+    let mut entry = JMap::new();
+    entry.insert("path".to_string(), JVal::JString(path.to_string()));
+    entry.insert(
+        "status".to_string(),
+        JVal::JString(status.name().to_string()),
+    );
+    if let Some(error) = error {
+        entry.insert("error".to_string(), JVal::JString(format!("{:#}", error)));
+        if let (CheckStatus::Invalid, Some(parse_error)) =
+            (status, error.downcast_ref::<ParseError>())
+        {
+            let position = parse_error.position();
+            entry.insert("line".to_string(), JVal::from(position.line));
+            entry.insert("column".to_string(), JVal::from(position.column));
+            entry.insert("offset".to_string(), JVal::from(position.offset));
+        }
+    }
+    JVal::JObject(entry)
+}
+
+fn report_error(path: Option<&Path>, error: &anyhow::Error) {
+    let parse_error = match error.downcast_ref::<ParseError>() {
+        Some(ParseError::Io { .. }) | None => {
+            eprintln!("error: {:#}", error);
+            return;
+        }
+        Some(parse_error) => parse_error,
+    };
+    let position = &parse_error.position();
+
+    eprintln!("error: {}", parse_error);
+    let source = path.map_or("<stdin>".into(), Path::to_string_lossy);
+    eprintln!(" --> {}:{}:{}", source, position.line, position.column);
+    // Stdin has already been consumed, so only files can be shown
+    if let Some(excerpt) = path.and_then(|path| excerpt(path, position)) {
+        eprint!("{}", excerpt);
+    }
+}
+
+/// Renders the line containing `position` with a caret under the offending column.
+fn excerpt(path: &Path, position: &Position) -> Option<String> {
+    let contents = std::fs::read(path).ok()?;
+    let line = contents
+        .split(|&byte| byte == b'\n')
+        .nth(position.line - 1)?;
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end_matches('\r');
+
+    // Keep tabs so the caret lines up with the text above it
+    let padding: String = line
+        .chars()
+        .take(position.column - 1)
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(position.line.to_string().len());
 
-    Ok(ExitCode::SUCCESS)
+    Some(format!(
+        "{gutter} |\n{line_number} | {line}\n{gutter} | {padding}^\n",
+        line_number = position.line,
+    ))
 }
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::JVal;
+
+#[cfg(test)]
+mod tests;
+
+/// The members of a JSON object, kept in the order their keys were first inserted.
+///
+/// Lookups go through a key index, so they cost the same as with a `HashMap`;
+/// `remove` shifts the later members down to keep the order intact.
+#[derive(Clone, Default)]
+pub struct JMap {
+    entries: Vec<(String, JVal)>,
+    index: HashMap<String, usize>,
+}
+
+impl JMap {
+    pub fn new() -> Self {
+        JMap::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        JMap {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JVal> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JVal> {
+        self.index.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    /// Like `get`, but also returns the stored key.
+    pub fn get_key_value(&self, key: &str) -> Option<(&String, &JVal)> {
+        self.index.get(key).map(|&i| {
+            let (key, value) = &self.entries[i];
+            (key, value)
+        })
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Inserts a member, returning the previous value for `key`. A replaced
+    /// member keeps its original position.
+    pub fn insert(&mut self, key: String, value: JVal) -> Option<JVal> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<JVal> {
+        let removed = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(removed);
+        for i in self.index.values_mut() {
+            if *i > removed {
+                *i -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            inner: self.entries.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &JVal> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+/// Maps are equal when they hold the same members, regardless of order.
+impl PartialEq for JMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl fmt::Debug for JMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(String, JVal)> for JMap {
+    fn from_iter<I: IntoIterator<Item = (String, JVal)>>(iter: I) -> Self {
+        let mut map = JMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl Extend<(String, JVal)> for JMap {
+    fn extend<I: IntoIterator<Item = (String, JVal)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+pub struct Iter<'a> {
+    inner: std::slice::Iter<'a, (String, JVal)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a JVal);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, value)| (key, value))
+    }
+}
+
+pub struct IterMut<'a> {
+    inner: std::slice::IterMut<'a, (String, JVal)>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut JVal);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, value)| (&*key, value))
+    }
+}
+
+impl IntoIterator for JMap {
+    type Item = (String, JVal);
+    type IntoIter = std::vec::IntoIter<(String, JVal)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a JMap {
+    type Item = (&'a String, &'a JVal);
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut JMap {
+    type Item = (&'a String, &'a mut JVal);
+    type IntoIter = IterMut<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+fn map_of(keys: &[&str]) -> JMap {
+    keys.iter()
+        .enumerate()
+        .map(|(i, key)| (key.to_string(), JVal::from(i)))
+        .collect()
+}
+
+#[test]
+fn test_preserves_insertion_order() {
+    let map = map_of(&["zebra", "apple", "mango"]);
+
+    let keys: Vec<&String> = map.keys().collect();
+    assert_eq!(keys, vec!["zebra", "apple", "mango"]);
+}
+
+#[test]
+fn test_insert_replaces_in_place() {
+    let mut map = map_of(&["a", "b", "c"]);
+
+    assert_eq!(
+        map.insert("a".to_string(), JVal::JNull),
+        Some(JVal::from(0))
+    );
+    assert_eq!(map.len(), 3);
+    let entries: Vec<(&String, &JVal)> = map.iter().collect();
+    assert_eq!(entries[0], (&"a".to_string(), &JVal::JNull));
+}
+
+#[test]
+fn test_get_and_get_mut() {
+    let mut map = map_of(&["a", "b"]);
+
+    assert_eq!(map.get("b"), Some(&JVal::from(1)));
+    assert_eq!(map.get("missing"), None);
+    *map.get_mut("b").unwrap() = JVal::JBool(true);
+    assert_eq!(map.get("b"), Some(&JVal::JBool(true)));
+    assert!(map.contains_key("a"));
+}
+
+#[test]
+fn test_remove_keeps_order_and_index() {
+    let mut map = map_of(&["a", "b", "c", "d"]);
+
+    assert_eq!(map.remove("b"), Some(JVal::from(1)));
+    assert_eq!(map.remove("b"), None);
+    let keys: Vec<&String> = map.keys().collect();
+    assert_eq!(keys, vec!["a", "c", "d"]);
+    assert_eq!(map.get("d"), Some(&JVal::from(3)));
+
+    map.insert("b".to_string(), JVal::JNull);
+    let keys: Vec<&String> = map.keys().collect();
+    assert_eq!(keys, vec!["a", "c", "d", "b"]);
+}
+
+#[test]
+fn test_equality_ignores_order() {
+    let mut reversed = JMap::new();
+    reversed.insert("b".to_string(), JVal::from(1));
+    reversed.insert("a".to_string(), JVal::from(0));
+
+    assert_eq!(map_of(&["a", "b"]), reversed);
+    assert_ne!(map_of(&["a", "b"]), map_of(&["a", "c"]));
+}
+
+#[test]
+fn test_debug_output_in_order() {
+    let map = map_of(&["z", "a"]);
+
+    assert_eq!(format!("{:?}", map), r#"{"z": JInt(0), "a": JInt(1)}"#);
+}
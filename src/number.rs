@@ -0,0 +1,325 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+use crate::error::ParseError;
+use crate::lexer;
+use crate::reader::Position;
+
+#[cfg(test)]
+mod tests;
+
+/// A JSON number, kept as the text it was written with, so that integers beyond 2^53
+/// and long decimal fractions survive a round trip digit for digit.
+#[derive(Clone)]
+pub struct Number {
+    // Always matches the RFC 8259 number grammar, unless it is one of `NON_FINITE`
+    text: String,
+}
+
+/// The spellings `ParseOptions::allow_nan_infinity` accepts for numbers JSON has none for.
+pub(crate) const NON_FINITE: [&str; 3] = ["NaN", "Infinity", "-Infinity"];
+
+/// A number's place on the number line, extended with the infinities and NaN.
+/// NaN sorts above everything else and equals itself, keeping the order total.
+#[derive(Debug, PartialEq)]
+enum Value {
+    NegInfinity,
+    Finite(Decimal),
+    Infinity,
+    NaN,
+}
+
+impl Value {
+    fn new(text: &str) -> Self {
+        match text {
+            "-Infinity" => Value::NegInfinity,
+            "Infinity" => Value::Infinity,
+            "NaN" => Value::NaN,
+            _ => Value::Finite(Decimal::new(text)),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Value::NegInfinity => 0,
+            Value::Finite(_) => 1,
+            Value::Infinity => 2,
+            Value::NaN => 3,
+        }
+    }
+
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Finite(a), Value::Finite(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        match self {
+            Value::Finite(decimal) => decimal.to_i128(),
+            _ => None,
+        }
+    }
+}
+
+/// The exact value of a number as `digits × 10^exponent`, negated if `negative`.
+/// `digits` has no leading or trailing zeros, so equal values have equal parts.
+#[derive(Debug, PartialEq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    fn new(text: &str) -> Self {
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(at) => (&text[..at], parse_exponent(&text[at + 1..])),
+            None => (text, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, mantissa),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let digits = format!("{}{}", integer, fraction);
+        let digits = digits.trim_start_matches('0');
+        let significant = digits.trim_end_matches('0');
+        let exponent = exponent
+            .saturating_sub(fraction.len() as i64)
+            .saturating_add((digits.len() - significant.len()) as i64);
+
+        if significant.is_empty() {
+            // Zero, whatever its sign or exponent
+            return Decimal {
+                negative: false,
+                digits: String::new(),
+                exponent: 0,
+            };
+        }
+        Decimal {
+            negative,
+            digits: significant.to_string(),
+            exponent,
+        }
+    }
+
+    fn sign(&self) -> i8 {
+        match (self.digits.is_empty(), self.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        }
+    }
+
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let sign = self.sign();
+        if sign != other.sign() || sign == 0 {
+            return sign.cmp(&other.sign());
+        }
+        // Where the leading digit sits decides first, then the digits themselves
+        let magnitude = |decimal: &Decimal| decimal.digits.len() as i128 + decimal.exponent as i128;
+        let ordering = magnitude(self)
+            .cmp(&magnitude(other))
+            .then_with(|| self.digits.cmp(&other.digits));
+        if sign < 0 {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        if self.exponent < 0 || (self.digits.len() as i64).saturating_add(self.exponent) > 39 {
+            return None;
+        }
+        let mut value: i128 = 0;
+        for digit in self.digits.bytes() {
+            value = value
+                .checked_mul(10)?
+                .checked_add(i128::from(digit - b'0'))?;
+        }
+        for _ in 0..self.exponent {
+            value = value.checked_mul(10)?;
+        }
+        Some(if self.negative { -value } else { value })
+    }
+}
+
+// Exponents too large for an `i64` describe values no type can hold anyway
+fn parse_exponent(text: &str) -> i64 {
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let magnitude = digits.bytes().fold(0i64, |value, digit| {
+        value
+            .saturating_mul(10)
+            .saturating_add(i64::from(digit - b'0'))
+    });
+    if negative {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+impl Number {
+    /// Wraps text the lexer has already checked against the number grammar, or one
+    /// of `NaN`, `Infinity` and `-Infinity`.
+    pub(crate) fn from_valid_text(text: String) -> Self {
+        Number { text }
+    }
+
+    /// The shortest text that reads back as `num`, or `None` for NaN and the
+    /// infinities, which JSON cannot represent.
+    pub fn from_f64(num: f64) -> Option<Self> {
+        if !num.is_finite() {
+            return None;
+        }
+        let text = if num.fract() == 0.0 && num.abs() < 1e16 {
+            // Display drops the `.0` but never switches to an exponent, so only use it
+            // where Debug would have printed every digit anyway
+            format!("{}", num)
+        } else {
+            format!("{:?}", num)
+        };
+        Some(Number { text })
+    }
+
+    /// The number exactly as written in the input.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The nearest `f64`, or `None` if a finite number is too large for one.
+    /// `NaN` and the infinities convert to their `f64` counterparts.
+    pub fn as_f64(&self) -> Option<f64> {
+        let num: f64 = self.text.parse().expect("number text is valid");
+        (num.is_finite() || !self.is_finite()).then_some(num)
+    }
+
+    /// Whether the number is other than `NaN`, `Infinity` or `-Infinity`, which can
+    /// only be read with `ParseOptions::allow_nan_infinity`.
+    pub fn is_finite(&self) -> bool {
+        !NON_FINITE.contains(&self.text.as_str())
+    }
+
+    /// Returns the number if it is a whole number that fits in an `i64`, whether
+    /// written as `42`, `42.0` or `4.2e1`.
+    pub fn as_i64(&self) -> Option<i64> {
+        Value::new(&self.text).to_i128()?.try_into().ok()
+    }
+
+    /// Returns the number if it is a whole number that fits in a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        Value::new(&self.text).to_i128()?.try_into().ok()
+    }
+
+    /// How the lexer classifies the number: written without a fraction or exponent,
+    /// it is an `i64` if it fits, else a `u64` if that fits. Anything else, including
+    /// `-0`, which no integer type can hold, is a float.
+    pub(crate) fn kind(&self) -> Kind {
+        if self.text.contains(['.', 'e', 'E']) || self.text == "-0" {
+            return Kind::Float;
+        }
+        match (self.text.parse(), self.text.parse()) {
+            (Ok(num), _) => Kind::Int(num),
+            (_, Ok(num)) => Kind::Uint(num),
+            _ => Kind::Float,
+        }
+    }
+}
+
+pub(crate) enum Kind {
+    Int(i64),
+    Uint(u64),
+    Float,
+}
+
+/// Numbers are equal when they have the same value, however they were written,
+/// so `1`, `1.0` and `10e-1` are all equal. No rounding is involved.
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.text == other.text || Value::new(&self.text) == Value::new(&other.text)
+    }
+}
+
+impl Eq for Number {}
+
+/// Orders numbers by their exact value, with `-Infinity` first and `NaN` last.
+impl Ord for Number {
+    fn cmp(&self, other: &Number) -> Ordering {
+        Value::new(&self.text).cmp(&Value::new(&other.text))
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        if self.text == "NaN" {
+            return self;
+        }
+        let text = match self.text.strip_prefix('-') {
+            Some(positive) => positive.to_string(),
+            None => format!("-{}", self.text),
+        };
+        Number { text }
+    }
+}
+
+/// Writes the number as it was written in the input, e.g. `42.0` rather than `42`.
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Writes the number as it was written in the input.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Accepts text matching the JSON number grammar, e.g. `-12.5e3`.
+impl FromStr for Number {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, ParseError> {
+        match lexer::validate_number(text) {
+            Ok(()) => Ok(Number::from_valid_text(text.to_string())),
+            Err(reason) => Err(ParseError::InvalidNumber {
+                text: text.to_string(),
+                reason,
+                position: Position::default(),
+            }),
+        }
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(num: $ty) -> Self {
+                    Number { text: num.to_string() }
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
@@ -0,0 +1,167 @@
+use std::cmp::Ordering;
+
+use super::*;
+
+fn number(text: &str) -> Number {
+    text.parse().unwrap()
+}
+
+#[test]
+fn test_keeps_original_text() {
+    for text in [
+        "9007199254740993",
+        "-0",
+        "1.50",
+        "12345678901234567890.000000001",
+        "6.02E+23",
+    ] {
+        assert_eq!(number(text).as_str(), text);
+        assert_eq!(number(text).to_string(), text);
+    }
+}
+
+#[test]
+fn test_rejects_invalid_text() {
+    for text in ["", "01", "1.", ".5", "+1", "1e", "--1", "0x10", "1 "] {
+        assert!(
+            matches!(
+                text.parse::<Number>(),
+                Err(ParseError::InvalidNumber { .. })
+            ),
+            "{:?}",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_integer_accessors() {
+    assert_eq!(number("9007199254740993").as_i64(), Some(9007199254740993));
+    assert_eq!(number("18446744073709551615").as_u64(), Some(u64::MAX));
+    assert_eq!(number("18446744073709551615").as_i64(), None);
+    assert_eq!(number("18446744073709551616").as_u64(), None);
+    assert_eq!(number("-9223372036854775808").as_i64(), Some(i64::MIN));
+    assert_eq!(number("-1").as_u64(), None);
+    assert_eq!(number("42.0").as_i64(), Some(42));
+    assert_eq!(number("4.2e1").as_u64(), Some(42));
+    assert_eq!(number("4200e-2").as_i64(), Some(42));
+    assert_eq!(number("42.5").as_i64(), None);
+    assert_eq!(number("1e400").as_i64(), None);
+    assert_eq!(number("-0.0").as_i64(), Some(0));
+}
+
+#[test]
+fn test_integer_accessors_with_huge_exponents() {
+    // The exponent saturates rather than overflowing on the way to an answer
+    let huge = number("1e99999999999999999999999");
+    assert_eq!(huge.as_i64(), None);
+    assert_eq!(huge.as_u64(), None);
+    assert_eq!(number("-1e99999999999999999999999").as_i64(), None);
+    assert_eq!(number("1e-99999999999999999999999").as_u64(), None);
+    assert_eq!(number("-1e-99999999999999999999999").as_i64(), None);
+    assert_eq!(number("0e99999999999999999999999").as_i64(), Some(0));
+}
+
+#[test]
+fn test_float_accessor() {
+    assert_eq!(number("0.1").as_f64(), Some(0.1));
+    assert_eq!(number("-2.5e-3").as_f64(), Some(-0.0025));
+    assert_eq!(
+        number("9007199254740993").as_f64(),
+        Some(9007199254740992.0)
+    );
+    assert_eq!(number("1e400").as_f64(), None);
+    assert_eq!(number("-1e400").as_f64(), None);
+}
+
+#[test]
+fn test_equality_is_by_exact_value() {
+    assert_eq!(number("1"), number("1.0"));
+    assert_eq!(number("1"), number("10e-1"));
+    assert_eq!(number("0"), number("-0.0e5"));
+    assert_eq!(number("1200"), number("1.2e3"));
+    // These two round to the same `f64`, but are different numbers
+    assert_ne!(number("9007199254740993"), number("9007199254740992"));
+    assert_ne!(number("1"), number("-1"));
+}
+
+#[test]
+fn test_ordering_is_by_exact_value() {
+    let mut numbers: Vec<Number> = [
+        "10",
+        "-2.5",
+        "1e-3",
+        "0",
+        "9007199254740993",
+        "-1e10",
+        "9.5",
+        "9007199254740992",
+    ]
+    .into_iter()
+    .map(number)
+    .collect();
+    numbers.sort();
+    let texts: Vec<&str> = numbers.iter().map(Number::as_str).collect();
+    assert_eq!(
+        texts,
+        [
+            "-1e10",
+            "-2.5",
+            "0",
+            "1e-3",
+            "9.5",
+            "10",
+            "9007199254740992",
+            "9007199254740993"
+        ]
+    );
+    assert_eq!(number("-10").cmp(&number("-9")), Ordering::Less);
+    assert_eq!(number("1e1000").cmp(&number("9e999")), Ordering::Greater);
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(Number::from(u64::MAX).as_str(), "18446744073709551615");
+    assert_eq!(Number::from(-7i8).as_str(), "-7");
+    assert_eq!(Number::from_f64(0.1).unwrap().as_str(), "0.1");
+    assert_eq!(Number::from_f64(30.0).unwrap().as_str(), "30");
+    assert_eq!(Number::from_f64(1e16).unwrap().as_str(), "1e16");
+    assert!(Number::from_f64(f64::NAN).is_none());
+    assert!(Number::from_f64(f64::INFINITY).is_none());
+    assert_eq!((-number("2.50")).as_str(), "-2.50");
+    assert_eq!((-number("-1e3")).as_str(), "1e3");
+}
+
+#[test]
+fn test_non_finite() {
+    let non_finite = |text: &str| Number::from_valid_text(text.to_string());
+    let (nan, infinity, neg_infinity) = (
+        non_finite("NaN"),
+        non_finite("Infinity"),
+        non_finite("-Infinity"),
+    );
+
+    assert!(nan.as_f64().unwrap().is_nan());
+    assert_eq!(infinity.as_f64(), Some(f64::INFINITY));
+    assert_eq!(neg_infinity.as_f64(), Some(f64::NEG_INFINITY));
+    assert_eq!(infinity.as_i64(), None);
+    assert_eq!(nan.as_u64(), None);
+    assert!(!nan.is_finite() && !infinity.is_finite());
+    assert!(number("1e400").is_finite());
+
+    // NaN equals itself and sorts last, so the order stays total
+    assert_eq!(nan, non_finite("NaN"));
+    let mut numbers = [
+        nan.clone(),
+        number("1e400"),
+        infinity.clone(),
+        neg_infinity.clone(),
+    ];
+    numbers.sort();
+    let texts: Vec<&str> = numbers.iter().map(Number::as_str).collect();
+    assert_eq!(texts, ["-Infinity", "1e400", "Infinity", "NaN"]);
+
+    assert_eq!((-infinity).as_str(), "-Infinity");
+    assert_eq!((-neg_infinity).as_str(), "Infinity");
+    assert_eq!((-nan).as_str(), "NaN");
+}
@@ -0,0 +1,186 @@
+use crate::error::ParseError;
+use crate::reader::Position;
+
+/// What to do when an object contains the same key more than once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep the value of the last occurrence.
+    #[default]
+    LastWins,
+    /// Keep the value of the first occurrence and ignore later ones.
+    FirstWins,
+    /// Fail with `ParseError::DuplicateKey`.
+    Error,
+    /// Gather every value for the key, in source order, into an array. Keys that occur
+    /// once get a one-element array too, so a collected value is never mistaken for an
+    /// array in the input.
+    CollectAll,
+}
+
+/// Caps on the size and shape of a document, for parsing untrusted input without
+/// letting it exhaust memory. `None` leaves that dimension unlimited, the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// How deeply objects and arrays may nest. The root container is at depth 1.
+    pub max_depth: Option<usize>,
+    /// The longest string or object key, in bytes of UTF-8 once unescaped.
+    pub max_string_length: Option<usize>,
+    /// The most members a single object may have.
+    pub max_object_members: Option<usize>,
+    /// The most elements a single array may have.
+    pub max_array_elements: Option<usize>,
+    /// The most bytes of input that will be read.
+    pub max_input_size: Option<usize>,
+    /// The longest number, in characters as written.
+    pub max_number_length: Option<usize>,
+}
+
+impl Limits {
+    /// Fails if opening a container at `position` would nest it `depth` levels deep.
+    pub(crate) fn check_depth(&self, depth: usize, position: Position) -> Result<(), ParseError> {
+        match self.max_depth {
+            Some(limit) if depth > limit => Err(ParseError::DepthLimitExceeded { limit, position }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if the member at `position` is number `count` of an object, or of an array
+    /// if `array` is set, and that is more than allowed.
+    pub(crate) fn check_members(
+        &self,
+        array: bool,
+        count: usize,
+        position: Position,
+    ) -> Result<(), ParseError> {
+        match (array, self.max_array_elements, self.max_object_members) {
+            (true, Some(limit), _) if count > limit => {
+                Err(ParseError::TooManyElements { limit, position })
+            }
+            (false, _, Some(limit)) if count > limit => {
+                Err(ParseError::TooManyMembers { limit, position })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Settings shared by the reader, lexer and parser.
+///
+/// The default is strict RFC 8259, with every extension off and no limits. Start from
+/// a preset and adjust it with the builder methods, each named after the field it sets,
+/// e.g. `ParseOptions::json5().duplicate_keys(DuplicateKeys::Error).max_depth(64)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Replace invalid UTF-8 sequences with U+FFFD instead of failing.
+    pub replace_invalid_utf8: bool,
+    /// Replace unpaired UTF-16 surrogates in `\u` escapes with U+FFFD instead of failing.
+    pub replace_lone_surrogates: bool,
+    /// Only accept an object or array as the document root, as RFC 4627 did.
+    pub container_root_only: bool,
+    /// Accept `true`, `false` and `null` in any letter case, e.g. `True` or `NULL`.
+    pub case_insensitive_keywords: bool,
+    /// Separate tokens with any Unicode whitespace, e.g. U+00A0 or U+2028, rather than
+    /// only space, tab, line feed and carriage return.
+    pub allow_unicode_whitespace: bool,
+    /// Accept characters below U+0020, such as a line break, unescaped inside strings.
+    pub allow_control_characters: bool,
+    /// Skip `// line` and `/* block */` comments wherever whitespace may appear.
+    pub allow_comments: bool,
+    /// Accept a comma after the last member of an object or array, as in `[1, 2,]`.
+    pub allow_trailing_commas: bool,
+    /// Accept strings and keys in single quotes, inside which `"` needs no escape
+    /// and `\'` is an escape for `'`.
+    pub allow_single_quotes: bool,
+    /// Accept `NaN`, `Infinity` and `-Infinity` as numbers.
+    pub allow_nan_infinity: bool,
+    /// How to resolve a key repeated within one object; the last value wins by default.
+    pub duplicate_keys: DuplicateKeys,
+    /// Caps on depth, sizes and lengths; every one is unlimited by default.
+    pub limits: Limits,
+}
+
+// Builder methods named after the fields they set, limits included
+macro_rules! setters {
+    ($($field:ident: $ty:ty),*; limits: $($limit:ident),*) => {
+        $(
+            #[doc = concat!("Sets `", stringify!($field), "`.")]
+            #[must_use]
+            pub fn $field(mut self, value: $ty) -> Self {
+                self.$field = value;
+                self
+            }
+        )*
+        $(
+            #[doc = concat!("Sets `limits.", stringify!($limit), "`.")]
+            #[must_use]
+            pub fn $limit(mut self, limit: usize) -> Self {
+                self.limits.$limit = Some(limit);
+                self
+            }
+        )*
+    };
+}
+
+impl ParseOptions {
+    /// Exactly the RFC 8259 grammar, the same as `ParseOptions::default()`.
+    pub fn strict() -> Self {
+        ParseOptions::default()
+    }
+
+    /// The JSON5 extensions this parser supports: comments, trailing commas,
+    /// single-quoted strings, `NaN` and the infinities, and Unicode whitespace.
+    pub fn json5() -> Self {
+        ParseOptions {
+            allow_unicode_whitespace: true,
+            allow_comments: true,
+            allow_trailing_commas: true,
+            allow_single_quotes: true,
+            allow_nan_infinity: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// Every extension, with keywords in any case, raw control characters in strings
+    /// and malformed text replaced by U+FFFD rather than rejected. For salvaging
+    /// hand-edited or damaged input.
+    pub fn lenient() -> Self {
+        ParseOptions {
+            allow_control_characters: true,
+            replace_invalid_utf8: true,
+            replace_lone_surrogates: true,
+            case_insensitive_keywords: true,
+            ..ParseOptions::json5()
+        }
+    }
+
+    setters! {
+        replace_invalid_utf8: bool,
+        replace_lone_surrogates: bool,
+        container_root_only: bool,
+        case_insensitive_keywords: bool,
+        allow_unicode_whitespace: bool,
+        allow_control_characters: bool,
+        allow_comments: bool,
+        allow_trailing_commas: bool,
+        allow_single_quotes: bool,
+        allow_nan_infinity: bool,
+        duplicate_keys: DuplicateKeys,
+        limits: Limits;
+        limits: max_depth,
+        max_string_length,
+        max_object_members,
+        max_array_elements,
+        max_input_size,
+        max_number_length
+    }
+}
+
+/// Settings for the serializer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Put each member on its own line, indented by this many spaces per level.
+    /// `None` writes the whole document on one line.
+    pub indent: Option<usize>,
+    /// Escape every non-ASCII character as `\uXXXX`, using a surrogate pair beyond U+FFFF.
+    pub ascii_only: bool,
+}
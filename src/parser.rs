@@ -1,220 +1,198 @@
-use crate::lexer::{Token, TokenReader};
+use crate::error::ParseError;
+use crate::events::{Event, EventReader};
+use crate::lexer::TokenReader;
+use crate::map::JMap;
+use crate::number::Number;
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::reader::{JsonReader, Position};
 use std::collections::HashMap;
+use std::io::Read;
 
 #[cfg(test)]
 mod tests;
 
-const UNEXPECTED_EOF: &str = "Unexpected end of file while parsing JSON";
-
-#[derive(Debug, PartialEq, Clone)]
+/// A JSON value. Integers are kept apart from floats, so `42` and `42.0` parse to
+/// different variants, though they compare equal; see the `PartialEq` impl.
+#[derive(Debug, Clone, Default)]
 pub enum JVal {
     JString(String),
     JBool(bool),
-    JNum(f64),
-    JObject(std::collections::HashMap<String, JVal>),
+    /// An integer that fits in an `i64`.
+    JInt(i64),
+    /// A non-negative integer too large for an `i64` that fits in a `u64`.
+    JUint(u64),
+    /// Any other number: one written with a fraction or exponent, `-0`, or an integer
+    /// too large for a `u64`. Kept as written.
+    JFloat(Number),
+    JObject(JMap),
     JArray(Vec<JVal>),
+    #[default]
     JNull,
 }
 
+/// Parses a complete document held in memory.
+pub fn from_str(input: &str) -> Result<JVal, ParseError> {
+    parse(&mut TokenReader::new(JsonReader::from_str(input)))
+}
+
+pub fn from_slice(input: &[u8]) -> Result<JVal, ParseError> {
+    parse(&mut TokenReader::new(JsonReader::from_slice(input)))
+}
+
+/// Parses a complete document from any byte source, such as stdin or a socket.
+pub fn from_reader<R: Read>(reader: R) -> Result<JVal, ParseError> {
+    parse(&mut TokenReader::new(JsonReader::from_reader(reader)))
+}
+
+/// Parses a complete document held in memory, with strictness, extensions and
+/// limits set by `options`.
+pub fn from_str_with_options(input: &str, options: ParseOptions) -> Result<JVal, ParseError> {
+    from_reader_with_options(input.as_bytes(), options)
+}
+
+pub fn from_reader_with_options<R: Read>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<JVal, ParseError> {
+    parse(&mut TokenReader::new(JsonReader::from_reader_with_options(
+        reader, options,
+    )))
+}
+
+/// An object or array under construction.
 #[derive(Debug)]
-enum ParseState {
-    Object(HashMap<String, JVal>),
+enum PartialValue {
+    Object(PartialObject, Option<String>),
     Array(Vec<JVal>),
-    Value(JVal),
 }
 
-pub fn parse(iter: &mut TokenReader) -> Result<JVal, anyhow::Error> {
-    let mut state_stack: Vec<ParseState> = Vec::new();
-    let mut key_stack: Vec<String> = Vec::new();
-    let mut comma_stack: Vec<bool> = Vec::new();
-
-    match iter
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("Empty input"))??
-    {
-        Token::OpenObject => state_stack.push(ParseState::Object(HashMap::new())),
-        Token::OpenArray => state_stack.push(ParseState::Array(Vec::new())),
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Invalid JSON: Document must start with either '{{' or '['"
-            ))
-        }
-    }
+/// An object under construction, with the bookkeeping its duplicate-key policy needs.
+#[derive(Debug, Default)]
+struct PartialObject {
+    pairs: JMap,
+    // Where each key first occurred, only filled in for `DuplicateKeys::Error`
+    key_positions: HashMap<String, Position>,
+}
 
-    while !state_stack.is_empty() {
-        let last_state = state_stack.pop();
-        match last_state {
-            Some(ParseState::Object(pairs)) => {
-                parse_object(
-                    iter,
-                    &mut state_stack,
-                    &mut key_stack,
-                    &mut comma_stack,
-                    pairs,
-                )?;
-            }
-            Some(ParseState::Array(array)) => {
-                parse_array(iter, &mut state_stack, &mut comma_stack, array)?;
-            }
-            Some(ParseState::Value(val)) => {
-                match parse_value(&mut state_stack, &mut key_stack, val)? {
-                    Some(final_value) => return Ok(final_value),
-                    None => continue,
-                }
-            }
+impl PartialObject {
+    fn check_key(
+        &mut self,
+        key: &str,
+        position: Position,
+        policy: DuplicateKeys,
+    ) -> Result<(), ParseError> {
+        if policy != DuplicateKeys::Error {
+            return Ok(());
+        }
+        match self.key_positions.get(key) {
+            Some(&first) => Err(ParseError::DuplicateKey {
+                key: key.to_string(),
+                first,
+                position,
+            }),
             None => {
-                return Err(anyhow::anyhow!(
-                    "Internal parser error: Invalid parse state"
-                ))
+                self.key_positions.insert(key.to_string(), position);
+                Ok(())
             }
         }
     }
 
-    Err(anyhow::anyhow!(
-        "Invalid JSON structure: Unclosed object or array"
-    ))
-}
-
-fn parse_object(
-    iter: &mut TokenReader,
-    state_stack: &mut Vec<ParseState>,
-    key_stack: &mut Vec<String>,
-    comma_stack: &mut Vec<bool>,
-    mut pairs: HashMap<String, JVal>,
-) -> Result<(), anyhow::Error> {
-    while let Some(token) = iter.next() {
-        match token? {
-            Token::TString(key) => {
-                let colon = iter
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!(UNEXPECTED_EOF))??;
-                if colon != Token::Colon {
-                    return Err(anyhow::anyhow!(
-                        "Invalid JSON object: Expected ':' after key '{}'",
-                        key
-                    ));
-                }
-
-                let val = iter
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!(UNEXPECTED_EOF))??;
-                match val {
-                    Token::OpenArray => {
-                        check_comma(pairs.len(), comma_stack)?;
-                        key_stack.push(key);
-                        state_stack.push(ParseState::Object(pairs));
-                        state_stack.push(ParseState::Array(Vec::new()));
-                        break;
-                    }
-                    Token::OpenObject => {
-                        check_comma(pairs.len(), comma_stack)?;
-                        key_stack.push(key);
-                        state_stack.push(ParseState::Object(pairs));
-                        state_stack.push(ParseState::Object(HashMap::new()));
-                        break;
-                    }
-                    val => {
-                        check_comma(pairs.len(), comma_stack)?;
-                        pairs.insert(key, parse_atom(val)?);
-                    }
-                }
-            }
-            Token::Comma => comma_stack.push(true),
-            Token::CloseObject => {
-                state_stack.push(ParseState::Value(JVal::JObject(pairs)));
-                break;
+    fn insert(&mut self, key: String, value: JVal, policy: DuplicateKeys) {
+        match (policy, self.pairs.get_mut(&key)) {
+            (DuplicateKeys::FirstWins, Some(_)) => {}
+            (DuplicateKeys::CollectAll, Some(JVal::JArray(values))) => values.push(value),
+            (DuplicateKeys::CollectAll, _) => {
+                self.pairs.insert(key, JVal::JArray(vec![value]));
             }
             _ => {
-                return Err(anyhow::anyhow!(
-                    "Invalid JSON object structure: Expected string key, '}}', or ','"
-                ))
+                self.pairs.insert(key, value);
             }
         }
     }
-    Ok(())
 }
 
-fn parse_array(
-    iter: &mut TokenReader,
-    state_stack: &mut Vec<ParseState>,
-    comma_stack: &mut Vec<bool>,
-    mut array: Vec<JVal>,
-) -> Result<(), anyhow::Error> {
-    while let Some(token) = iter.next() {
-        match token? {
-            Token::OpenArray => {
-                check_comma(array.len(), comma_stack)?;
-                state_stack.push(ParseState::Array(array));
-                state_stack.push(ParseState::Array(Vec::new()));
-                break;
-            }
-            Token::OpenObject => {
-                check_comma(array.len(), comma_stack)?;
-                state_stack.push(ParseState::Array(array));
-                state_stack.push(ParseState::Object(HashMap::new()));
-                break;
-            }
-            Token::Comma => comma_stack.push(true),
-            Token::CloseArray => {
-                state_stack.push(ParseState::Value(JVal::JArray(array)));
-                break;
-            }
-            val => {
-                check_comma(array.len(), comma_stack)?;
-                array.push(parse_atom(val)?);
-            }
-        }
+/// Parses a complete document, failing if anything but whitespace follows the root value.
+pub fn parse<R: Read>(iter: &mut TokenReader<R>) -> Result<JVal, ParseError> {
+    let value = parse_partial(iter)?;
+    expect_end(iter)?;
+    Ok(value)
+}
+
+/// Fails unless the token stream has nothing left but whitespace.
+pub(crate) fn expect_end<R: Read>(iter: &mut TokenReader<R>) -> Result<(), ParseError> {
+    match iter.next() {
+        None => Ok(()),
+        Some(Ok(token)) => Err(ParseError::TrailingData {
+            position: token.span.start,
+        }),
+        // Failing to read the input, or a limit, is not the same as finding more of it
+        Some(Err(
+            e @ (ParseError::Io { .. }
+            | ParseError::InvalidUtf8 { .. }
+            | ParseError::IncompleteUtf8 { .. }
+            | ParseError::InputTooLarge { .. }
+            | ParseError::StringTooLong { .. }
+            | ParseError::NumberTooLong { .. }),
+        )) => Err(e),
+        // A malformed token is trailing data all the same
+        Some(Err(e)) => Err(ParseError::TrailingData {
+            position: e.position(),
+        }),
     }
-    Ok(())
 }
 
-fn parse_value(
-    state_stack: &mut Vec<ParseState>,
-    key_stack: &mut Vec<String>,
-    value: JVal,
-) -> Result<Option<JVal>, anyhow::Error> {
-    match state_stack.pop() {
-        Some(ParseState::Array(mut array)) => {
-            array.push(value);
-            state_stack.push(ParseState::Array(array));
-        }
-        Some(ParseState::Object(mut pairs)) => match key_stack.pop() {
-            Some(key) => {
-                pairs.insert(key, value);
-                state_stack.push(ParseState::Object(pairs));
+/// Parses the first value in the token stream and leaves the rest unread, so
+/// that e.g. a sequence of concatenated documents can be read one at a time.
+pub fn parse_partial<R: Read>(iter: &mut TokenReader<R>) -> Result<JVal, ParseError> {
+    let policy = iter.options().duplicate_keys;
+    let mut events = EventReader::new(iter);
+    // The open containers, each object with the key its next value belongs to
+    let mut stack: Vec<PartialValue> = Vec::new();
+
+    while let Some(event) = events.next() {
+        let value = match event? {
+            Event::StartObject => {
+                stack.push(PartialValue::Object(PartialObject::default(), None));
+                continue;
             }
-            None => {
-                return Err(anyhow::anyhow!(
-                    "Invalid JSON structure: Missing key for nested object/array"
-                ))
+            Event::StartArray => {
+                stack.push(PartialValue::Array(Vec::new()));
+                continue;
+            }
+            Event::Key(key) => {
+                if let Some(PartialValue::Object(object, pending)) = stack.last_mut() {
+                    object.check_key(&key, events.position(), policy)?;
+                    *pending = Some(key);
+                }
+                continue;
             }
-        },
-        Some(ParseState::Value(_)) => {
-            return Err(anyhow::anyhow!(
-                "Invalid JSON structure: Cannot have consecutive values without separators"
-            ))
+            Event::Value(value) => value,
+            Event::EndObject | Event::EndArray => match stack.pop() {
+                Some(PartialValue::Object(object, _)) => JVal::JObject(object.pairs),
+                Some(PartialValue::Array(array)) => JVal::JArray(array),
+                None => return Err(internal(&events, "Unbalanced end of object or array")),
+            },
+        };
+
+        match stack.last_mut() {
+            Some(PartialValue::Array(array)) => array.push(value),
+            Some(PartialValue::Object(object, pending)) => match pending.take() {
+                Some(key) => object.insert(key, value, policy),
+                None => return Err(internal(&events, "Missing key for object member")),
+            },
+            None => return Ok(value),
         }
-        None => return Ok(Some(value)),
     }
-    Ok(None)
-}
 
-fn parse_atom(token: Token) -> Result<JVal, anyhow::Error> {
-    match token {
-        Token::TString(val) => Ok(JVal::JString(val)),
-        Token::TNumber(val) => Ok(JVal::JNum(val)),
-        Token::TBool(val) => Ok(JVal::JBool(val)),
-        Token::TNull => Ok(JVal::JNull),
-        _ => Err(anyhow::anyhow!(
-            "Invalid JSON value: Expected string, number, boolean, or null and got {:?}",
-            token
-        )),
-    }
+    Err(internal(
+        &events,
+        "Event stream ended before the value was complete",
+    ))
 }
 
-fn check_comma(length: usize, comma_stack: &mut Vec<bool>) -> Result<(), anyhow::Error> {
-    if length > 0 && comma_stack.pop() != Some(true) {
-        return Err(anyhow::anyhow!("Missing comma between elements"));
+fn internal<R: Read>(events: &EventReader<R>, message: &'static str) -> ParseError {
+    ParseError::Internal {
+        message,
+        position: events.position(),
     }
-    Ok(())
 }
@@ -1,12 +1,21 @@
 use super::*;
-use crate::{lexer::TokenReader, reader::JsonReader};
+use crate::error::ParseError;
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::{
+    lexer::{Token, TokenReader},
+    reader::{JsonReader, Position},
+};
 
 fn parse_str(input: &str) -> JVal {
-    let temp_file = tempfile::NamedTempFile::new().unwrap();
-    std::fs::write(&temp_file, input).unwrap();
-    let file_reader = JsonReader::new(temp_file.path().to_path_buf()).unwrap();
-    let mut reader = TokenReader::new(file_reader);
-    parse(&mut reader).unwrap()
+    parse_str_with(input, ParseOptions::default())
+}
+
+fn parse_str_with(input: &str, options: ParseOptions) -> JVal {
+    let mut reader = TokenReader::new(JsonReader::from_reader_with_options(
+        input.as_bytes(),
+        options,
+    ));
+    parse(&mut reader).unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[test]
@@ -16,7 +25,7 @@ fn test_parse_simple_object() {
     
     if let JVal::JObject(map) = result {
         assert_eq!(map.get("name"), Some(&JVal::JString("John".to_string())));
-        assert_eq!(map.get("age"), Some(&JVal::JNum(30.0)));
+        assert_eq!(map.get("age"), Some(&JVal::from(30)));
     } else {
         panic!("Expected JObject");
     }
@@ -29,7 +38,7 @@ fn test_parse_null_key() {
     
     if let JVal::JObject(map) = result {
         assert_eq!(map.get("name"), Some(&JVal::JNull));
-        assert_eq!(map.get("age"), Some(&JVal::JNum(30.0)));
+        assert_eq!(map.get("age"), Some(&JVal::from(30)));
     } else {
         panic!("Expected JObject");
     }
@@ -41,9 +50,9 @@ fn test_parse_simple_array() {
     let result = parse_str(input);
     
     if let JVal::JArray(arr) = result {
-        assert_eq!(arr[0], JVal::JNum(1.0));
-        assert_eq!(arr[1], JVal::JNum(2.0));
-        assert_eq!(arr[2], JVal::JNum(3.0));
+        assert_eq!(arr[0], JVal::from(1));
+        assert_eq!(arr[1], JVal::from(2));
+        assert_eq!(arr[2], JVal::from(3));
         assert_eq!(arr[3], JVal::JString("test".to_string()));
     } else {
         panic!("Expected JArray");
@@ -73,33 +82,51 @@ fn test_parse_nested_array() {
     let result = parse_str(input);
     
     if let JVal::JArray(arr) = result {
-        assert_eq!(arr[0], JVal::JNum(1.0));
+        assert_eq!(arr[0], JVal::from(1));
         if let JVal::JArray(inner) = &arr[1] {
-            assert_eq!(inner[0], JVal::JNum(2.0));
-            assert_eq!(inner[1], JVal::JNum(3.0));
+            assert_eq!(inner[0], JVal::from(2));
+            assert_eq!(inner[1], JVal::from(3));
         } else {
             panic!("Expected nested array");
         }
-        assert_eq!(arr[2], JVal::JNum(4.0));
+        assert_eq!(arr[2], JVal::from(4));
     } else {
         panic!("Expected JArray");
     }
 }
 
 #[test]
-#[should_panic(expected = "Document must start with either '{' or '['")]
+#[should_panic(expected = "Expected '{' or '[', found string \"invalid\"")]
 fn test_invalid_json_start() {
-    parse_str(r#""invalid""#);
+    let options = ParseOptions {
+        container_root_only: true,
+        ..ParseOptions::default()
+    };
+    parse_str_with(r#""invalid""#, options);
+}
+
+#[test]
+fn test_parse_scalar_roots() {
+    assert_eq!(parse_str(r#""text""#), JVal::JString("text".to_string()));
+    assert_eq!(parse_str(" 42 "), JVal::from(42));
+    assert_eq!(parse_str("true"), JVal::JBool(true));
+    assert_eq!(parse_str("null"), JVal::JNull);
+}
+
+#[test]
+#[should_panic(expected = "Expected string, number, boolean, or null, found ']'")]
+fn test_invalid_scalar_root() {
+    parse_str("]");
 }
 
 #[test]
-#[should_panic(expected = "Invalid JSON object: Expected ':' after key")]
+#[should_panic(expected = "Expected ':' after object key")]
 fn test_missing_colon() {
     parse_str(r#"{"key" "value"}"#);
 }
 
 #[test]
-#[should_panic(expected = "Invalid JSON structure: Unclosed object or array")]
+#[should_panic(expected = "Unexpected end of input")]
 fn test_unclosed_object() {
     parse_str(r#"{"key": "value""#);
 }
@@ -127,7 +154,7 @@ fn test_complex_nested_structure() {
         assert_eq!(map.get("active"), Some(&JVal::JBool(true)));
         
         if let Some(JVal::JObject(details)) = map.get("details") {
-            assert_eq!(details.get("age"), Some(&JVal::JNum(30.0)));
+            assert_eq!(details.get("age"), Some(&JVal::from(30)));
             
             if let Some(JVal::JArray(hobbies)) = details.get("hobbies") {
                 assert_eq!(hobbies[0], JVal::JString("reading".to_string()));
@@ -138,7 +165,7 @@ fn test_complex_nested_structure() {
             
             if let Some(JVal::JObject(address)) = details.get("address") {
                 assert_eq!(address.get("city"), Some(&JVal::JString("New York".to_string())));
-                assert_eq!(address.get("zip"), Some(&JVal::JNum(10001.0)));
+                assert_eq!(address.get("zip"), Some(&JVal::from(10001)));
             } else {
                 panic!("Expected address object");
             }
@@ -167,7 +194,7 @@ fn test_missing_comma_in_object() {
 fn test_valid_commas_in_array() {
     let result = parse_str(r#"[1, 2, 3]"#);
     if let JVal::JArray(arr) = result {
-        assert_eq!(arr, vec![JVal::JNum(1.0), JVal::JNum(2.0), JVal::JNum(3.0)]);
+        assert_eq!(arr, vec![JVal::from(1), JVal::from(2), JVal::from(3)]);
     } else {
         panic!("Expected array");
     }
@@ -177,10 +204,427 @@ fn test_valid_commas_in_array() {
 fn test_valid_commas_in_object() {
     let result = parse_str(r#"{"a": 1, "b": 2, "c": 3}"#);
     if let JVal::JObject(map) = result {
-        assert_eq!(map.get("a"), Some(&JVal::JNum(1.0)));
-        assert_eq!(map.get("b"), Some(&JVal::JNum(2.0))); 
-        assert_eq!(map.get("c"), Some(&JVal::JNum(3.0)));
+        assert_eq!(map.get("a"), Some(&JVal::from(1)));
+        assert_eq!(map.get("b"), Some(&JVal::from(2))); 
+        assert_eq!(map.get("c"), Some(&JVal::from(3)));
     } else {
         panic!("Expected object");
     }
 }
+
+#[test]
+fn test_trailing_data() {
+    let cases = [
+        (r#"{"a":1} garbage"#, 9),
+        (r#"{"a":1}{"b":2}"#, 8),
+        ("[1]\n]", 1),
+        ("1 2", 3),
+        ("null x", 6),
+    ];
+
+    for (input, column) in cases {
+        let err = parse_err(input);
+        assert!(matches!(err, ParseError::TrailingData { .. }), "{}", input);
+        assert_eq!(err.position().column, column, "{}", input);
+    }
+}
+
+#[test]
+fn test_trailing_whitespace_is_allowed() {
+    assert_eq!(parse_str("[1] \n\t"), JVal::JArray(vec![JVal::from(1)]));
+}
+
+#[test]
+fn test_parse_partial_leaves_remainder() {
+    let mut reader = TokenReader::new(JsonReader::from_str(r#"{"a": 1} [2] "three""#));
+
+    assert!(matches!(
+        parse_partial(&mut reader).unwrap(),
+        JVal::JObject(_)
+    ));
+    assert_eq!(
+        parse_partial(&mut reader).unwrap(),
+        JVal::JArray(vec![JVal::from(2)])
+    );
+    assert_eq!(
+        parse_partial(&mut reader).unwrap(),
+        JVal::JString("three".to_string())
+    );
+    assert!(reader.next().is_none());
+}
+
+fn parse_err(input: &str) -> ParseError {
+    from_str(input).unwrap_err()
+}
+
+#[test]
+fn test_error_positions() {
+    let cases = [
+        ("{\n  \"a\": 1\n  \"b\": 2\n}", 3, 3, 13),
+        ("[1, 2", 1, 6, 5),
+        ("{\"key\" \"value\"}", 1, 8, 7),
+        ("[1, :]", 1, 5, 4),
+        ("[\n\n  1.]", 3, 3, 5),
+    ];
+
+    for (input, line, column, offset) in cases {
+        let err = parse_err(input);
+        assert_eq!(
+            err.position(),
+            Position {
+                line,
+                column,
+                offset
+            },
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_error_variants() {
+    assert!(matches!(
+        parse_err("[1 2]"),
+        ParseError::MissingComma { .. }
+    ));
+    assert!(matches!(
+        parse_err("[1, 2"),
+        ParseError::UnexpectedEof { .. }
+    ));
+    assert!(matches!(
+        parse_err(""),
+        ParseError::UnexpectedEof {
+            expected: "JSON value",
+            ..
+        }
+    ));
+    assert!(matches!(
+        parse_err(r#"{"a": ]}"#),
+        ParseError::UnexpectedToken {
+            found: Token::CloseArray,
+            ..
+        }
+    ));
+    assert!(matches!(
+        parse_err(r#"["a\qb"]"#),
+        ParseError::InvalidEscape { ref sequence, .. } if sequence == "q"
+    ));
+    assert!(matches!(
+        parse_err("[1.]"),
+        ParseError::InvalidNumber { ref text, .. } if text == "1."
+    ));
+    assert!(matches!(
+        parse_err("[nul]"),
+        ParseError::InvalidLiteral {
+            expected: "null",
+            ..
+        }
+    ));
+    assert!(matches!(
+        parse_err(r#"["abc"#),
+        ParseError::UnexpectedEof { .. }
+    ));
+}
+
+#[test]
+fn test_object_keys_keep_source_order() {
+    let result = parse_str(r#"{"zeta": 1, "alpha": {"y": 2, "b": 3}, "mid": 4}"#);
+
+    if let JVal::JObject(map) = result {
+        let keys: Vec<&String> = map.keys().collect();
+        assert_eq!(keys, vec!["zeta", "alpha", "mid"]);
+        if let Some(JVal::JObject(inner)) = map.get("alpha") {
+            let keys: Vec<&String> = inner.keys().collect();
+            assert_eq!(keys, vec!["y", "b"]);
+        } else {
+            panic!("Expected nested object");
+        }
+    } else {
+        panic!("Expected JObject");
+    }
+}
+
+fn with_duplicate_keys(duplicate_keys: DuplicateKeys) -> ParseOptions {
+    ParseOptions {
+        duplicate_keys,
+        ..ParseOptions::default()
+    }
+}
+
+const DUPLICATES: &str = r#"{"a": 1, "b": [0], "a": {"x": 2}, "b": true, "a": 3}"#;
+
+#[test]
+fn test_duplicate_keys_last_wins() {
+    let result = parse_str(DUPLICATES);
+
+    if let JVal::JObject(map) = result {
+        assert_eq!(map.get("a"), Some(&JVal::from(3)));
+        assert_eq!(map.get("b"), Some(&JVal::JBool(true)));
+        let keys: Vec<&String> = map.keys().collect();
+        assert_eq!(keys, vec!["a", "b"]);
+    } else {
+        panic!("Expected JObject");
+    }
+}
+
+#[test]
+fn test_duplicate_keys_first_wins() {
+    let result = parse_str_with(DUPLICATES, with_duplicate_keys(DuplicateKeys::FirstWins));
+
+    if let JVal::JObject(map) = result {
+        assert_eq!(map.get("a"), Some(&JVal::from(1)));
+        assert_eq!(map.get("b"), Some(&JVal::JArray(vec![JVal::from(0)])));
+    } else {
+        panic!("Expected JObject");
+    }
+}
+
+#[test]
+fn test_duplicate_keys_collect_all() {
+    let result = parse_str_with(DUPLICATES, with_duplicate_keys(DuplicateKeys::CollectAll));
+
+    if let JVal::JObject(map) = result {
+        // Members of nested objects are collected as well
+        let nested: JMap = [("x".to_string(), JVal::JArray(vec![JVal::from(2)]))]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            map.get("a"),
+            Some(&JVal::JArray(vec![
+                JVal::from(1),
+                JVal::JObject(nested),
+                JVal::from(3),
+            ]))
+        );
+        assert_eq!(
+            map.get("b"),
+            Some(&JVal::JArray(vec![
+                JVal::JArray(vec![JVal::from(0)]),
+                JVal::JBool(true),
+            ]))
+        );
+    } else {
+        panic!("Expected JObject");
+    }
+}
+
+#[test]
+fn test_duplicate_keys_collect_all_wraps_single_values() {
+    let options = with_duplicate_keys(DuplicateKeys::CollectAll);
+    let repeated = parse_str_with(r#"{"a": 1, "a": 2}"#, options.clone());
+    let array = parse_str_with(r#"{"a": [1, 2]}"#, options.clone());
+
+    assert_eq!(
+        repeated["a"],
+        JVal::JArray(vec![JVal::from(1), JVal::from(2)])
+    );
+    assert_eq!(
+        array["a"],
+        JVal::JArray(vec![JVal::JArray(vec![JVal::from(1), JVal::from(2)])])
+    );
+    assert_ne!(repeated, array);
+    assert_eq!(
+        parse_str_with(r#"{"a": {"b": 1}}"#, options),
+        parse_str(r#"{"a": [{"b": [1]}]}"#)
+    );
+}
+
+#[test]
+fn test_duplicate_keys_error() {
+    let file_reader = JsonReader::from_reader_with_options(
+        "{\"a\": 1,\n \"a\": 2}".as_bytes(),
+        with_duplicate_keys(DuplicateKeys::Error),
+    );
+    let err = parse(&mut TokenReader::new(file_reader)).unwrap_err();
+
+    match err {
+        ParseError::DuplicateKey {
+            key,
+            first,
+            position,
+        } => {
+            assert_eq!(key, "a");
+            assert_eq!((first.line, first.column), (1, 2));
+            assert_eq!((position.line, position.column), (2, 2));
+        }
+        other => panic!("Expected DuplicateKey, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_duplicate_keys_in_separate_objects() {
+    let input = r#"[{"a": 1}, {"a": 2}]"#;
+
+    assert!(matches!(
+        parse_str_with(input, with_duplicate_keys(DuplicateKeys::Error)),
+        JVal::JArray(_)
+    ));
+}
+
+#[test]
+fn test_comma_error_matrix() {
+    let cases = [
+        ("[,1]", "leading", 2),
+        ("[,]", "leading", 2),
+        ("[1,,2]", "doubled", 4),
+        ("[1,]", "trailing", 3),
+        ("[1 2]", "missing", 4),
+        ("[[1] [2]]", "missing", 6),
+        ("[[1],,[2]]", "doubled", 6),
+        ("[[1,],2]", "trailing", 4),
+        // A comma inside a nested container must not stand in for the outer one
+        ("[[1,] 2]", "trailing", 4),
+        ("[[1] ,]", "trailing", 6),
+        (r#"{,"a":1}"#, "leading", 2),
+        (r#"{"a":1,,"b":2}"#, "doubled", 8),
+        (r#"{"a":1,}"#, "trailing", 7),
+        (r#"{"a":{"b":1,},"c":2}"#, "trailing", 12),
+    ];
+
+    for (input, kind, column) in cases {
+        let err = parse_err(input);
+        let found = match err {
+            ParseError::LeadingComma { .. } => "leading",
+            ParseError::DoubledComma { .. } => "doubled",
+            ParseError::TrailingComma { .. } => "trailing",
+            ParseError::MissingComma { .. } => "missing",
+            _ => panic!("{}: got {:?}", input, err),
+        };
+        assert_eq!(found, kind, "{}", input);
+        assert_eq!(err.position().column, column, "{}", input);
+    }
+}
+
+#[test]
+fn test_commas_scoped_to_container() {
+    assert!(matches!(
+        parse_err(r#"{"a": [1,] "b": 2}"#),
+        ParseError::TrailingComma { .. }
+    ));
+    assert!(matches!(
+        parse_err(r#"{"a": [1], "b": {"c": 2} "d": 3}"#),
+        ParseError::MissingComma { .. }
+    ));
+    assert_eq!(
+        parse_str(r#"[[1, 2], [], {}, {"a": [3]}, 4]"#),
+        parse_str(r#"[[1,2],[],{},{"a":[3]},4]"#)
+    );
+}
+
+#[test]
+fn test_in_memory_entry_points() {
+    let expected = JVal::JArray(vec![JVal::JString("é".to_string()), JVal::JNull]);
+
+    assert_eq!(from_str(r#"["é", null]"#).unwrap(), expected);
+    assert_eq!(from_slice("[\"é\", null]".as_bytes()).unwrap(), expected);
+    assert_eq!(
+        from_reader(std::io::Cursor::new(b"[\"\xc3\xa9\", null]")).unwrap(),
+        expected
+    );
+}
+
+const JSON5: &str = "{
+    // Extensions a config file might use
+    'name': 'it\\'s',
+    \"ratio\": NaN,
+    \"range\": [-Infinity, Infinity,],
+}";
+
+#[test]
+fn test_presets() {
+    let strict = ParseOptions::strict();
+    assert_eq!(strict, ParseOptions::default());
+    assert!(from_str_with_options(JSON5, strict).is_err());
+
+    let value = from_str_with_options(JSON5, ParseOptions::json5()).unwrap();
+    assert_eq!(value["name"], JVal::JString("it's".to_string()));
+    assert!(value["ratio"].as_f64().unwrap().is_nan());
+    assert_eq!(value["range"][1].as_f64(), Some(f64::INFINITY));
+    assert_eq!(crate::ser::to_string(&value["range"]), "[null,null]");
+    assert!(from_str_with_options("[True]", ParseOptions::json5()).is_err());
+    let commented = "[true/*c*/, null//c\n, false//x\n]";
+    assert_eq!(
+        from_str_with_options(commented, ParseOptions::json5()).unwrap(),
+        JVal::JArray(vec![JVal::JBool(true), JVal::JNull, JVal::JBool(false)])
+    );
+
+    let lenient = ParseOptions::lenient();
+    assert!(lenient.allow_comments && lenient.case_insensitive_keywords);
+    assert_eq!(
+        from_str_with_options("[True, /* x */ NULL,]", lenient).unwrap(),
+        JVal::JArray(vec![JVal::JBool(true), JVal::JNull])
+    );
+}
+
+#[test]
+fn test_each_extension_is_separate() {
+    let cases = [
+        ("[1 /* c */]", ParseOptions::default().allow_comments(true)),
+        ("[1,]", ParseOptions::default().allow_trailing_commas(true)),
+        ("['a']", ParseOptions::default().allow_single_quotes(true)),
+        ("[NaN]", ParseOptions::default().allow_nan_infinity(true)),
+    ];
+    for (i, (input, _)) in cases.iter().enumerate() {
+        for (j, (_, other)) in cases.iter().enumerate() {
+            let result = from_str_with_options(input, other.clone());
+            assert_eq!(result.is_ok(), i == j, "{} with {:?}", input, other);
+        }
+    }
+}
+
+#[test]
+fn test_builder_sets_limits_and_policy() {
+    let options = ParseOptions::json5()
+        .duplicate_keys(DuplicateKeys::Error)
+        .max_depth(1)
+        .max_string_length(8);
+    assert_eq!(options.limits.max_depth, Some(1));
+    assert_eq!(options.limits.max_string_length, Some(8));
+    assert!(options.allow_comments);
+
+    assert!(matches!(
+        from_str_with_options("{'a': 1, 'a': 2}", options.clone()),
+        Err(ParseError::DuplicateKey { .. })
+    ));
+    assert!(matches!(
+        from_str_with_options("[[]]", options),
+        Err(ParseError::DepthLimitExceeded { .. })
+    ));
+}
+
+#[test]
+fn test_errors_after_the_root() {
+    let too_large = from_str_with_options("[1]     ", ParseOptions::default().max_input_size(5));
+    assert!(
+        matches!(
+            too_large,
+            Err(ParseError::InputTooLarge { limit: 5, position }) if position.offset == 5
+        ),
+        "{:?}",
+        too_large
+    );
+    assert!(matches!(
+        from_slice(b"[1] \xff"),
+        Err(ParseError::InvalidUtf8 { .. })
+    ));
+    assert!(matches!(
+        from_slice(b"[1] \xc3"),
+        Err(ParseError::IncompleteUtf8 { .. })
+    ));
+    assert!(matches!(
+        from_str_with_options(
+            r#"[1] "abcd""#,
+            ParseOptions::default().max_string_length(2)
+        ),
+        Err(ParseError::StringTooLong { .. })
+    ));
+
+    for input in ["[1] 2", "[1] x", "[1] \"open"] {
+        assert!(
+            matches!(from_str(input), Err(ParseError::TrailingData { .. })),
+            "{}",
+            input
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::PointerError;
+use crate::map::JMap;
+use crate::parser::JVal;
+
+#[cfg(test)]
+mod tests;
+
+/// One unescaped segment of a pointer, along with where it ends in the pointer text
+/// so errors can quote the prefix that failed.
+struct ReferenceToken {
+    key: String,
+    end: usize,
+}
+
+impl JVal {
+    /// Returns the value `pointer` refers to, or `None` if it is malformed or names
+    /// nothing. The empty pointer refers to the whole document.
+    pub fn pointer(&self, pointer: &str) -> Option<&JVal> {
+        self.try_pointer(pointer).ok()
+    }
+
+    /// Like `pointer`, but says why the lookup failed.
+    pub fn try_pointer(&self, pointer: &str) -> Result<&JVal, PointerError> {
+        let mut current = self;
+        for token in parse_pointer(pointer)? {
+            let prefix = &pointer[..token.end];
+            current = match current {
+                JVal::JObject(map) => map.get(&token.key).ok_or_else(|| not_found(prefix))?,
+                JVal::JArray(items) => &items[existing_index(&token.key, items.len(), prefix)?],
+                _ => return Err(not_a_container(prefix)),
+            };
+        }
+        Ok(current)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JVal> {
+        parse_pointer(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| match value {
+                JVal::JObject(map) => map.get_mut(&token.key),
+                JVal::JArray(items) => items.get_mut(parse_index(&token.key)?),
+                _ => None,
+            })
+    }
+
+    /// Adds `value` at `pointer`, following the `add` operation of RFC 6902: an object
+    /// member is inserted or replaced, an array element is inserted before the one at
+    /// the index and `-` appends. Returns the member that was replaced, if any.
+    ///
+    /// With `create_intermediates`, missing members along the way are created as
+    /// arrays when the next token is `0` or `-` and as objects otherwise.
+    pub fn pointer_insert(
+        &mut self,
+        pointer: &str,
+        value: JVal,
+        create_intermediates: bool,
+    ) -> Result<Option<JVal>, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+
+        let mut current = self;
+        for (i, token) in parents.iter().enumerate() {
+            let prefix = &pointer[..token.end];
+            current = match current {
+                JVal::JObject(map) => {
+                    if !map.contains_key(&token.key) {
+                        if !create_intermediates {
+                            return Err(not_found(prefix));
+                        }
+                        map.insert(token.key.clone(), new_container(&tokens[i + 1].key));
+                    }
+                    map.get_mut(&token.key).expect("member was just checked")
+                }
+                JVal::JArray(items) => {
+                    if create_intermediates && token.key == "-" {
+                        items.push(new_container(&tokens[i + 1].key));
+                        items.last_mut().expect("element was just pushed")
+                    } else {
+                        let index = existing_index(&token.key, items.len(), prefix)?;
+                        &mut items[index]
+                    }
+                }
+                _ => return Err(not_a_container(prefix)),
+            };
+        }
+
+        let prefix = &pointer[..last.end];
+        match current {
+            JVal::JObject(map) => Ok(map.insert(last.key.clone(), value)),
+            JVal::JArray(items) if last.key == "-" => {
+                items.push(value);
+                Ok(None)
+            }
+            JVal::JArray(items) => {
+                let index = parse_index(&last.key).ok_or_else(|| invalid_index(prefix))?;
+                if index > items.len() {
+                    return Err(out_of_bounds(index, items.len(), prefix));
+                }
+                items.insert(index, value);
+                Ok(None)
+            }
+            _ => Err(not_a_container(prefix)),
+        }
+    }
+
+    /// Removes and returns the value at `pointer`. Later array elements shift down.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<JVal, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Err(PointerError::RemoveRoot);
+        };
+
+        let mut current = self;
+        for token in parents {
+            let prefix = &pointer[..token.end];
+            current = match current {
+                JVal::JObject(map) => map.get_mut(&token.key).ok_or_else(|| not_found(prefix))?,
+                JVal::JArray(items) => {
+                    let index = existing_index(&token.key, items.len(), prefix)?;
+                    &mut items[index]
+                }
+                _ => return Err(not_a_container(prefix)),
+            };
+        }
+
+        let prefix = &pointer[..last.end];
+        match current {
+            JVal::JObject(map) => map.remove(&last.key).ok_or_else(|| not_found(prefix)),
+            JVal::JArray(items) => {
+                let index = existing_index(&last.key, items.len(), prefix)?;
+                Ok(items.remove(index))
+            }
+            _ => Err(not_a_container(prefix)),
+        }
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<ReferenceToken>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PointerError::MissingSlash {
+            pointer: pointer.to_string(),
+        });
+    };
+
+    let mut end = 0;
+    rest.split('/')
+        .map(|raw| {
+            end += 1 + raw.len();
+            let key = unescape(raw).ok_or_else(|| PointerError::InvalidEscape {
+                pointer: pointer[..end].to_string(),
+            })?;
+            Ok(ReferenceToken { key, end })
+        })
+        .collect()
+}
+
+// Decoding in a single pass turns `~01` into `~1`, not `/`, as the RFC requires
+fn unescape(raw: &str) -> Option<String> {
+    let mut key = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '~' => match chars.next()? {
+                '0' => key.push('~'),
+                '1' => key.push('/'),
+                _ => return None,
+            },
+            ch => key.push(ch),
+        }
+    }
+    Some(key)
+}
+
+/// Parses an array index, which may not have a sign or leading zeros.
+fn parse_index(key: &str) -> Option<usize> {
+    match key.as_bytes() {
+        [b'0'] => Some(0),
+        [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => key.parse().ok(),
+        _ => None,
+    }
+}
+
+fn existing_index(key: &str, len: usize, prefix: &str) -> Result<usize, PointerError> {
+    if key == "-" {
+        // Refers to the element after the last one, which never exists
+        return Err(out_of_bounds(len, len, prefix));
+    }
+    match parse_index(key) {
+        Some(index) if index < len => Ok(index),
+        Some(index) => Err(out_of_bounds(index, len, prefix)),
+        None => Err(invalid_index(prefix)),
+    }
+}
+
+fn new_container(next_key: &str) -> JVal {
+    match next_key {
+        "0" | "-" => JVal::JArray(Vec::new()),
+        _ => JVal::JObject(JMap::new()),
+    }
+}
+
+fn not_found(prefix: &str) -> PointerError {
+    PointerError::NotFound {
+        pointer: prefix.to_string(),
+    }
+}
+
+fn not_a_container(prefix: &str) -> PointerError {
+    PointerError::NotAContainer {
+        pointer: prefix.to_string(),
+    }
+}
+
+fn invalid_index(prefix: &str) -> PointerError {
+    PointerError::InvalidIndex {
+        pointer: prefix.to_string(),
+    }
+}
+
+fn out_of_bounds(index: usize, len: usize, prefix: &str) -> PointerError {
+    PointerError::IndexOutOfBounds {
+        index,
+        len,
+        pointer: prefix.to_string(),
+    }
+}
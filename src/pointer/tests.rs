@@ -0,0 +1,240 @@
+use super::*;
+use crate::parser::from_str;
+
+fn sample() -> JVal {
+    from_str(
+        r#"{"foo": ["bar", "baz"], "": 0, "a/b": 1, "m~n": 2, " ": 3, "nested": {"list": [{"id": 7}]}}"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_rfc_6901_examples() {
+    let value = sample();
+    assert_eq!(value.pointer(""), Some(&value));
+    assert_eq!(
+        value.pointer("/foo"),
+        Some(&from_str(r#"["bar", "baz"]"#).unwrap())
+    );
+    assert_eq!(value.pointer("/foo/0"), Some(&JVal::from("bar")));
+    assert_eq!(value.pointer("/"), Some(&JVal::from(0)));
+    assert_eq!(value.pointer("/a~1b"), Some(&JVal::from(1)));
+    assert_eq!(value.pointer("/m~0n"), Some(&JVal::from(2)));
+    assert_eq!(value.pointer("/ "), Some(&JVal::from(3)));
+    assert_eq!(value.pointer("/nested/list/0/id"), Some(&JVal::from(7)));
+}
+
+#[test]
+fn test_escapes_decode_in_one_pass() {
+    let value = from_str(r#"{"~1": "tilde-one", "/": "slash"}"#).unwrap();
+    assert_eq!(value.pointer("/~01"), Some(&JVal::from("tilde-one")));
+    assert_eq!(value.pointer("/~1"), Some(&JVal::from("slash")));
+}
+
+#[test]
+fn test_pointer_misses() {
+    let value = sample();
+    for pointer in [
+        "foo",
+        "/missing",
+        "/foo/2",
+        "/foo/-",
+        "/foo/01",
+        "/foo/+1",
+        "/foo/0/deeper",
+        "/m~2n",
+        "/m~",
+    ] {
+        assert_eq!(value.pointer(pointer), None, "{}", pointer);
+    }
+}
+
+#[test]
+fn test_try_pointer_explains_misses() {
+    let value = sample();
+    assert_eq!(value.try_pointer("/foo/1"), Ok(&JVal::from("baz")));
+    assert_eq!(
+        value.try_pointer("/nested/nope/x"),
+        Err(PointerError::NotFound {
+            pointer: "/nested/nope".to_string()
+        })
+    );
+    assert_eq!(
+        value.try_pointer("/foo/01"),
+        Err(PointerError::InvalidIndex {
+            pointer: "/foo/01".to_string()
+        })
+    );
+    assert_eq!(
+        value.try_pointer("/a~1b/0"),
+        Err(PointerError::NotAContainer {
+            pointer: "/a~1b/0".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_pointer_mut() {
+    let mut value = sample();
+    *value.pointer_mut("/foo/1").unwrap() = JVal::from("qux");
+    *value.pointer_mut("/nested/list/0/id").unwrap() = JVal::from(8);
+    assert_eq!(value["foo"][1], JVal::from("qux"));
+    assert_eq!(value["nested"]["list"][0]["id"], JVal::from(8));
+    assert_eq!(value.pointer_mut("/nope"), None);
+}
+
+#[test]
+fn test_insert_into_objects_and_arrays() {
+    let mut value = sample();
+    assert_eq!(
+        value.pointer_insert("/new", JVal::from(true), false),
+        Ok(None)
+    );
+    assert_eq!(
+        value.pointer_insert("/a~1b", JVal::from(10), false),
+        Ok(Some(JVal::from(1)))
+    );
+    assert_eq!(
+        value.pointer_insert("/foo/1", JVal::from("mid"), false),
+        Ok(None)
+    );
+    assert_eq!(
+        value.pointer_insert("/foo/-", JVal::from("end"), false),
+        Ok(None)
+    );
+    assert_eq!(
+        value.pointer_insert("/foo/4", JVal::from("last"), false),
+        Ok(None)
+    );
+
+    assert_eq!(value["new"], JVal::from(true));
+    assert_eq!(value["a/b"], JVal::from(10));
+    assert_eq!(
+        value["foo"],
+        from_str(r#"["bar", "mid", "baz", "end", "last"]"#).unwrap()
+    );
+}
+
+#[test]
+fn test_insert_at_root_replaces_document() {
+    let mut value = sample();
+    let old = value.pointer_insert("", JVal::from(1), false).unwrap();
+    assert_eq!(old, Some(sample()));
+    assert_eq!(value, JVal::from(1));
+}
+
+#[test]
+fn test_insert_creates_intermediates_on_request() {
+    let mut value = from_str("{}").unwrap();
+    assert_eq!(
+        value.pointer_insert("/a/b/c", JVal::from(1), false),
+        Err(PointerError::NotFound {
+            pointer: "/a".to_string()
+        })
+    );
+    assert_eq!(value, from_str("{}").unwrap());
+
+    value.pointer_insert("/a/b/c", JVal::from(1), true).unwrap();
+    value
+        .pointer_insert("/list/-/name", JVal::from("x"), true)
+        .unwrap();
+    value
+        .pointer_insert("/list/-/name", JVal::from("y"), true)
+        .unwrap();
+    value
+        .pointer_insert("/first/0", JVal::from(2), true)
+        .unwrap();
+    assert_eq!(
+        value,
+        from_str(r#"{"a": {"b": {"c": 1}}, "list": [{"name": "x"}, {"name": "y"}], "first": [2]}"#)
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_insert_errors() {
+    let mut value = sample();
+    assert_eq!(
+        value.pointer_insert("/foo/3", JVal::JNull, false),
+        Err(PointerError::IndexOutOfBounds {
+            index: 3,
+            len: 2,
+            pointer: "/foo/3".to_string()
+        })
+    );
+    assert_eq!(
+        value.pointer_insert("/foo/x", JVal::JNull, true),
+        Err(PointerError::InvalidIndex {
+            pointer: "/foo/x".to_string()
+        })
+    );
+    assert_eq!(
+        value.pointer_insert("/foo/0/x", JVal::JNull, true),
+        Err(PointerError::NotAContainer {
+            pointer: "/foo/0/x".to_string()
+        })
+    );
+    assert_eq!(
+        value.pointer_insert("/m~x/y", JVal::JNull, true),
+        Err(PointerError::InvalidEscape {
+            pointer: "/m~x".to_string()
+        })
+    );
+    assert_eq!(
+        value.pointer_insert("x", JVal::JNull, true),
+        Err(PointerError::MissingSlash {
+            pointer: "x".to_string()
+        })
+    );
+    assert_eq!(value, sample());
+}
+
+#[test]
+fn test_remove() {
+    let mut value = sample();
+    assert_eq!(value.pointer_remove("/foo/0"), Ok(JVal::from("bar")));
+    assert_eq!(value.pointer_remove("/m~0n"), Ok(JVal::from(2)));
+    assert_eq!(value.pointer_remove("/nested/list/0/id"), Ok(JVal::from(7)));
+    assert_eq!(value["foo"], from_str(r#"["baz"]"#).unwrap());
+    assert!(value.get("m~n").is_none());
+    assert_eq!(value["nested"]["list"][0], from_str("{}").unwrap());
+}
+
+#[test]
+fn test_remove_errors() {
+    let mut value = sample();
+    assert_eq!(value.pointer_remove(""), Err(PointerError::RemoveRoot));
+    assert_eq!(
+        value.pointer_remove("/missing/x"),
+        Err(PointerError::NotFound {
+            pointer: "/missing".to_string()
+        })
+    );
+    assert_eq!(
+        value.pointer_remove("/foo/-"),
+        Err(PointerError::IndexOutOfBounds {
+            index: 2,
+            len: 2,
+            pointer: "/foo/-".to_string()
+        })
+    );
+    assert_eq!(value, sample());
+}
+
+#[test]
+fn test_error_messages() {
+    assert_eq!(
+        PointerError::NotFound {
+            pointer: "/a".to_string()
+        }
+        .to_string(),
+        "No value at '/a'"
+    );
+    assert_eq!(
+        PointerError::MissingSlash {
+            pointer: "a".to_string()
+        }
+        .to_string(),
+        "JSON pointer 'a' must be empty or start with '/'"
+    );
+}
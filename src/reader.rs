@@ -1,6 +1,13 @@
-use std::{fs::File, io::{ BufReader, Read}, path::PathBuf};
-use anyhow::Context;
 use std::collections::VecDeque;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use crate::error::ParseError;
+use crate::options::ParseOptions;
 
 #[cfg(test)]
 mod tests;
@@ -8,65 +15,235 @@ mod tests;
 const NUM_CHARS_IN_BUFFER: usize = 1024;
 const BUFFER_SIZE: usize = std::mem::size_of::<char>() * NUM_CHARS_IN_BUFFER;
 
-pub struct JsonReader {
-    reader: BufReader<File>,
+/// A location in the input. `line` and `column` are 1-based, with columns counted in
+/// characters; `offset` is the number of bytes before the location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (byte {})",
+            self.line, self.column, self.offset
+        )
+    }
+}
+
+// Why the input ended early; turned into a `ParseError` once the reader reaches that point
+#[derive(Debug)]
+enum ReadFailure {
+    Io(io::Error),
+    InvalidUtf8,
+    IncompleteUtf8,
+    // More input followed the first `limit` bytes
+    TooLarge(usize),
+}
+
+/// Decodes UTF-8 text from any byte source into characters, tracking the position of each.
+pub struct JsonReader<R: Read> {
+    reader: R,
+    options: ParseOptions,
     is_eof: bool,
-    buffer: VecDeque<char>,
+    // Decoded characters along with the number of input bytes each one was decoded from
+    buffer: VecDeque<(char, usize)>,
+    position: Position,
+    // Bytes read from the source that do not yet form a complete UTF-8 sequence
+    pending: Vec<u8>,
+    bytes_read: usize,
+    error: Option<ReadFailure>,
 }
 
-impl JsonReader {
-    pub fn new(path: PathBuf) -> Result<Self, anyhow::Error> {
-        let json_file = File::open(&path)
-            .with_context(|| format!("Could not read file `{}`", path.display()))?;
+impl JsonReader<File> {
+    pub fn new(path: PathBuf) -> Result<Self, ParseError> {
+        Self::with_options(path, ParseOptions::default())
+    }
 
-        Ok(JsonReader {
-            reader: BufReader::new(json_file),
+    pub fn with_options(path: PathBuf, options: ParseOptions) -> Result<Self, ParseError> {
+        let json_file = File::open(path).map_err(|source| ParseError::Io {
+            source,
+            position: Position::default(),
+        })?;
+
+        Ok(Self::from_reader_with_options(json_file, options))
+    }
+}
+
+impl<'a> JsonReader<&'a [u8]> {
+    /// Reads directly from the borrowed string without copying it up front.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Self::from_slice(input.as_bytes())
+    }
+
+    pub fn from_slice(input: &'a [u8]) -> Self {
+        Self::from_reader(input)
+    }
+}
+
+impl<R: Read> JsonReader<R> {
+    pub fn from_reader(reader: R) -> Self {
+        Self::from_reader_with_options(reader, ParseOptions::default())
+    }
+
+    pub fn from_reader_with_options(reader: R, options: ParseOptions) -> Self {
+        JsonReader {
+            reader,
+            options,
             is_eof: false,
             buffer: VecDeque::with_capacity(BUFFER_SIZE),
-        })
+            position: Position::default(),
+            pending: Vec::new(),
+            bytes_read: 0,
+            error: None,
+        }
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
     }
-    
+
+    /// Position of the next character to be read.
+    pub fn current_position(&self) -> Position {
+        self.position
+    }
+
     pub fn peek(&mut self) -> Option<char> {
         if self.buffer.is_empty() {
-            self.refill_buffer().ok()?;
+            self.fill();
         }
-        self.buffer.front().copied()
+        self.buffer.front().map(|&(ch, _)| ch)
     }
 
-    fn refill_buffer(&mut self) -> Result<(), anyhow::Error> {
-        if self.is_eof {
-            return Ok(());
+    /// Returns the read or decode error that ended the input, once every
+    /// character decoded before it has been consumed.
+    pub fn take_error(&mut self) -> Option<ParseError> {
+        if !self.buffer.is_empty() {
+            return None;
         }
 
-        if !self.buffer.is_empty() {
-            return Err(anyhow::anyhow!("Attempted to refill on non-empty buffer"));
+        let position = self.position;
+        self.error.take().map(|failure| match failure {
+            ReadFailure::Io(source) => ParseError::Io { source, position },
+            ReadFailure::InvalidUtf8 => ParseError::InvalidUtf8 { position },
+            ReadFailure::IncompleteUtf8 => ParseError::IncompleteUtf8 { position },
+            ReadFailure::TooLarge(limit) => ParseError::InputTooLarge { limit, position },
+        })
+    }
+
+    fn fill(&mut self) {
+        if let Err(e) = self.refill_buffer() {
+            self.is_eof = true;
+            self.error = Some(e);
         }
+    }
+
+    fn refill_buffer(&mut self) -> Result<(), ReadFailure> {
+        debug_assert!(
+            self.buffer.is_empty(),
+            "Attempted to refill on non-empty buffer"
+        );
 
         let mut temp_buf = [0; BUFFER_SIZE];
+        while self.buffer.is_empty() && !self.is_eof {
             match self.reader.read(&mut temp_buf) {
                 Ok(0) => {
                     self.is_eof = true;
-                    return Ok(());
+                    if !self.pending.is_empty() {
+                        if !self.options.replace_invalid_utf8 {
+                            return Err(ReadFailure::IncompleteUtf8);
+                        }
+                        self.buffer
+                            .push_back((char::REPLACEMENT_CHARACTER, self.pending.len()));
+                        self.pending.clear();
+                    }
                 }
                 Ok(n) => {
-                    self.buffer.extend(temp_buf[..n].iter().copied().map(char::from));
+                    self.bytes_read += n;
+                    // Decode up to the limit, so the error is reported where it was crossed
+                    let limit = self.options.limits.max_input_size;
+                    let excess = limit.map_or(0, |limit| self.bytes_read.saturating_sub(limit));
+                    self.pending.extend_from_slice(&temp_buf[..n - excess]);
+                    self.decode_pending()?;
+                    if let (Some(limit), 1..) = (limit, excess) {
+                        return Err(ReadFailure::TooLarge(limit));
+                    }
                 }
                 Err(e) => {
-                    self.is_eof = true;
-                    return Err(e).with_context(|| "Failed to read from file")?;
+                    return Err(ReadFailure::Io(e));
                 }
             }
+        }
 
         Ok(())
     }
+
+    fn decode_pending(&mut self) -> Result<(), ReadFailure> {
+        let mut start = 0;
+        let result = loop {
+            match std::str::from_utf8(&self.pending[start..]) {
+                Ok(valid) => {
+                    self.buffer
+                        .extend(valid.chars().map(|ch| (ch, ch.len_utf8())));
+                    start = self.pending.len();
+                    break Ok(());
+                }
+                Err(e) => {
+                    let valid_end = start + e.valid_up_to();
+                    let valid = std::str::from_utf8(&self.pending[start..valid_end])
+                        .expect("prefix was validated by the previous call");
+                    self.buffer
+                        .extend(valid.chars().map(|ch| (ch, ch.len_utf8())));
+                    start = valid_end;
+
+                    match e.error_len() {
+                        // The sequence continues in the next read
+                        None => break Ok(()),
+                        Some(_) if !self.options.replace_invalid_utf8 => {
+                            break Err(ReadFailure::InvalidUtf8);
+                        }
+                        Some(len) => {
+                            self.buffer.push_back((char::REPLACEMENT_CHARACTER, len));
+                            start += len;
+                        }
+                    }
+                }
+            }
+        };
+
+        self.pending.drain(..start);
+        result
+    }
 }
 
-impl Iterator for JsonReader {
+impl<R: Read> Iterator for JsonReader<R> {
     type Item = char;
     fn next(&mut self) -> Option<Self::Item> {
         if self.buffer.is_empty() {
-            self.refill_buffer().ok();
+            self.fill();
+        }
+        let (ch, len) = self.buffer.pop_front()?;
+        self.position.offset += len;
+        if ch == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
         }
-        self.buffer.pop_front()
+        Some(ch)
     }
 }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::options::{Limits, ParseOptions};
 use std::fs::write;
 use tempfile::NamedTempFile;
 
@@ -42,15 +43,15 @@ fn test_multiple_lines() {
 fn test_peek() {
     let (path, _guard) = create_temp_file("abc");
     let mut reader = JsonReader::new(path).unwrap();
-    
+
     assert_eq!(reader.peek(), Some('a'));
     assert_eq!(reader.peek(), Some('a'));
     assert_eq!(reader.next(), Some('a'));
-    
+
     assert_eq!(reader.peek(), Some('b'));
     assert_eq!(reader.next(), Some('b'));
     assert_eq!(reader.next(), Some('c'));
-    
+
     assert_eq!(reader.peek(), None);
     assert_eq!(reader.next(), None);
 }
@@ -60,3 +61,208 @@ fn test_invalid_path() {
     let result = JsonReader::new(PathBuf::from("/nonexistent/path"));
     assert!(result.is_err());
 }
+
+fn create_temp_file_bytes(content: &[u8]) -> (PathBuf, NamedTempFile) {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_path_buf();
+    write(&path, content).unwrap();
+    (path, temp_file)
+}
+
+fn lossy_options() -> ParseOptions {
+    ParseOptions {
+        replace_invalid_utf8: true,
+        ..ParseOptions::default()
+    }
+}
+
+#[test]
+fn test_multi_byte_characters() {
+    let (path, _guard) = create_temp_file("é中🦀");
+    let reader = JsonReader::new(path).unwrap();
+    assert_eq!(reader.collect::<String>(), "é中🦀");
+}
+
+#[test]
+fn test_multi_byte_across_buffer_boundary() {
+    for padding in BUFFER_SIZE - 3..=BUFFER_SIZE {
+        let content = format!("{}🦀é", "a".repeat(padding));
+        let (path, _guard) = create_temp_file(&content);
+        let mut reader = JsonReader::new(path).unwrap();
+        assert_eq!(reader.by_ref().collect::<String>(), content);
+        assert!(reader.take_error().is_none());
+    }
+}
+
+#[test]
+fn test_invalid_utf8_reports_offset() {
+    let (path, _guard) = create_temp_file_bytes(b"ab\xffcd");
+    let mut reader = JsonReader::new(path).unwrap();
+    assert_eq!(reader.next(), Some('a'));
+    assert!(reader.take_error().is_none());
+    assert_eq!(reader.next(), Some('b'));
+    assert_eq!(reader.next(), None);
+    let err = reader.take_error().unwrap();
+    assert_eq!(err.to_string(), "Invalid UTF-8 sequence at byte offset 2");
+}
+
+#[test]
+fn test_overlong_encoding_rejected() {
+    // 0xC0 0xAF is an overlong encoding of '/'
+    let (path, _guard) = create_temp_file_bytes(b"a\xc0\xaf");
+    let mut reader = JsonReader::new(path).unwrap();
+    assert_eq!(reader.next(), Some('a'));
+    assert_eq!(reader.next(), None);
+    let err = reader.take_error().unwrap();
+    assert_eq!(err.to_string(), "Invalid UTF-8 sequence at byte offset 1");
+}
+
+#[test]
+fn test_truncated_sequence_at_eof() {
+    let (path, _guard) = create_temp_file_bytes(b"ab\xe4\xb8");
+    let mut reader = JsonReader::new(path).unwrap();
+    assert_eq!(reader.by_ref().collect::<String>(), "ab");
+    let err = reader.take_error().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Incomplete UTF-8 sequence at byte offset 2"
+    );
+}
+
+#[test]
+fn test_lossy_mode_replaces_invalid_sequences() {
+    let (path, _guard) = create_temp_file_bytes(b"a\xffb\xc0\xafc\xe4\xb8");
+    let mut reader = JsonReader::with_options(path, lossy_options()).unwrap();
+    assert_eq!(
+        reader.by_ref().collect::<String>(),
+        "a\u{FFFD}b\u{FFFD}\u{FFFD}c\u{FFFD}"
+    );
+    assert!(reader.take_error().is_none());
+}
+
+#[test]
+fn test_position_tracking() {
+    let (path, _guard) = create_temp_file("aé\n中b");
+    let mut reader = JsonReader::new(path).unwrap();
+    assert_eq!(
+        reader.current_position(),
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0
+        }
+    );
+    reader.next();
+    reader.next();
+    assert_eq!(
+        reader.current_position(),
+        Position {
+            line: 1,
+            column: 3,
+            offset: 3
+        }
+    );
+    reader.next();
+    assert_eq!(
+        reader.current_position(),
+        Position {
+            line: 2,
+            column: 1,
+            offset: 4
+        }
+    );
+    reader.peek();
+    assert_eq!(
+        reader.current_position(),
+        Position {
+            line: 2,
+            column: 1,
+            offset: 4
+        }
+    );
+    reader.next();
+    assert_eq!(
+        reader.current_position(),
+        Position {
+            line: 2,
+            column: 2,
+            offset: 7
+        }
+    );
+}
+
+#[test]
+fn test_position_counts_replaced_bytes() {
+    let (path, _guard) = create_temp_file_bytes(b"\xc0\xafa");
+    let mut reader = JsonReader::with_options(path, lossy_options()).unwrap();
+    assert_eq!(reader.next(), Some(char::REPLACEMENT_CHARACTER));
+    assert_eq!(reader.next(), Some(char::REPLACEMENT_CHARACTER));
+    assert_eq!(
+        reader.current_position(),
+        Position {
+            line: 1,
+            column: 3,
+            offset: 2
+        }
+    );
+}
+
+#[test]
+fn test_from_str_and_slice() {
+    let reader = JsonReader::from_str("aé中");
+    assert_eq!(reader.collect::<String>(), "aé中");
+
+    let mut reader = JsonReader::from_slice(b"a\xff");
+    assert_eq!(reader.by_ref().collect::<String>(), "a");
+    assert!(matches!(
+        reader.take_error(),
+        Some(ParseError::InvalidUtf8 { .. })
+    ));
+}
+
+#[test]
+fn test_from_reader_across_short_reads() {
+    // Yields one byte per read so every multi-byte character is split
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&byte, rest)) if !buf.is_empty() => {
+                    buf[0] = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let reader = JsonReader::from_reader(OneByte("é🦀x".as_bytes()));
+    assert_eq!(reader.collect::<String>(), "é🦀x");
+}
+
+#[test]
+fn test_input_size_limit() {
+    let options = ParseOptions {
+        limits: Limits {
+            max_input_size: Some(4),
+            ..Limits::default()
+        },
+        ..ParseOptions::default()
+    };
+
+    let mut reader = JsonReader::from_reader_with_options(&b"abcd"[..], options.clone());
+    assert_eq!(reader.by_ref().collect::<String>(), "abcd");
+    assert!(reader.take_error().is_none());
+
+    // Everything up to the limit is still read, so the error points where it was crossed
+    let mut reader = JsonReader::from_reader_with_options(&b"abcde"[..], options);
+    assert_eq!(reader.by_ref().collect::<String>(), "abcd");
+    match reader.take_error() {
+        Some(ParseError::InputTooLarge { limit, position }) => {
+            assert_eq!((limit, position.offset), (4, 4));
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+}
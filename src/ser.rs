@@ -0,0 +1,210 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::map;
+use crate::number::Number;
+use crate::options::WriteOptions;
+use crate::parser::JVal;
+
+#[cfg(test)]
+mod tests;
+
+/// Serializes `value` as compact JSON on a single line.
+pub fn to_string(value: &JVal) -> String {
+    to_string_with_options(value, WriteOptions::default())
+}
+
+/// Serializes `value` with each member on its own line, indented by `indent` spaces per level.
+pub fn to_string_pretty(value: &JVal, indent: usize) -> String {
+    to_string_with_options(
+        value,
+        WriteOptions {
+            indent: Some(indent),
+            ..WriteOptions::default()
+        },
+    )
+}
+
+pub fn to_string_with_options(value: &JVal, options: WriteOptions) -> String {
+    let mut output = Vec::new();
+    to_writer_with_options(&mut output, value, options).expect("writing to a Vec cannot fail");
+    String::from_utf8(output).expect("the serializer only writes UTF-8")
+}
+
+pub fn to_writer<W: Write>(writer: W, value: &JVal) -> io::Result<()> {
+    to_writer_with_options(writer, value, WriteOptions::default())
+}
+
+pub fn to_writer_with_options<W: Write>(
+    writer: W,
+    value: &JVal,
+    options: WriteOptions,
+) -> io::Result<()> {
+    Serializer::new(writer, options).write_value(value)
+}
+
+/// Writes the compact form, so `value.to_string()` yields valid JSON.
+impl fmt::Display for JVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_string(self))
+    }
+}
+
+// A container whose members are still being written
+enum Frame<'a> {
+    Array(std::slice::Iter<'a, JVal>),
+    Object(map::Iter<'a>),
+}
+
+pub(crate) struct Serializer<W: Write> {
+    writer: W,
+    options: WriteOptions,
+}
+
+impl<W: Write> Serializer<W> {
+    pub(crate) fn new(writer: W, options: WriteOptions) -> Self {
+        Serializer { writer, options }
+    }
+
+    pub(crate) fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)
+    }
+
+    /// Writes the colon between a key and its value.
+    pub(crate) fn write_colon(&mut self) -> io::Result<()> {
+        let colon: &[u8] = if self.options.indent.is_some() {
+            b": "
+        } else {
+            b":"
+        };
+        self.writer.write_all(colon)
+    }
+
+    pub(crate) fn into_inner(self) -> W {
+        self.writer
+    }
+
+    // Iterative, so writing adds no stack depth of its own however deeply the value nests
+    fn write_value(&mut self, root: &JVal) -> io::Result<()> {
+        let mut stack: Vec<(Frame, bool)> = Vec::new();
+        let mut next = Some(root);
+
+        loop {
+            match next.take() {
+                Some(JVal::JArray(items)) if !items.is_empty() => {
+                    self.writer.write_all(b"[")?;
+                    stack.push((Frame::Array(items.iter()), true));
+                }
+                Some(JVal::JObject(members)) if !members.is_empty() => {
+                    self.writer.write_all(b"{")?;
+                    stack.push((Frame::Object(members.iter()), true));
+                }
+                Some(JVal::JArray(_)) => self.writer.write_all(b"[]")?,
+                Some(JVal::JObject(_)) => self.writer.write_all(b"{}")?,
+                Some(JVal::JString(string)) => self.write_string(string)?,
+                Some(JVal::JInt(num)) => write!(self.writer, "{}", num)?,
+                Some(JVal::JUint(num)) => write!(self.writer, "{}", num)?,
+                Some(JVal::JFloat(num)) => self.write_number(num)?,
+                Some(JVal::JBool(true)) => self.writer.write_all(b"true")?,
+                Some(JVal::JBool(false)) => self.writer.write_all(b"false")?,
+                Some(JVal::JNull) => self.writer.write_all(b"null")?,
+                None => {}
+            }
+
+            let depth = stack.len();
+            let Some((frame, first)) = stack.last_mut() else {
+                return Ok(());
+            };
+            let is_first = std::mem::replace(first, false);
+            match frame {
+                Frame::Array(items) => match items.next() {
+                    Some(item) => {
+                        self.write_separator(is_first, depth)?;
+                        next = Some(item);
+                    }
+                    None => {
+                        stack.pop();
+                        self.write_newline(depth - 1)?;
+                        self.writer.write_all(b"]")?;
+                    }
+                },
+                Frame::Object(members) => match members.next() {
+                    Some((key, item)) => {
+                        self.write_separator(is_first, depth)?;
+                        self.write_string(key)?;
+                        self.write_colon()?;
+                        next = Some(item);
+                    }
+                    None => {
+                        stack.pop();
+                        self.write_newline(depth - 1)?;
+                        self.writer.write_all(b"}")?;
+                    }
+                },
+            }
+        }
+    }
+
+    pub(crate) fn write_separator(&mut self, is_first: bool, depth: usize) -> io::Result<()> {
+        if !is_first {
+            self.writer.write_all(b",")?;
+        }
+        self.write_newline(depth)
+    }
+
+    /// Starts a new line indented to `depth`, when pretty-printing.
+    pub(crate) fn write_newline(&mut self, depth: usize) -> io::Result<()> {
+        if let Some(indent) = self.options.indent {
+            write!(self.writer, "\n{:width$}", "", width = indent * depth)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the number with the digits it was read with. `NaN` and the infinities have
+    /// no JSON spelling, so they are written as `null`.
+    pub(crate) fn write_number(&mut self, num: &Number) -> io::Result<()> {
+        if num.is_finite() {
+            self.writer.write_all(num.as_str().as_bytes())
+        } else {
+            self.writer.write_all(b"null")
+        }
+    }
+
+    pub(crate) fn write_string(&mut self, string: &str) -> io::Result<()> {
+        self.writer.write_all(b"\"")?;
+
+        let mut unescaped_from = 0;
+        for (i, ch) in string.char_indices() {
+            let escape = match ch {
+                '"' => Some("\\\""),
+                '\\' => Some("\\\\"),
+                '\n' => Some("\\n"),
+                '\r' => Some("\\r"),
+                '\t' => Some("\\t"),
+                '\u{8}' => Some("\\b"),
+                '\u{c}' => Some("\\f"),
+                _ => None,
+            };
+            let needs_code_units = ch < ' ' || (self.options.ascii_only && !ch.is_ascii());
+            if escape.is_none() && !needs_code_units {
+                continue;
+            }
+
+            self.writer
+                .write_all(&string.as_bytes()[unescaped_from..i])?;
+            unescaped_from = i + ch.len_utf8();
+            match escape {
+                Some(escape) => self.writer.write_all(escape.as_bytes())?,
+                None => {
+                    for unit in ch.encode_utf16(&mut [0; 2]) {
+                        write!(self.writer, "\\u{:04x}", unit)?;
+                    }
+                }
+            }
+        }
+
+        self.writer
+            .write_all(&string.as_bytes()[unescaped_from..])?;
+        self.writer.write_all(b"\"")
+    }
+}
@@ -0,0 +1,154 @@
+use super::*;
+use crate::map::JMap;
+use crate::parser::from_str;
+
+fn string(s: &str) -> JVal {
+    JVal::JString(s.to_string())
+}
+
+#[test]
+fn test_compact_output() {
+    let value = from_str(r#" { "a" : [1, true, null], "b" : { } , "c": [ ] } "#).unwrap();
+    assert_eq!(to_string(&value), r#"{"a":[1,true,null],"b":{},"c":[]}"#);
+}
+
+#[test]
+fn test_pretty_output() {
+    let value = from_str(r#"{"a": [1, {"b": "x"}], "c": {}, "d": []}"#).unwrap();
+    let expected = concat!(
+        "{\n",
+        "  \"a\": [\n",
+        "    1,\n",
+        "    {\n",
+        "      \"b\": \"x\"\n",
+        "    }\n",
+        "  ],\n",
+        "  \"c\": {},\n",
+        "  \"d\": []\n",
+        "}",
+    );
+    assert_eq!(to_string_pretty(&value, 2), expected);
+    assert_eq!(
+        to_string_pretty(&from_str("[1]").unwrap(), 4),
+        "[\n    1\n]"
+    );
+}
+
+#[test]
+fn test_keys_written_in_map_order() {
+    let map: JMap = [("z", 1.0), ("a", 2.0), ("m", 3.0)]
+        .into_iter()
+        .map(|(key, num)| (key.to_string(), JVal::from(num)))
+        .collect();
+    assert_eq!(to_string(&JVal::JObject(map)), r#"{"z":1,"a":2,"m":3}"#);
+}
+
+#[test]
+fn test_scalar_roots() {
+    assert_eq!(to_string(&string("text")), r#""text""#);
+    assert_eq!(to_string(&JVal::JBool(false)), "false");
+    assert_eq!(to_string(&JVal::JNull), "null");
+    assert_eq!(to_string_pretty(&JVal::from(5), 2), "5");
+}
+
+#[test]
+fn test_string_escapes() {
+    assert_eq!(
+        to_string(&string("quote\" backslash\\ slash/")),
+        r#""quote\" backslash\\ slash/""#
+    );
+    assert_eq!(to_string(&string("\n\r\t\u{8}\u{c}")), r#""\n\r\t\b\f""#);
+    assert_eq!(
+        to_string(&string("\u{0}\u{1f} \u{7f}")),
+        "\"\\u0000\\u001f \u{7f}\""
+    );
+}
+
+#[test]
+fn test_non_ascii_strings() {
+    let value = string("caf\u{e9} \u{1f600}");
+    assert_eq!(to_string(&value), "\"caf\u{e9} \u{1f600}\"");
+
+    let ascii_only = WriteOptions {
+        ascii_only: true,
+        ..WriteOptions::default()
+    };
+    assert_eq!(
+        to_string_with_options(&value, ascii_only),
+        r#""caf\u00e9 \ud83d\ude00""#
+    );
+}
+
+#[test]
+fn test_integer_valued_numbers() {
+    for (num, expected) in [
+        (0.0, "0"),
+        (-0.0, "-0"),
+        (30.0, "30"),
+        (-12.0, "-12"),
+        (9007199254740993.0, "9007199254740992"),
+        (1e16, "1e16"),
+        (1.5e300, "1.5e300"),
+    ] {
+        assert_eq!(to_string(&JVal::from(num)), expected);
+    }
+}
+
+#[test]
+fn test_fractional_numbers() {
+    for (num, expected) in [
+        (0.1, "0.1"),
+        (-2.5, "-2.5"),
+        (1e-7, "1e-7"),
+        (0.30000000000000004, "0.30000000000000004"),
+    ] {
+        assert_eq!(to_string(&JVal::from(num)), expected);
+    }
+}
+
+#[test]
+fn test_non_finite_numbers_become_null() {
+    assert_eq!(to_string(&JVal::from(f64::NAN)), "null");
+    assert_eq!(to_string(&JVal::from(f64::NEG_INFINITY)), "null");
+}
+
+#[test]
+fn test_round_trip() {
+    let input = r#"{"s": "a\u0001\"\\\ud83d\ude00 é", "n": [0.1, 1e-300, 123456789.125, -4], "o": {"k": [null, false, {}]}}"#;
+    let value = from_str(input).unwrap();
+
+    assert_eq!(from_str(&to_string(&value)).unwrap(), value);
+    assert_eq!(from_str(&to_string_pretty(&value, 3)).unwrap(), value);
+    let ascii_only = WriteOptions {
+        ascii_only: true,
+        ..WriteOptions::default()
+    };
+    assert_eq!(
+        from_str(&to_string_with_options(&value, ascii_only)).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn test_deeply_nested_document() {
+    let depth = 5_000;
+    let input = "[".repeat(depth) + &"]".repeat(depth);
+    let value = from_str(&input).unwrap();
+    assert_eq!(to_string(&value), input);
+}
+
+#[test]
+fn test_to_writer_and_display() {
+    let value = from_str(r#"{"a": [1.5]}"#).unwrap();
+    let mut output = Vec::new();
+    to_writer(&mut output, &value).unwrap();
+    assert_eq!(output, br#"{"a":[1.5]}"#);
+    assert_eq!(value.to_string(), r#"{"a":[1.5]}"#);
+}
+
+#[test]
+fn test_numbers_keep_their_digits() {
+    let input =
+        r#"[9007199254740993,-0,1.50,12345678901234567890.123456789012345678901,6.02E+23,1e400]"#;
+    assert_eq!(to_string(&from_str(input).unwrap()), input);
+}
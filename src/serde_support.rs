@@ -0,0 +1,85 @@
+//! Conversions between JSON and any type implementing serde's `Serialize` and
+//! `Deserialize`, without going through `JVal`. Enabled by the `serde` feature.
+
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::SerdeError;
+use crate::options::WriteOptions;
+use crate::reader::JsonReader;
+
+mod de;
+mod ser;
+mod value;
+
+#[cfg(test)]
+mod tests;
+
+pub use de::Deserializer;
+pub use ser::Serializer;
+
+/// Deserializes a complete document held in memory.
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T, SerdeError> {
+    from_json_reader(JsonReader::from_str(input))
+}
+
+pub fn from_slice<T: DeserializeOwned>(input: &[u8]) -> Result<T, SerdeError> {
+    from_json_reader(JsonReader::from_slice(input))
+}
+
+/// Deserializes a complete document from any byte source, such as a file or stdin.
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, SerdeError> {
+    from_json_reader(JsonReader::from_reader(reader))
+}
+
+/// Deserializes a complete document, with the reader's parse options.
+pub fn from_json_reader<R: Read, T: DeserializeOwned>(
+    reader: JsonReader<R>,
+) -> Result<T, SerdeError> {
+    let mut deserializer = Deserializer::new(reader);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Serializes `value` as compact JSON on a single line.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerdeError> {
+    to_string_with_options(value, WriteOptions::default())
+}
+
+/// Serializes `value` with each member on its own line, indented by `indent` spaces per level.
+pub fn to_string_pretty<T: Serialize + ?Sized>(
+    value: &T,
+    indent: usize,
+) -> Result<String, SerdeError> {
+    to_string_with_options(
+        value,
+        WriteOptions {
+            indent: Some(indent),
+            ..WriteOptions::default()
+        },
+    )
+}
+
+pub fn to_string_with_options<T: Serialize + ?Sized>(
+    value: &T,
+    options: WriteOptions,
+) -> Result<String, SerdeError> {
+    let mut output = Vec::new();
+    to_writer_with_options(&mut output, value, options)?;
+    Ok(String::from_utf8(output).expect("the serializer only writes UTF-8"))
+}
+
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<(), SerdeError> {
+    to_writer_with_options(writer, value, WriteOptions::default())
+}
+
+pub fn to_writer_with_options<W: Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+    options: WriteOptions,
+) -> Result<(), SerdeError> {
+    value.serialize(&mut Serializer::new(writer, options))
+}
@@ -0,0 +1,500 @@
+use std::fmt;
+use std::io::Read;
+
+use serde::de::value::MapDeserializer;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{ParseError, SerdeError};
+use crate::events::Separator;
+use crate::lexer::{SpannedToken, Token, TokenReader};
+use crate::number::Number;
+use crate::parser;
+use crate::reader::{JsonReader, Position};
+use crate::serde_support::value::RAW_NUMBER;
+
+impl de::Error for SerdeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        SerdeError::Custom {
+            message: message.to_string(),
+            position: None,
+        }
+    }
+}
+
+/// How deeply containers may nest when `Limits::max_depth` is unset. Deserializing
+/// recurses once per level, so this keeps deep input from overflowing the stack.
+const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Deserializes values straight from the token stream, one token of lookahead at a time.
+/// Nesting is limited to 128 levels unless `Limits::max_depth` says otherwise.
+pub struct Deserializer<R: Read> {
+    tokens: TokenReader<R>,
+    peeked: Option<SpannedToken>,
+    // How many objects and arrays are open
+    depth: usize,
+}
+
+impl<R: Read> Deserializer<R> {
+    pub fn new(reader: JsonReader<R>) -> Self {
+        Self::from_tokens(TokenReader::new(reader))
+    }
+
+    pub fn from_tokens(tokens: TokenReader<R>) -> Self {
+        Deserializer {
+            tokens,
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    /// Fails unless nothing but whitespace follows the value deserialized so far.
+    pub fn end(&mut self) -> Result<(), SerdeError> {
+        if let Some(token) = self.peeked.take() {
+            return Err(ParseError::TrailingData {
+                position: token.span.start,
+            }
+            .into());
+        }
+        Ok(parser::expect_end(&mut self.tokens)?)
+    }
+
+    fn peek(&mut self, expected: &'static str) -> Result<&SpannedToken, SerdeError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read(expected)?);
+        }
+        Ok(self.peeked.as_ref().expect("a token was just peeked"))
+    }
+
+    fn next_token(&mut self, expected: &'static str) -> Result<SpannedToken, SerdeError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => Ok(self.read(expected)?),
+        }
+    }
+
+    fn read(&mut self, expected: &'static str) -> Result<SpannedToken, ParseError> {
+        self.tokens.next().unwrap_or_else(|| {
+            Err(ParseError::UnexpectedEof {
+                expected,
+                position: self.tokens.current_position(),
+            })
+        })
+    }
+
+    /// Hands an integer too wide for 64 bits to `visit_i128` or `visit_u128`. Only done
+    /// when a 128-bit integer is asked for, since other visitors, `f64`'s among them,
+    /// do not take those calls; anything else is deserialized as usual.
+    fn deserialize_wide_integer<'de, V: Visitor<'de>>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let SpannedToken { token, span } = self.peek("JSON value")?;
+        let Token::TFloat(num) = token else {
+            return de::Deserializer::deserialize_any(self, visitor);
+        };
+        let (text, start) = (num.as_str(), span.start);
+        let unsigned = text.parse::<u128>().ok();
+        // `-0` stays a float, as it does for 64-bit integers
+        let signed = text.parse::<i128>().ok().filter(|_| text != "-0");
+        let value: Result<V::Value, SerdeError> = match (unsigned, signed) {
+            (Some(value), _) => visitor.visit_u128(value),
+            (None, Some(value)) => visitor.visit_i128(value),
+            (None, None) => return de::Deserializer::deserialize_any(self, visitor),
+        };
+        self.peeked = None;
+        value.map_err(|e| e.at(start))
+    }
+
+    /// Enters the container whose opening bracket is at `position`.
+    fn open(&mut self, position: Position) -> Result<(), ParseError> {
+        self.depth += 1;
+        let mut limits = self.tokens.options().limits;
+        limits.max_depth = Some(limits.max_depth.unwrap_or(DEFAULT_MAX_DEPTH));
+        limits.check_depth(self.depth, position)
+    }
+
+    /// Consumes the bracket that ends the container just visited.
+    fn close(&mut self, bracket: Token, expected: &'static str) -> Result<(), SerdeError> {
+        let SpannedToken { token, span } = self.next_token(expected)?;
+        if token != bracket {
+            return Err(ParseError::UnexpectedToken {
+                expected,
+                found: token,
+                position: span.start,
+            }
+            .into());
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+/// Hands a float to the visitor as the nearest `f64`. Integers never get here, so an
+/// integer field rejects `3.0` as it would `3.5`.
+fn visit_float<'de, V: Visitor<'de>>(num: Number, visitor: V) -> Result<V::Value, SerdeError> {
+    match num.as_f64() {
+        Some(value) => visitor.visit_f64(value),
+        None => Err(de::Error::custom(format_args!(
+            "number {} is out of range",
+            num
+        ))),
+    }
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        let SpannedToken { token, span } = self.next_token("JSON value")?;
+        if matches!(token, Token::OpenObject | Token::OpenArray) {
+            self.open(span.start)?;
+        }
+        let value = match token {
+            Token::OpenObject => visitor.visit_map(Members::new(self)).and_then(|value| {
+                self.close(Token::CloseObject, "'}'")?;
+                Ok(value)
+            }),
+            Token::OpenArray => visitor.visit_seq(Members::new(self)).and_then(|value| {
+                self.close(Token::CloseArray, "']'")?;
+                Ok(value)
+            }),
+            Token::TString(string) => visitor.visit_string(string),
+            // Non-negative integers go to `visit_u64`, which every integer visitor accepts
+            Token::TInt(num) if num >= 0 => visitor.visit_u64(num as u64),
+            Token::TInt(num) => visitor.visit_i64(num),
+            Token::TUint(num) => visitor.visit_u64(num),
+            Token::TFloat(num) => visit_float(num, visitor),
+            Token::TBool(value) => visitor.visit_bool(value),
+            Token::TNull => visitor.visit_unit(),
+            token => Err(ParseError::UnexpectedToken {
+                expected: "JSON value",
+                found: token,
+                position: span.start,
+            }
+            .into()),
+        };
+        value.map_err(|e| e.at(span.start))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        if self.peek("JSON value")?.token == Token::TNull {
+            self.peeked = None;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_wide_integer(visitor)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_wide_integer(visitor)
+    }
+
+    /// Answers `JVal`'s request for `RAW_NUMBER` with the text of a float, if one is next.
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        if name != RAW_NUMBER {
+            return visitor.visit_newtype_struct(self);
+        }
+        if !matches!(self.peek("JSON value")?.token, Token::TFloat(_)) {
+            return de::Deserializer::deserialize_any(self, visitor);
+        }
+        let SpannedToken { token, span } = self.next_token("JSON value")?;
+        let Token::TFloat(num) = token else {
+            unreachable!("a float was just peeked")
+        };
+        let raw = std::iter::once((RAW_NUMBER, num.as_str().to_string()));
+        visitor
+            .visit_map(MapDeserializer::<_, SerdeError>::new(raw))
+            .map_err(|e| e.at(span.start))
+    }
+
+    /// Accepts a unit variant as a string, e.g. `"Red"`, and any other variant as an
+    /// object with a single member, e.g. `{"Rgb": [0, 0, 255]}`.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let SpannedToken { token, span } = self.next_token("JSON value")?;
+        if token == Token::OpenObject {
+            self.open(span.start)?;
+        }
+        let value = match token {
+            Token::TString(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Token::OpenObject => visitor.visit_enum(Variant { de: self }).and_then(|value| {
+                self.close(Token::CloseObject, "'}' after the variant's value")?;
+                Ok(value)
+            }),
+            token => Err(de::Error::invalid_type(unexpected(&token), &visitor)),
+        };
+        value.map_err(|e| e.at(span.start))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn unexpected(token: &Token) -> Unexpected<'_> {
+    match token {
+        Token::TString(string) => Unexpected::Str(string),
+        Token::TInt(num) => Unexpected::Signed(*num),
+        Token::TUint(num) => Unexpected::Unsigned(*num),
+        Token::TFloat(num) => Unexpected::Other(num.as_str()),
+        Token::TBool(value) => Unexpected::Bool(*value),
+        Token::TNull => Unexpected::Unit,
+        Token::OpenArray => Unexpected::Seq,
+        _ => Unexpected::Map,
+    }
+}
+
+/// The members of an object or array, after its opening bracket.
+struct Members<'a, R: Read> {
+    de: &'a mut Deserializer<R>,
+    separator: Separator,
+    count: usize,
+}
+
+impl<'a, R: Read> Members<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        Members {
+            de,
+            separator: Separator::Open,
+            count: 0,
+        }
+    }
+
+    /// Steps over any comma, stopping at the closing bracket or the next member.
+    /// When a member follows, leaves its first token peeked and returns what
+    /// preceded it.
+    fn next_member(
+        &mut self,
+        close: Token,
+        expected: &'static str,
+    ) -> Result<Option<Separator>, SerdeError> {
+        loop {
+            let SpannedToken { token, span } = self.de.peek(expected)?;
+            let start = span.start;
+            if *token == close {
+                self.separator
+                    .close(self.de.tokens.options().allow_trailing_commas)?;
+                return Ok(None);
+            } else if *token == Token::Comma {
+                self.de.peeked = None;
+                self.separator = self.separator.comma(start)?;
+            } else {
+                self.separator.expect_member(start)?;
+                self.count += 1;
+                let limits = self.de.tokens.options().limits;
+                limits.check_members(close == Token::CloseArray, self.count, start)?;
+                return Ok(Some(std::mem::replace(
+                    &mut self.separator,
+                    Separator::Member,
+                )));
+            }
+        }
+    }
+}
+
+impl<'de, R: Read> SeqAccess<'de> for Members<'_, R> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        if self
+            .next_member(Token::CloseArray, "']' to close the open array")?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de, R: Read> MapAccess<'de> for Members<'_, R> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let Some(separator) =
+            self.next_member(Token::CloseObject, "'}' to close the open object")?
+        else {
+            return Ok(None);
+        };
+        let SpannedToken { token, span } = self.de.next_token("string key")?;
+        let Token::TString(key) = token else {
+            return Err(ParseError::UnexpectedToken {
+                expected: match separator {
+                    Separator::Member => "',' or '}'",
+                    Separator::Open => "string key or '}'",
+                    Separator::Comma(_) => "string key",
+                },
+                found: token,
+                position: span.start,
+            }
+            .into());
+        };
+        expect_colon(self.de)?;
+        seed.deserialize(MapKey(key))
+            .map(Some)
+            .map_err(|e| e.at(span.start))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+fn expect_colon<R: Read>(de: &mut Deserializer<R>) -> Result<(), SerdeError> {
+    let colon = de.next_token("':' after object key")?;
+    if colon.token != Token::Colon {
+        return Err(ParseError::UnexpectedToken {
+            expected: "':' after object key",
+            found: colon.token,
+            position: colon.span.start,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// A variant written as an object with a single member, after its opening brace.
+struct Variant<'a, R: Read> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'de, R: Read> EnumAccess<'de> for Variant<'_, R> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), SerdeError> {
+        let SpannedToken { token, span } = self.de.next_token("variant name")?;
+        let Token::TString(variant) = token else {
+            return Err(ParseError::UnexpectedToken {
+                expected: "variant name",
+                found: token,
+                position: span.start,
+            }
+            .into());
+        };
+        expect_colon(self.de)?;
+        let variant = seed
+            .deserialize(variant.into_deserializer())
+            .map_err(|e: SerdeError| e.at(span.start))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R: Read> VariantAccess<'de> for Variant<'_, R> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// An object key, which also deserializes as a number for maps with integer keys.
+struct MapKey(String);
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    deserialize_parsed_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf option unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
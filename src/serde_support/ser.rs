@@ -0,0 +1,554 @@
+use std::fmt;
+use std::io::Write;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::error::SerdeError;
+use crate::number::Number;
+use crate::options::WriteOptions;
+use crate::ser::Serializer as Writer;
+
+use super::value::{RAW_NUMBER, RAW_NUMBER_WANTED};
+
+impl ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        SerdeError::Custom {
+            message: message.to_string(),
+            position: None,
+        }
+    }
+}
+
+/// Writes values as JSON, compact or pretty-printed, the same way `ser::to_writer`
+/// writes a `JVal`. Enum variants other than unit variants are written as an object
+/// with a single member named after the variant.
+pub struct Serializer<W: Write> {
+    writer: Writer<W>,
+    depth: usize,
+}
+
+impl<W: Write> Serializer<W> {
+    pub fn new(writer: W, options: WriteOptions) -> Self {
+        Serializer {
+            writer: Writer::new(writer, options),
+            depth: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    fn open(&mut self, bracket: &[u8]) -> Result<(), SerdeError> {
+        self.writer.write_raw(bracket)?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn close(&mut self, bracket: &[u8], is_empty: bool) -> Result<(), SerdeError> {
+        self.depth -= 1;
+        if !is_empty {
+            self.writer.write_newline(self.depth)?;
+        }
+        Ok(self.writer.write_raw(bracket)?)
+    }
+
+    fn member(&mut self, is_first: bool) -> Result<(), SerdeError> {
+        Ok(self.writer.write_separator(is_first, self.depth)?)
+    }
+
+    fn key(&mut self, key: &str) -> Result<(), SerdeError> {
+        self.writer.write_string(key)?;
+        Ok(self.writer.write_colon()?)
+    }
+
+    /// Opens the object that wraps a variant's value, up to the value itself.
+    fn start_variant(&mut self, variant: &str) -> Result<(), SerdeError> {
+        self.open(b"{")?;
+        self.member(true)?;
+        self.key(variant)
+    }
+
+    fn compound(&mut self, bracket: &'static [u8], in_variant: bool) -> Compound<'_, W> {
+        Compound {
+            ser: self,
+            close: bracket,
+            is_empty: true,
+            in_variant,
+        }
+    }
+
+    fn write_display(&mut self, value: impl fmt::Display) -> Result<(), SerdeError> {
+        Ok(self.writer.write_raw(value.to_string().as_bytes())?)
+    }
+}
+
+/// An array or object whose members are being written.
+pub struct Compound<'a, W: Write> {
+    ser: &'a mut Serializer<W>,
+    close: &'static [u8],
+    is_empty: bool,
+    // Whether the container is the value of an enum variant's wrapping object
+    in_variant: bool,
+}
+
+impl<W: Write> Compound<'_, W> {
+    fn member(&mut self) -> Result<(), SerdeError> {
+        let is_first = std::mem::replace(&mut self.is_empty, false);
+        self.ser.member(is_first)
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.member()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerdeError> {
+        self.member()?;
+        self.ser.key(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(self) -> Result<(), SerdeError> {
+        self.ser.close(self.close, self.is_empty)?;
+        if self.in_variant {
+            self.ser.close(b"}", false)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($type:ty),)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<(), SerdeError> {
+                self.write_display(value)
+            }
+        )*
+    };
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = SerdeError;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    serialize_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), SerdeError> {
+        if !value.is_finite() {
+            self.serialize_unit()
+        } else if value.fract() == 0.0 && value.abs() < 1e16 {
+            self.write_display(value)
+        } else {
+            // Debug gives the shortest text that reads back as the same `f32`
+            self.write_display(format_args!("{:?}", value))
+        }
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), SerdeError> {
+        match Number::from_f64(value) {
+            Some(num) => Ok(self.writer.write_number(&num)?),
+            None => self.serialize_unit(),
+        }
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), SerdeError> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), SerdeError> {
+        if RAW_NUMBER_WANTED.with(|wanted| wanted.replace(false)) {
+            let num = Number::from_valid_text(value.to_string());
+            return Ok(self.writer.write_number(&num)?);
+        }
+        Ok(self.writer.write_string(value)?)
+    }
+
+    /// Writes the bytes as an array of numbers.
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), SerdeError> {
+        let mut seq = self.serialize_seq(Some(value.len()))?;
+        for byte in value {
+            seq.element(byte)?;
+        }
+        seq.finish()
+    }
+
+    fn serialize_none(self) -> Result<(), SerdeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerdeError> {
+        Ok(self.writer.write_raw(b"null")?)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerdeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        if name != RAW_NUMBER {
+            return value.serialize(self);
+        }
+        RAW_NUMBER_WANTED.with(|wanted| wanted.set(true));
+        let result = value.serialize(&mut *self);
+        RAW_NUMBER_WANTED.with(|wanted| wanted.set(false));
+        result
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.start_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.close(b"}", false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>, SerdeError> {
+        self.open(b"[")?;
+        Ok(self.compound(b"]", false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, SerdeError> {
+        self.start_variant(variant)?;
+        self.open(b"[")?;
+        Ok(self.compound(b"]", true))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>, SerdeError> {
+        self.open(b"{")?;
+        Ok(self.compound(b"}", false))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, SerdeError> {
+        self.start_variant(variant)?;
+        self.open(b"{")?;
+        Ok(self.compound(b"}", true))
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.member()?;
+        key.serialize(MapKey {
+            ser: &mut *self.ser,
+        })?;
+        Ok(self.ser.writer.write_colon()?)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        self.finish()
+    }
+}
+
+/// Writes a map key, which must be a string, or a number or character to write as one.
+struct MapKey<'a, W: Write> {
+    ser: &'a mut Serializer<W>,
+}
+
+fn key_must_be_a_string() -> SerdeError {
+    ser::Error::custom("Object keys must be strings, numbers or characters")
+}
+
+macro_rules! serialize_key_display {
+    ($($method:ident($type:ty),)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<(), SerdeError> {
+                self.serialize_str(&value.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! reject_key {
+    ($($method:ident($($arg:ty),*),)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<(), SerdeError> {
+                Err(key_must_be_a_string())
+            }
+        )*
+    };
+}
+
+impl<W: Write> ser::Serializer for MapKey<'_, W> {
+    type Ok = ();
+    type Error = SerdeError;
+    type SerializeSeq = Impossible<(), SerdeError>;
+    type SerializeTuple = Impossible<(), SerdeError>;
+    type SerializeTupleStruct = Impossible<(), SerdeError>;
+    type SerializeTupleVariant = Impossible<(), SerdeError>;
+    type SerializeMap = Impossible<(), SerdeError>;
+    type SerializeStruct = Impossible<(), SerdeError>;
+    type SerializeStructVariant = Impossible<(), SerdeError>;
+
+    serialize_key_display! {
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_char(char),
+    }
+
+    reject_key! {
+        serialize_bool(bool),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), SerdeError> {
+        Ok(self.ser.writer.write_string(value)?)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<(), SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Err(key_must_be_a_string())
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::error::ParseError;
+use crate::map::JMap;
+use crate::options::{Limits, ParseOptions};
+use crate::parser::{self, JVal};
+use crate::reader::JsonReader;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Order {
+    id: u64,
+    customer: Customer,
+    items: Vec<Item>,
+    status: Status,
+    note: Option<String>,
+    discount: Option<f64>,
+    tags: BTreeMap<String, i32>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Customer {
+    name: String,
+    vip: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    sku: String,
+    quantity: u8,
+    price: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Status {
+    Pending,
+    Shipped {
+        carrier: String,
+        tracking: Option<String>,
+    },
+    Split(u32, u32),
+    Refunded(Money),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Money(i64);
+
+fn order(status: Status) -> Order {
+    Order {
+        id: 1042,
+        customer: Customer {
+            name: "Ada \"Countess\" Lovelace".to_string(),
+            vip: true,
+        },
+        items: vec![
+            Item {
+                sku: "A-1".to_string(),
+                quantity: 3,
+                price: 9.99,
+            },
+            Item {
+                sku: "ß-2".to_string(),
+                quantity: 1,
+                price: 120.0,
+            },
+        ],
+        status,
+        note: None,
+        discount: Some(-0.5),
+        tags: [("x".to_string(), -1), ("y".to_string(), 2)]
+            .into_iter()
+            .collect(),
+    }
+}
+
+#[test]
+fn test_round_trip_nested_structs_enums_and_options() {
+    let statuses = [
+        Status::Pending,
+        Status::Shipped {
+            carrier: "post".to_string(),
+            tracking: Some("T1".to_string()),
+        },
+        Status::Shipped {
+            carrier: "courier".to_string(),
+            tracking: None,
+        },
+        Status::Split(2, 3),
+        Status::Refunded(Money(-1500)),
+    ];
+
+    for status in statuses {
+        let original = order(status);
+        let compact = to_string(&original).unwrap();
+        assert_eq!(
+            from_str::<Order>(&compact).unwrap(),
+            original,
+            "{}",
+            compact
+        );
+        let pretty = to_string_pretty(&original, 2).unwrap();
+        assert_eq!(from_str::<Order>(&pretty).unwrap(), original, "{}", pretty);
+        assert_eq!(
+            from_reader::<_, Order>(compact.as_bytes()).unwrap(),
+            original
+        );
+    }
+}
+
+#[test]
+fn test_serialized_form() {
+    let json = to_string(&order(Status::Split(2, 3))).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"id":1042,"customer":{"name":"Ada \"Countess\" Lovelace","vip":true},"#,
+            r#""items":[{"sku":"A-1","quantity":3,"price":9.99},{"sku":"ß-2","quantity":1,"price":120}],"#,
+            r#""status":{"Split":[2,3]},"note":null,"discount":-0.5,"tags":{"x":-1,"y":2}}"#,
+        )
+    );
+    assert_eq!(to_string(&Status::Pending).unwrap(), r#""Pending""#);
+    assert_eq!(
+        to_string(&Status::Refunded(Money(5))).unwrap(),
+        r#"{"Refunded":5}"#
+    );
+    assert_eq!(
+        to_string_pretty(&Status::Split(1, 2), 2).unwrap(),
+        "{\n  \"Split\": [\n    1,\n    2\n  ]\n}"
+    );
+    assert_eq!(to_string(&Vec::<u8>::new()).unwrap(), "[]");
+    assert_eq!(
+        to_string(&BTreeMap::from([(3, 'c')])).unwrap(),
+        r#"{"3":"c"}"#
+    );
+    assert_eq!(to_string(&1.5f32).unwrap(), "1.5");
+    assert_eq!(to_string(&f64::NAN).unwrap(), "null");
+}
+
+#[test]
+fn test_matches_jval_serializer() {
+    let input = r#"{"a": [1, -2.5, 1e100, {"b": null, "c": [true, "é\n"]}], "d": {}, "e": []}"#;
+    let value = parser::from_str(input).unwrap();
+    assert_eq!(to_string(&value).unwrap(), crate::ser::to_string(&value));
+    assert_eq!(
+        to_string_pretty(&value, 4).unwrap(),
+        crate::ser::to_string_pretty(&value, 4)
+    );
+}
+
+#[test]
+fn test_jval_round_trip() {
+    let input = r#"{"z": [1, 2.5, "three", null, false], "a": {"nested": {}}, "m": []}"#;
+    let value: JVal = from_str(input).unwrap();
+    assert_eq!(value, parser::from_str(input).unwrap());
+    assert_eq!(
+        from_str::<JVal>(&to_string(&value).unwrap()).unwrap(),
+        value
+    );
+
+    let keys: Vec<_> = match &value {
+        JVal::JObject(members) => members.keys().cloned().collect(),
+        _ => panic!("expected an object"),
+    };
+    assert_eq!(keys, ["z", "a", "m"]);
+    assert_eq!(
+        format!("{:?}", from_str::<JVal>("[1, 1.0, -1]").unwrap()),
+        "JArray([JInt(1), JFloat(1.0), JInt(-1)])"
+    );
+    // Floats keep every digit, inside containers and options too
+    let precise = r#"[123456789012345678901234,0.10000000000000000000000000001,-0,{"n":1.50}]"#;
+    let value: JVal = from_str(precise).unwrap();
+    assert_eq!(crate::ser::to_string(&value), precise);
+    assert_eq!(to_string(&value).unwrap(), precise);
+    let exact = "[1.50,18446744073709551616,-0,-1e400,1E+2]";
+    assert_eq!(to_string(&from_str::<JVal>(exact).unwrap()).unwrap(), exact);
+    let wrapped: Option<Vec<JVal>> = from_str(precise).unwrap();
+    assert_eq!(JVal::JArray(wrapped.unwrap()), value);
+    assert!(from_str::<JMap>(r#"{"k": [1]}"#).is_ok());
+    assert!(from_str::<JMap>("[1]").is_err());
+
+    let as_struct: Customer = from_str(
+        &to_string(&JVal::from_iter([
+            ("name", JVal::from("Grace")),
+            ("vip", JVal::from(false)),
+        ]))
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        as_struct,
+        Customer {
+            name: "Grace".to_string(),
+            vip: false
+        }
+    );
+}
+
+#[test]
+fn test_integer_keys_and_unknown_fields() {
+    let map: BTreeMap<u32, bool> = from_str(r#"{"1": true, "20": false}"#).unwrap();
+    assert_eq!(map, BTreeMap::from([(1, true), (20, false)]));
+
+    let customer: Customer =
+        from_str(r#"{"extra": [1, {"deep": null}], "name": "x", "vip": false}"#).unwrap();
+    assert_eq!(customer.name, "x");
+}
+
+#[test]
+fn test_type_errors_carry_positions() {
+    let err = from_str::<Customer>("{\n  \"name\": 5,\n  \"vip\": true\n}").unwrap_err();
+    match &err {
+        SerdeError::Custom {
+            message,
+            position: Some(position),
+        } => {
+            assert!(message.contains("invalid type"), "{}", message);
+            assert_eq!((position.line, position.column), (2, 11));
+        }
+        _ => panic!("unexpected error {:?}", err),
+    }
+    assert!(err.to_string().ends_with("at line 2, column 11 (byte 12)"));
+
+    let err = from_str::<Customer>(r#"{"name": "x"}"#).unwrap_err();
+    assert!(err.to_string().contains("missing field `vip`"), "{}", err);
+
+    let err = from_str::<Item>(r#"{"sku": "a", "quantity": 300, "price": 1}"#).unwrap_err();
+    assert!(err.to_string().contains("300"), "{}", err);
+
+    let err = from_str::<Item>(r#"{"sku": "a", "quantity": 3.0, "price": 1}"#).unwrap_err();
+    assert!(err.to_string().contains("invalid type"), "{}", err);
+
+    let err = from_str::<Status>(r#""Unknown""#).unwrap_err();
+    assert!(err.to_string().contains("unknown variant"), "{}", err);
+}
+
+#[test]
+fn test_syntax_errors() {
+    assert!(matches!(
+        from_str::<Vec<u8>>("[1, 2,]"),
+        Err(SerdeError::Parse(ParseError::TrailingComma { .. }))
+    ));
+    assert!(matches!(
+        from_str::<Vec<u8>>("[1 2]"),
+        Err(SerdeError::Parse(ParseError::MissingComma { .. }))
+    ));
+    assert!(matches!(
+        from_str::<Vec<u8>>("[1] 2"),
+        Err(SerdeError::Parse(ParseError::TrailingData { .. }))
+    ));
+    assert!(matches!(
+        from_str::<Customer>(r#"{"name": "x", "vip": true"#),
+        Err(SerdeError::Parse(ParseError::UnexpectedEof { .. }))
+    ));
+    assert!(matches!(
+        from_str::<Customer>(r#"{"name" "x"}"#),
+        Err(SerdeError::Parse(ParseError::UnexpectedToken { .. }))
+    ));
+    assert!(matches!(
+        from_str::<(u8, u8)>("[1, 2, 3]"),
+        Err(SerdeError::Parse(ParseError::UnexpectedToken { .. }))
+    ));
+}
+
+#[test]
+fn test_limits() {
+    let limited = |input: &'static str, limits: Limits| {
+        let options = ParseOptions {
+            limits,
+            ..ParseOptions::default()
+        };
+        from_json_reader::<_, JVal>(JsonReader::from_reader_with_options(
+            input.as_bytes(),
+            options,
+        ))
+    };
+    let limits = Limits {
+        max_depth: Some(2),
+        max_object_members: Some(1),
+        max_array_elements: Some(2),
+        ..Limits::default()
+    };
+
+    assert!(limited(r#"[{"a": 1}, [2, 3]]"#, limits).is_ok());
+    assert!(matches!(
+        limited("[[[]]]", limits),
+        Err(SerdeError::Parse(ParseError::DepthLimitExceeded { .. }))
+    ));
+    assert!(matches!(
+        limited(r#"{"a": 1, "b": 2}"#, limits),
+        Err(SerdeError::Parse(ParseError::TooManyMembers { .. }))
+    ));
+    assert!(matches!(
+        limited("[1, 2, 3]", limits),
+        Err(SerdeError::Parse(ParseError::TooManyElements { .. }))
+    ));
+
+    // Each sibling starts from the same depth
+    let siblings = r#"{"a": [[1]]}"#;
+    assert!(limited(
+        siblings,
+        Limits {
+            max_depth: Some(3),
+            ..limits
+        }
+    )
+    .is_ok());
+    assert!(limited(siblings, limits).is_err());
+}
+
+#[test]
+fn test_default_depth_limit() {
+    let deep = "[".repeat(200_000) + &"]".repeat(200_000);
+    assert!(matches!(
+        from_str::<serde::de::IgnoredAny>(&deep),
+        Err(SerdeError::Parse(ParseError::DepthLimitExceeded {
+            limit: 128,
+            ..
+        }))
+    ));
+    assert!(matches!(
+        from_str::<JVal>(&deep),
+        Err(SerdeError::Parse(ParseError::DepthLimitExceeded { .. }))
+    ));
+
+    let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+    assert!(from_str::<JVal>(&nested(128)).is_ok());
+    let (input, options) = (nested(200), ParseOptions::default().max_depth(200));
+    let reader = JsonReader::from_reader_with_options(input.as_bytes(), options);
+    assert!(from_json_reader::<_, JVal>(reader).is_ok());
+}
+
+#[test]
+fn test_extensions() {
+    #[derive(Debug, Deserialize)]
+    struct Range {
+        low: f64,
+        high: f64,
+        labels: Vec<String>,
+    }
+
+    let input = "{'low': -Infinity, /* open */ 'high': NaN, 'labels': ['a',],}";
+    let reader = JsonReader::from_reader_with_options(input.as_bytes(), ParseOptions::json5());
+    let range: Range = from_json_reader(reader).unwrap();
+    assert_eq!(range.low, f64::NEG_INFINITY);
+    assert!(range.high.is_nan());
+    assert_eq!(range.labels, ["a"]);
+
+    assert!(matches!(
+        from_str::<Vec<i32>>("[1,]"),
+        Err(SerdeError::Parse(ParseError::TrailingComma { .. }))
+    ));
+}
+
+#[test]
+fn test_wide_integers_round_trip() {
+    for value in [1u128 << 100, u128::MAX, 7] {
+        let json = to_string(&value).unwrap();
+        assert_eq!(from_str::<u128>(&json).unwrap(), value, "{}", json);
+    }
+    for value in [i128::MIN, -(1i128 << 100), i128::MAX, -7] {
+        let json = to_string(&value).unwrap();
+        assert_eq!(from_str::<i128>(&json).unwrap(), value, "{}", json);
+    }
+    let keys = BTreeMap::from([(u128::MAX, true)]);
+    let json = to_string(&keys).unwrap();
+    assert_eq!(from_str::<BTreeMap<u128, bool>>(&json).unwrap(), keys);
+
+    // Other numeric types still read wide integers as floats, and fractions stay floats
+    assert_eq!(
+        from_str::<f64>(&u128::MAX.to_string()).unwrap(),
+        2f64.powi(128)
+    );
+    assert!(from_str::<u128>("1.5").is_err());
+    assert!(from_str::<u128>(&format!("{}0", u128::MAX)).is_err());
+}
@@ -0,0 +1,175 @@
+use std::cell::Cell;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::map::JMap;
+use crate::number::{Number, NON_FINITE};
+use crate::parser::JVal;
+
+/// The newtype name `JVal` deserializes through, and the key of the one-member map
+/// this crate's `Deserializer` answers it with for a float, whose value is the
+/// number's text. Serde has no arbitrary-precision type, so this keeps the digits
+/// that the trip through `visit_f64` would lose. A float is serialized as a newtype
+/// of this name too, which this crate's `Serializer` writes as the text.
+pub(super) const RAW_NUMBER: &str = "$jsonp::private::RawNumber";
+
+thread_local! {
+    /// Set while this crate's `Serializer` writes a `RAW_NUMBER` newtype, asking the
+    /// number inside for its text rather than the nearest `f64`.
+    pub(super) static RAW_NUMBER_WANTED: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Serialize for JVal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JVal::JString(string) => serializer.serialize_str(string),
+            JVal::JBool(value) => serializer.serialize_bool(*value),
+            JVal::JInt(num) => serializer.serialize_i64(*num),
+            JVal::JUint(num) => serializer.serialize_u64(*num),
+            JVal::JFloat(num) => serializer.serialize_newtype_struct(RAW_NUMBER, &RawNumber(num)),
+            JVal::JObject(members) => members.serialize(serializer),
+            JVal::JArray(items) => items.serialize(serializer),
+            JVal::JNull => serializer.serialize_unit(),
+        }
+    }
+}
+
+struct RawNumber<'a>(&'a Number);
+
+// Other serializers have no type for arbitrary precision, so for them a float goes out
+// as the nearest `f64`, and one too large for an `f64` as null
+impl Serialize for RawNumber<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if RAW_NUMBER_WANTED.with(Cell::get) {
+            return serializer.serialize_str(self.0.as_str());
+        }
+        match self.0.as_f64() {
+            Some(value) => serializer.serialize_f64(value),
+            None => serializer.serialize_unit(),
+        }
+    }
+}
+
+impl Serialize for JMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for JVal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<JVal, D::Error> {
+        deserializer.deserialize_newtype_struct(RAW_NUMBER, JValVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for JMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<JMap, D::Error> {
+        deserializer.deserialize_map(JMapVisitor)
+    }
+}
+
+struct JValVisitor;
+
+impl<'de> Visitor<'de> for JValVisitor {
+    type Value = JVal;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<JVal, E> {
+        Ok(JVal::JBool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<JVal, E> {
+        Ok(JVal::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<JVal, E> {
+        Ok(JVal::from(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<JVal, E> {
+        Ok(JVal::from(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<JVal, E> {
+        Ok(JVal::JString(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<JVal, E> {
+        Ok(JVal::JString(value))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<JVal, E> {
+        Ok(JVal::JNull)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<JVal, E> {
+        Ok(JVal::JNull)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JVal, D::Error> {
+        JVal::deserialize(deserializer)
+    }
+
+    // Other deserializers treat the `RAW_NUMBER` newtype as any other
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<JVal, D::Error> {
+        deserializer.deserialize_any(JValVisitor)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JVal, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JVal::JArray(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JVal, A::Error> {
+        let Some(first) = map.next_key::<String>()? else {
+            return Ok(JVal::JObject(JMap::new()));
+        };
+        if first == RAW_NUMBER {
+            let text: String = map.next_value()?;
+            return raw_number(text).map(JVal::from);
+        }
+        let mut members = JMap::with_capacity(map.size_hint().unwrap_or(0) + 1);
+        members.insert(first, map.next_value()?);
+        while let Some((key, value)) = map.next_entry()? {
+            members.insert(key, value);
+        }
+        Ok(JVal::JObject(members))
+    }
+}
+
+fn raw_number<E: de::Error>(text: String) -> Result<Number, E> {
+    if NON_FINITE.contains(&text.as_str()) {
+        return Ok(Number::from_valid_text(text));
+    }
+    text.parse().map_err(de::Error::custom)
+}
+
+struct JMapVisitor;
+
+impl<'de> Visitor<'de> for JMapVisitor {
+    type Value = JMap;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JMap, A::Error> {
+        let mut members = JMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry()? {
+            members.insert(key, value);
+        }
+        Ok(members)
+    }
+}
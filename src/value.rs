@@ -0,0 +1,366 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Index;
+
+use crate::map::JMap;
+use crate::number::{Kind, Number};
+use crate::parser::JVal;
+
+#[cfg(test)]
+mod tests;
+
+// What indexing yields for a missing key, an out-of-range index or a value of the wrong type
+static NULL: JVal = JVal::JNull;
+
+impl JVal {
+    /// Looks up `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&JVal> {
+        self.as_object()?.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JVal> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Looks up the element at `index` if this is an array.
+    pub fn get_index(&self, index: usize) -> Option<&JVal> {
+        self.as_array()?.get(index)
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut JVal> {
+        self.as_array_mut()?.get_mut(index)
+    }
+
+    /// Replaces the value with `null` and returns what was there.
+    pub fn take(&mut self) -> JVal {
+        std::mem::take(self)
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, JVal::JString(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, JVal::JInt(_) | JVal::JUint(_) | JVal::JFloat(_))
+    }
+
+    /// Whether this is a number parsed as a float rather than an integer, e.g. `42.0`.
+    pub fn is_f64(&self) -> bool {
+        matches!(self, JVal::JFloat(_))
+    }
+
+    /// Whether this is a number that `as_i64` can return.
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Whether this is a number that `as_u64` can return.
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, JVal::JBool(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, JVal::JArray(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, JVal::JObject(_))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JVal::JNull)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JVal::JString(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self {
+            JVal::JString(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns any number as a `Number`, borrowing it if it is a float.
+    pub fn as_number(&self) -> Option<Cow<'_, Number>> {
+        match self {
+            JVal::JInt(num) => Some(Cow::Owned(Number::from(*num))),
+            JVal::JUint(num) => Some(Cow::Owned(Number::from(*num))),
+            JVal::JFloat(num) => Some(Cow::Borrowed(num)),
+            _ => None,
+        }
+    }
+
+    /// Returns the nearest `f64` if this is a number small enough to be finite.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JVal::JInt(num) => Some(*num as f64),
+            JVal::JUint(num) => Some(*num as f64),
+            JVal::JFloat(num) => num.as_f64(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a whole number that fits in an `i64`, including
+    /// floats such as `42.0`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JVal::JInt(num) => Some(*num),
+            JVal::JUint(num) => (*num).try_into().ok(),
+            JVal::JFloat(num) => num.as_i64(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a whole number that fits in a `u64`, including
+    /// floats such as `42.0`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JVal::JInt(num) => (*num).try_into().ok(),
+            JVal::JUint(num) => Some(*num),
+            JVal::JFloat(num) => num.as_u64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JVal::JBool(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            JVal::JBool(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JVal>> {
+        match self {
+            JVal::JArray(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JVal>> {
+        match self {
+            JVal::JArray(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JMap> {
+        match self {
+            JVal::JObject(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut JMap> {
+        match self {
+            JVal::JObject(object) => Some(object),
+            _ => None,
+        }
+    }
+}
+
+/// Numbers are equal when they have the same value, whatever their variant, so
+/// `JInt(1)` equals a `JFloat` of `1.0` or `10e-1`, just as in jq and JSONPath.
+/// The comparison is exact: `9007199254740993` does not equal `9007199254740992.0`.
+/// Match on the variant to tell integers and floats apart.
+impl PartialEq for JVal {
+    fn eq(&self, other: &JVal) -> bool {
+        match (self, other) {
+            (JVal::JString(a), JVal::JString(b)) => a == b,
+            (JVal::JBool(a), JVal::JBool(b)) => a == b,
+            (JVal::JInt(a), JVal::JInt(b)) => a == b,
+            (JVal::JUint(a), JVal::JUint(b)) => a == b,
+            (JVal::JObject(a), JVal::JObject(b)) => a == b,
+            (JVal::JArray(a), JVal::JArray(b)) => a == b,
+            (JVal::JNull, JVal::JNull) => true,
+            (a, b) => match (a.as_number(), b.as_number()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Yields `null` when this is not an object or has no such key, so lookups can be
+/// chained, e.g. `value["users"][0]["name"]`.
+impl Index<&str> for JVal {
+    type Output = JVal;
+    fn index(&self, key: &str) -> &JVal {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Yields `null` when this is not an array or `index` is out of bounds.
+impl Index<usize> for JVal {
+    type Output = JVal;
+    fn index(&self, index: usize) -> &JVal {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
+
+impl From<bool> for JVal {
+    fn from(boolean: bool) -> Self {
+        JVal::JBool(boolean)
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for JVal {
+                fn from(num: $ty) -> Self {
+                    JVal::JInt(num.into())
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+impl From<u64> for JVal {
+    fn from(num: u64) -> Self {
+        i64::try_from(num).map_or(JVal::JUint(num), JVal::JInt)
+    }
+}
+
+// Wider integers take the variant parsing their text would give
+macro_rules! from_wide_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for JVal {
+                fn from(num: $ty) -> Self {
+                    if let Ok(num) = i64::try_from(num) {
+                        JVal::JInt(num)
+                    } else if let Ok(num) = u64::try_from(num) {
+                        JVal::JUint(num)
+                    } else {
+                        JVal::JFloat(Number::from(num))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_wide_integer!(i128, isize, u128, usize);
+
+// NaN and the infinities have no JSON spelling, so they become null, as they would
+// when a parsed `NaN` is serialized
+macro_rules! from_float {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for JVal {
+                fn from(num: $ty) -> Self {
+                    Number::from_f64(num.into()).map_or(JVal::JNull, JVal::JFloat)
+                }
+            }
+        )*
+    };
+}
+
+from_float!(f64, f32);
+
+/// Classifies the number as parsing its text would, so `42` becomes a `JInt`.
+impl From<Number> for JVal {
+    fn from(num: Number) -> Self {
+        match num.kind() {
+            Kind::Int(num) => JVal::JInt(num),
+            Kind::Uint(num) => JVal::JUint(num),
+            Kind::Float => JVal::JFloat(num),
+        }
+    }
+}
+
+impl From<String> for JVal {
+    fn from(string: String) -> Self {
+        JVal::JString(string)
+    }
+}
+
+impl From<&str> for JVal {
+    fn from(string: &str) -> Self {
+        JVal::JString(string.to_string())
+    }
+}
+
+impl From<char> for JVal {
+    fn from(ch: char) -> Self {
+        JVal::JString(ch.to_string())
+    }
+}
+
+impl From<()> for JVal {
+    fn from(_: ()) -> Self {
+        JVal::JNull
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<JVal>> From<Option<T>> for JVal {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(JVal::JNull, Into::into)
+    }
+}
+
+impl<T: Into<JVal>> From<Vec<T>> for JVal {
+    fn from(items: Vec<T>) -> Self {
+        JVal::JArray(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<JVal>> From<&[T]> for JVal {
+    fn from(items: &[T]) -> Self {
+        JVal::JArray(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl From<JMap> for JVal {
+    fn from(map: JMap) -> Self {
+        JVal::JObject(map)
+    }
+}
+
+/// Members are added in the map's iteration order, which for a `HashMap` is arbitrary.
+impl<K: Into<String>, V: Into<JVal>> From<HashMap<K, V>> for JVal {
+    fn from(map: HashMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<JVal>> From<BTreeMap<K, V>> for JVal {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<T: Into<JVal>> FromIterator<T> for JVal {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        JVal::JArray(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<JVal>> FromIterator<(K, V)> for JVal {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        JVal::JObject(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
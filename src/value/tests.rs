@@ -0,0 +1,217 @@
+use super::*;
+use crate::parser::from_str;
+
+fn sample() -> JVal {
+    from_str(r#"{"name": "Ada", "age": 36, "tags": ["x", "y"], "admin": false, "boss": null}"#)
+        .unwrap()
+}
+
+#[test]
+fn test_get_and_get_index() {
+    let value = sample();
+    assert_eq!(value.get("name"), Some(&JVal::JString("Ada".to_string())));
+    assert_eq!(value.get("missing"), None);
+    assert_eq!(value.get_index(0), None);
+
+    let tags = value.get("tags").unwrap();
+    assert_eq!(tags.get_index(1), Some(&JVal::JString("y".to_string())));
+    assert_eq!(tags.get_index(2), None);
+    assert_eq!(tags.get("name"), None);
+}
+
+#[test]
+fn test_index_chains_through_missing_values() {
+    let value = sample();
+    assert_eq!(value["name"], JVal::from("Ada"));
+    assert_eq!(value["tags"][0], JVal::from("x"));
+    assert_eq!(value["tags"][5], JVal::JNull);
+    assert_eq!(value["missing"]["deeper"][0], JVal::JNull);
+    assert_eq!(value[0], JVal::JNull);
+    assert!(value["boss"].is_null());
+}
+
+#[test]
+fn test_typed_accessors() {
+    let value = sample();
+    assert_eq!(value["name"].as_str(), Some("Ada"));
+    assert_eq!(value["age"].as_f64(), Some(36.0));
+    assert_eq!(value["age"].as_i64(), Some(36));
+    assert_eq!(value["age"].as_u64(), Some(36));
+    assert_eq!(value["admin"].as_bool(), Some(false));
+    assert_eq!(value["tags"].as_array().map(Vec::len), Some(2));
+    assert_eq!(value.as_object().map(JMap::len), Some(5));
+
+    assert_eq!(value["name"].as_f64(), None);
+    assert_eq!(value["age"].as_str(), None);
+    assert_eq!(value["tags"].as_object(), None);
+    assert_eq!(value.as_array(), None);
+}
+
+#[test]
+fn test_integer_accessors_reject_fractions_and_out_of_range() {
+    assert_eq!(JVal::from(1.5).as_i64(), None);
+    assert_eq!(JVal::from(-3).as_i64(), Some(-3));
+    assert_eq!(JVal::from(-3).as_u64(), None);
+    assert_eq!(JVal::from(1e19).as_i64(), None);
+    assert_eq!(JVal::from(1e19).as_u64(), Some(10_000_000_000_000_000_000));
+    assert_eq!(JVal::from(1e20).as_u64(), None);
+    assert_eq!(JVal::from(i64::MIN).as_i64(), Some(i64::MIN));
+    assert_eq!(JVal::from(f64::NAN).as_i64(), None);
+    assert!(JVal::from(7).is_i64());
+    assert!(!JVal::from(0.5).is_u64());
+}
+
+#[test]
+fn test_is_predicates() {
+    let value = sample();
+    assert!(value.is_object());
+    assert!(value["tags"].is_array());
+    assert!(value["name"].is_string());
+    assert!(value["age"].is_number());
+    assert!(value["admin"].is_bool());
+    assert!(value["boss"].is_null());
+    assert!(!value["name"].is_number());
+    assert!(!value.is_null());
+}
+
+#[test]
+fn test_mutable_accessors() {
+    let mut value = sample();
+    value
+        .get_mut("name")
+        .unwrap()
+        .as_str_mut()
+        .unwrap()
+        .push_str(" L.");
+    *value.get_mut("age").unwrap() = JVal::from(37);
+    *value.get_mut("admin").unwrap().as_bool_mut().unwrap() = true;
+    value
+        .get_mut("tags")
+        .unwrap()
+        .as_array_mut()
+        .unwrap()
+        .push(JVal::from("z"));
+    *value.get_mut("tags").unwrap().get_index_mut(0).unwrap() = JVal::from(0);
+    value
+        .as_object_mut()
+        .unwrap()
+        .insert("new".to_string(), JVal::from(true));
+
+    assert_eq!(
+        value,
+        from_str(
+            r#"{"name": "Ada L.", "age": 37, "tags": [0, "y", "z"], "admin": true, "boss": null, "new": true}"#
+        )
+        .unwrap()
+    );
+    assert_eq!(value.get_mut("missing"), None);
+    assert_eq!(value.get_index_mut(0), None);
+}
+
+#[test]
+fn test_take_leaves_null() {
+    let mut value = sample();
+    let tags = value.get_mut("tags").unwrap().take();
+    assert_eq!(tags, from_str(r#"["x", "y"]"#).unwrap());
+    assert!(value["tags"].is_null());
+}
+
+#[test]
+fn test_from_primitives() {
+    assert_eq!(JVal::from(true), JVal::JBool(true));
+    assert!(matches!(JVal::from(3u8), JVal::JInt(3)));
+    assert!(matches!(JVal::from(-3i64), JVal::JInt(-3)));
+    assert!(matches!(JVal::from(u64::MAX), JVal::JUint(u64::MAX)));
+    assert!(matches!(JVal::from(u128::MAX), JVal::JFloat(_)));
+    assert!(matches!(JVal::from(2.0), JVal::JFloat(_)));
+    assert_eq!(JVal::from(0.5f32), JVal::from(0.5));
+    assert_eq!(JVal::from("a"), JVal::JString("a".to_string()));
+    assert_eq!(
+        JVal::from(String::from("b")),
+        JVal::JString("b".to_string())
+    );
+    assert_eq!(JVal::from('c'), JVal::JString("c".to_string()));
+    assert_eq!(JVal::from(()), JVal::JNull);
+    assert_eq!(JVal::default(), JVal::JNull);
+}
+
+#[test]
+fn test_integer_and_float_variants() {
+    assert!(matches!(from_str("42").unwrap(), JVal::JInt(42)));
+    assert!(matches!(from_str("-42").unwrap(), JVal::JInt(-42)));
+    assert!(matches!(
+        from_str("18446744073709551615").unwrap(),
+        JVal::JUint(u64::MAX)
+    ));
+    assert!(matches!(from_str("42.0").unwrap(), JVal::JFloat(_)));
+    assert!(matches!(from_str("-0").unwrap(), JVal::JFloat(_)));
+    assert!(from_str("4.2e1").unwrap().is_f64());
+    assert!(!from_str("42").unwrap().is_f64());
+    assert_eq!(from_str("42.0").unwrap().as_i64(), Some(42));
+    assert_eq!(
+        format!("{:?}", from_str("[42, 42.0]").unwrap()),
+        "JArray([JInt(42), JFloat(42.0)])"
+    );
+}
+
+#[test]
+fn test_numbers_equal_by_value_across_variants() {
+    assert_eq!(JVal::JInt(1), from_str("1.0").unwrap());
+    assert_eq!(JVal::JInt(1), from_str("10e-1").unwrap());
+    assert_eq!(JVal::JInt(1), JVal::JUint(1));
+    assert_eq!(JVal::JInt(0), from_str("-0").unwrap());
+    assert_eq!(
+        JVal::JUint(u64::MAX),
+        from_str("1.8446744073709551615e19").unwrap()
+    );
+    assert_eq!(
+        from_str(r#"[1, {"a": 2}]"#).unwrap(),
+        from_str(r#"[1.0, {"a": 2e0}]"#).unwrap()
+    );
+    // No rounding through `f64`, which cannot tell these apart
+    assert_ne!(
+        from_str("9007199254740993").unwrap(),
+        from_str("9007199254740992.0").unwrap()
+    );
+    assert_ne!(JVal::JInt(-1), JVal::JUint(u64::MAX));
+    assert_ne!(JVal::JInt(1), JVal::from("1"));
+    assert_ne!(JVal::JInt(0), JVal::JBool(false));
+}
+
+#[test]
+fn test_from_option() {
+    assert_eq!(JVal::from(Some(1)), JVal::from(1));
+    assert_eq!(JVal::from(None::<i32>), JVal::JNull);
+}
+
+#[test]
+fn test_from_collections() {
+    let expected = from_str(r#"[1, 2, null]"#).unwrap();
+    assert_eq!(JVal::from(vec![Some(1), Some(2), None]), expected);
+    assert_eq!(JVal::from(&[Some(1), Some(2), None][..]), expected);
+    assert_eq!(
+        [Some(1), Some(2), None].into_iter().collect::<JVal>(),
+        expected
+    );
+
+    let expected = from_str(r#"{"a": 1, "b": "x"}"#).unwrap();
+    let btree = BTreeMap::from([("a", JVal::from(1)), ("b", JVal::from("x"))]);
+    assert_eq!(JVal::from(btree.clone()), expected);
+    assert_eq!(
+        JVal::from(btree.into_iter().collect::<HashMap<_, _>>()),
+        expected
+    );
+    assert_eq!(
+        vec![("a", JVal::from(1)), ("b", JVal::from("x"))]
+            .into_iter()
+            .collect::<JVal>(),
+        expected
+    );
+}
+
+#[test]
+fn test_from_preserves_object_order() {
+    let value: JVal = [("z", 1), ("a", 2)].into_iter().collect();
+    let keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, ["z", "a"]);
+}
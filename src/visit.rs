@@ -0,0 +1,282 @@
+use std::io::{self, Read, Write};
+
+use crate::error::{EmitError, ParseError};
+use crate::events::{Event, EventReader};
+use crate::lexer::TokenReader;
+use crate::number::Number;
+use crate::options::WriteOptions;
+use crate::parser::{self, JVal};
+use crate::ser::Serializer;
+
+#[cfg(test)]
+mod tests;
+
+/// Callbacks for each part of a document, in source order. Every method does nothing
+/// by default, so a visitor only implements the ones it cares about. Returning an
+/// error stops the walk.
+pub trait Visitor {
+    /// What a callback, or the document being malformed, stops the walk with.
+    type Error: From<ParseError>;
+
+    fn start_object(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// An object key. The member's value is visited next.
+    fn key(&mut self, _key: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn start_array(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn string(&mut self, _value: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// An integer that fits in an `i64`. Passed on to `number` by default.
+    fn integer(&mut self, value: i64) -> Result<(), Self::Error> {
+        self.number(&Number::from(value))
+    }
+
+    /// A non-negative integer too large for an `i64`. Passed on to `number` by default.
+    fn unsigned(&mut self, value: u64) -> Result<(), Self::Error> {
+        self.number(&Number::from(value))
+    }
+
+    /// Any other number, as written, and by default integers too.
+    fn number(&mut self, _value: &Number) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn boolean(&mut self, _value: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn null(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Walks a complete document, failing if anything but whitespace follows the root
+/// value. No tree is built, so memory use grows with the nesting depth only.
+pub fn visit<R: Read, V: Visitor>(
+    iter: &mut TokenReader<R>,
+    visitor: &mut V,
+) -> Result<(), V::Error> {
+    visit_partial(iter, visitor)?;
+    parser::expect_end(iter)?;
+    Ok(())
+}
+
+/// Walks the first value in the token stream and leaves the rest unread.
+pub fn visit_partial<R: Read, V: Visitor>(
+    iter: &mut TokenReader<R>,
+    visitor: &mut V,
+) -> Result<(), V::Error> {
+    for event in EventReader::new(iter) {
+        match event? {
+            Event::StartObject => visitor.start_object()?,
+            Event::Key(key) => visitor.key(&key)?,
+            Event::EndObject => visitor.end_object()?,
+            Event::StartArray => visitor.start_array()?,
+            Event::EndArray => visitor.end_array()?,
+            Event::Value(JVal::JString(value)) => visitor.string(&value)?,
+            Event::Value(JVal::JInt(value)) => visitor.integer(value)?,
+            Event::Value(JVal::JUint(value)) => visitor.unsigned(value)?,
+            Event::Value(JVal::JFloat(value)) => visitor.number(&value)?,
+            Event::Value(JVal::JBool(value)) => visitor.boolean(value)?,
+            Event::Value(JVal::JNull) => visitor.null()?,
+            Event::Value(JVal::JObject(_) | JVal::JArray(_)) => {
+                unreachable!("containers are reported as start and end events")
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Counts what a document is made of.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub objects: usize,
+    pub arrays: usize,
+    /// Object members, counting every occurrence of a duplicated key.
+    pub keys: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize,
+    /// How deeply objects and arrays nest, 0 for a scalar document.
+    pub max_depth: usize,
+    depth: usize,
+}
+
+impl Stats {
+    fn open(&mut self) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+}
+
+impl Visitor for Stats {
+    type Error = ParseError;
+
+    fn start_object(&mut self) -> Result<(), ParseError> {
+        self.objects += 1;
+        self.open();
+        Ok(())
+    }
+
+    fn key(&mut self, _key: &str) -> Result<(), ParseError> {
+        self.keys += 1;
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), ParseError> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn start_array(&mut self) -> Result<(), ParseError> {
+        self.arrays += 1;
+        self.open();
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), ParseError> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn string(&mut self, _value: &str) -> Result<(), ParseError> {
+        self.strings += 1;
+        Ok(())
+    }
+
+    fn number(&mut self, _value: &Number) -> Result<(), ParseError> {
+        self.numbers += 1;
+        Ok(())
+    }
+
+    fn boolean(&mut self, _value: bool) -> Result<(), ParseError> {
+        self.booleans += 1;
+        Ok(())
+    }
+
+    fn null(&mut self) -> Result<(), ParseError> {
+        self.nulls += 1;
+        Ok(())
+    }
+}
+
+/// Writes the document back out as it is visited, compact or pretty-printed, with
+/// the same output `ser::to_writer_with_options` gives for the parsed tree.
+pub struct Emitter<W: Write> {
+    serializer: Serializer<W>,
+    // Whether each open container has had no members written yet, innermost last
+    empty: Vec<bool>,
+    // A key has been written, so the next value needs no separator
+    after_key: bool,
+}
+
+impl<W: Write> Emitter<W> {
+    pub fn new(writer: W, options: WriteOptions) -> Self {
+        Emitter {
+            serializer: Serializer::new(writer, options),
+            empty: Vec::new(),
+            after_key: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.serializer.into_inner()
+    }
+
+    /// Writes the comma and indentation a member needs before it.
+    fn separate(&mut self) -> io::Result<()> {
+        if std::mem::take(&mut self.after_key) {
+            return Ok(());
+        }
+        let depth = self.empty.len();
+        match self.empty.last_mut() {
+            Some(empty) => {
+                let is_first = std::mem::replace(empty, false);
+                self.serializer.write_separator(is_first, depth)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn open(&mut self, bracket: &[u8]) -> Result<(), EmitError> {
+        self.separate()?;
+        self.serializer.write_raw(bracket)?;
+        self.empty.push(true);
+        Ok(())
+    }
+
+    fn close(&mut self, bracket: &[u8]) -> Result<(), EmitError> {
+        if self.empty.pop() == Some(false) {
+            self.serializer.write_newline(self.empty.len())?;
+        }
+        self.serializer.write_raw(bracket)?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Visitor for Emitter<W> {
+    type Error = EmitError;
+
+    fn start_object(&mut self) -> Result<(), EmitError> {
+        self.open(b"{")
+    }
+
+    fn key(&mut self, key: &str) -> Result<(), EmitError> {
+        self.separate()?;
+        self.serializer.write_string(key)?;
+        self.serializer.write_colon()?;
+        self.after_key = true;
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), EmitError> {
+        self.close(b"}")
+    }
+
+    fn start_array(&mut self) -> Result<(), EmitError> {
+        self.open(b"[")
+    }
+
+    fn end_array(&mut self) -> Result<(), EmitError> {
+        self.close(b"]")
+    }
+
+    fn string(&mut self, value: &str) -> Result<(), EmitError> {
+        self.separate()?;
+        Ok(self.serializer.write_string(value)?)
+    }
+
+    fn number(&mut self, value: &Number) -> Result<(), EmitError> {
+        self.separate()?;
+        Ok(self.serializer.write_number(value)?)
+    }
+
+    fn boolean(&mut self, value: bool) -> Result<(), EmitError> {
+        self.separate()?;
+        let text: &[u8] = if value { b"true" } else { b"false" };
+        Ok(self.serializer.write_raw(text)?)
+    }
+
+    fn null(&mut self) -> Result<(), EmitError> {
+        self.separate()?;
+        Ok(self.serializer.write_raw(b"null")?)
+    }
+}
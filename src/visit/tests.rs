@@ -0,0 +1,143 @@
+use super::*;
+use crate::reader::JsonReader;
+use crate::ser;
+
+fn tokens(input: &str) -> TokenReader<&[u8]> {
+    TokenReader::new(JsonReader::from_str(input))
+}
+
+fn emit(input: &str, options: WriteOptions) -> String {
+    let mut emitter = Emitter::new(Vec::new(), options);
+    visit(&mut tokens(input), &mut emitter).unwrap_or_else(|e| panic!("{}", e));
+    String::from_utf8(emitter.into_inner()).unwrap()
+}
+
+#[test]
+fn test_stats() {
+    let mut stats = Stats::default();
+    visit(
+        &mut tokens(r#"{"a": [1, 2.5, "x", null], "b": {"c": [true, false, []]}, "d": {}}"#),
+        &mut stats,
+    )
+    .unwrap();
+
+    assert_eq!(stats.objects, 3);
+    assert_eq!(stats.arrays, 3);
+    assert_eq!(stats.keys, 4);
+    assert_eq!(stats.strings, 1);
+    assert_eq!(stats.numbers, 2);
+    assert_eq!(stats.booleans, 2);
+    assert_eq!(stats.nulls, 1);
+    assert_eq!(stats.max_depth, 4);
+
+    let mut scalar = Stats::default();
+    visit(&mut tokens("7"), &mut scalar).unwrap();
+    assert_eq!(scalar.numbers, 1);
+    assert_eq!(scalar.max_depth, 0);
+}
+
+#[test]
+fn test_emitter_matches_serializer() {
+    let inputs = [
+        r#"{"a": [1, {"b": "x\n"}], "c": {}, "d": [], "e": [[]], "f": {"g": {}}}"#,
+        r#"[true, false, null, -0.5, 1e300, "é"]"#,
+        "[]",
+        "{}",
+        r#""plain""#,
+        "[[[1]], [2, [3, []]]]",
+    ];
+    let options = [
+        WriteOptions::default(),
+        WriteOptions {
+            indent: Some(2),
+            ..WriteOptions::default()
+        },
+        WriteOptions {
+            indent: Some(4),
+            ascii_only: true,
+        },
+    ];
+
+    for input in inputs {
+        let value = parser::from_str(input).unwrap();
+        for options in options {
+            assert_eq!(
+                emit(input, options),
+                ser::to_string_with_options(&value, options),
+                "{} with {:?}",
+                input,
+                options
+            );
+        }
+    }
+}
+
+#[test]
+fn test_visitor_errors_stop_the_walk() {
+    #[derive(Debug)]
+    enum Rejected {
+        Null { after: usize },
+        Parse(ParseError),
+    }
+
+    impl From<ParseError> for Rejected {
+        fn from(error: ParseError) -> Self {
+            Rejected::Parse(error)
+        }
+    }
+
+    struct NoNulls {
+        seen: usize,
+    }
+
+    impl Visitor for NoNulls {
+        type Error = Rejected;
+
+        fn number(&mut self, _value: &Number) -> Result<(), Rejected> {
+            self.seen += 1;
+            Ok(())
+        }
+
+        fn null(&mut self) -> Result<(), Rejected> {
+            Err(Rejected::Null { after: self.seen })
+        }
+    }
+
+    let mut visitor = NoNulls { seen: 0 };
+    assert!(matches!(
+        visit(&mut tokens("[1, 2, null, 3]"), &mut visitor),
+        Err(Rejected::Null { after: 2 })
+    ));
+    assert_eq!(visitor.seen, 2);
+    assert!(matches!(
+        visit(&mut tokens("[1, 2"), &mut visitor),
+        Err(Rejected::Parse(ParseError::UnexpectedEof { .. }))
+    ));
+}
+
+#[test]
+fn test_malformed_and_trailing_input() {
+    let mut stats = Stats::default();
+    assert!(matches!(
+        visit(&mut tokens("[1, 2"), &mut stats),
+        Err(ParseError::UnexpectedEof { .. })
+    ));
+    assert_eq!(stats.numbers, 2);
+
+    assert!(matches!(
+        visit(&mut tokens("[1] 2"), &mut Stats::default()),
+        Err(ParseError::TrailingData { .. })
+    ));
+
+    let mut iter = tokens("[1] [2, 3]");
+    let mut stats = Stats::default();
+    visit_partial(&mut iter, &mut stats).unwrap();
+    visit_partial(&mut iter, &mut stats).unwrap();
+    assert_eq!((stats.arrays, stats.numbers), (2, 3));
+
+    let mut emitter = Emitter::new(Vec::new(), WriteOptions::default());
+    assert!(matches!(
+        visit(&mut tokens("{\"a\" 1}"), &mut emitter),
+        Err(EmitError::Parse(ParseError::UnexpectedToken { .. }))
+    ));
+}
@@ -0,0 +1,481 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::{Read, Write};
+use std::process::Stdio;
+use tempfile::NamedTempFile;
+
+fn json_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
+#[test]
+fn test_prints_parsed_document() {
+    let file = json_file(r#"{"name": "John", "tags": [1, 2.5]}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("fmt")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("{\n  \"name\": \"John\",\n  \"tags\": [\n    1,\n    2.5\n  ]\n}\n");
+}
+
+#[test]
+fn test_error_shows_caret_excerpt() {
+    let file = json_file("{\n  \"a\": 1\n  \"b\": 2\n}");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("fmt")
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "error: Missing comma between elements",
+        ))
+        .stderr(predicate::str::contains(":3:3\n"))
+        .stderr(predicate::str::contains("3 |   \"b\": 2\n  |   ^\n"));
+}
+
+#[test]
+fn test_prints_scalar_document() {
+    let file = json_file(r#""text""#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("fmt")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("\"text\"\n");
+}
+
+#[test]
+fn test_prints_keys_in_source_order() {
+    let file = json_file(r#"{"zeta": 1, "alpha": 2, "mid": 3}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("fmt")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r#"(?s)"zeta".*"alpha".*"mid""#).unwrap());
+}
+
+#[test]
+fn test_duplicate_keys_flag() {
+    let file = json_file(r#"{"a": 1, "a": 2}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("fmt")
+        .arg(file.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["fmt", "--duplicate-keys", "error"])
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Duplicate key \"a\""));
+}
+
+#[test]
+fn test_reads_stdin() {
+    for args in [&["fmt"][..], &["fmt", "-"][..]] {
+        Command::cargo_bin("jsonp")
+            .unwrap()
+            .args(args)
+            .write_stdin("[true]")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("true"));
+    }
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("fmt")
+        .write_stdin("[1,")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("<stdin>:1:4"));
+}
+
+#[test]
+fn test_fmt_compact_and_minify() {
+    let file = json_file("{ \"a\" : [ 1 , \"\u{e9}\" ] }");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["fmt", "--compact", "--ascii"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("{\"a\":[1,\"\\u00e9\"]}\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("minify")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("{\"a\":[1,\"\u{e9}\"]}\n");
+}
+
+#[test]
+fn test_fmt_write_in_place() {
+    let file = json_file(r#"{"a":[true]}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["fmt", "--write", "--indent", "4"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        std::fs::read_to_string(file.path()).unwrap(),
+        "{\n    \"a\": [\n        true\n    ]\n}\n"
+    );
+}
+
+#[test]
+fn test_fmt_write_leaves_invalid_file_untouched() {
+    let file = json_file("[1 2]");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["fmt", "--write"])
+        .arg(file.path())
+        .assert()
+        .failure();
+    assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "[1 2]");
+}
+
+#[test]
+fn test_check_exit_codes() {
+    let valid = json_file("[1]");
+    let invalid = json_file("[1 2]");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("check")
+        .arg(valid.path())
+        .assert()
+        .code(0)
+        .stdout(predicate::str::ends_with(": ok\n"));
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("check")
+        .args([valid.path(), invalid.path()])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            ":1:4: Missing comma between elements",
+        ));
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("check")
+        .arg(invalid.path())
+        .arg("missing.json")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "missing.json: Could not read file",
+        ));
+}
+
+#[test]
+fn test_check_json_report() {
+    let invalid = json_file("{\n  \"a\": tru\n}");
+
+    let output = Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["check", "--report", "json", "-"])
+        .arg(invalid.path())
+        .write_stdin("{}")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let report = jsonp::parser::from_slice(&output.stdout).unwrap();
+    let expected = jsonp::parser::from_str(&format!(
+        r#"[
+            {{"path": "-", "status": "valid"}},
+            {{"path": {path:?}, "status": "invalid", "error": "Invalid literal 'tru', expected 'true'",
+              "line": 2, "column": 8, "offset": 9}}
+        ]"#,
+        path = invalid.path().to_string_lossy()
+    ))
+    .unwrap();
+    assert_eq!(report, expected);
+}
+
+#[test]
+fn test_get_pointer() {
+    let file = json_file(r#"{"users": [{"name": "Ada", "a/b": {"x": 1}}]}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["get", "--compact"])
+        .arg(file.path())
+        .arg("/users/0/a~1b")
+        .assert()
+        .success()
+        .stdout("{\"x\":1}\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["get", "-", "/users/0/name"])
+        .write_stdin(r#"{"users": [{"name": "Ada"}]}"#)
+        .assert()
+        .success()
+        .stdout("\"Ada\"\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("get")
+        .arg(file.path())
+        .arg("/users/1/name")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "error: Index 1 is out of bounds for an array of length 1 at '/users/1'",
+        ));
+}
+
+#[test]
+fn test_query() {
+    let file = json_file(r#"{"items": [{"name": "a", "price": 5}, {"name": "b", "price": 15}]}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["query", "--compact"])
+        .arg(file.path())
+        .arg("$.items[?(@.price < 10)].name")
+        .assert()
+        .success()
+        .stdout("[\"a\"]\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["query", "--compact", "--with-paths"])
+        .arg(file.path())
+        .arg("$..price")
+        .assert()
+        .success()
+        .stdout(concat!(
+            r#"[{"path":"$['items'][0]['price']","value":5},"#,
+            r#"{"path":"$['items'][1]['price']","value":15}]"#,
+            "\n"
+        ));
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .arg("query")
+        .arg(file.path())
+        .arg("$.items[")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("error: Unexpected end of query"));
+}
+
+#[test]
+fn test_eval() {
+    let file = json_file(r#"{"items": [{"name": "a", "price": 5}, {"name": "b", "price": 15}]}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["eval", "--compact", ".items[] | select(.price < 10)"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("{\"name\":\"a\",\"price\":5}\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["eval", "--raw-output", ".items[].name"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a\nb\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args([
+            "eval",
+            "-c",
+            "--arg",
+            "name",
+            "b",
+            "--argjson",
+            "extra",
+            "1",
+        ])
+        .arg("[.items[] | select(.name == $name) | .price + $extra]")
+        .arg("-")
+        .write_stdin(r#"{"items": [{"name": "b", "price": 2}]}"#)
+        .assert()
+        .success()
+        .stdout("[3]\n");
+}
+
+#[test]
+fn test_eval_errors() {
+    let file = json_file(r#"{"items": 1}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["eval", ".items[]"])
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr("error: Cannot iterate over number (1)\n");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["eval", ".items | nope"])
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr("error: Unknown function 'nope/0' at offset 9\n");
+}
+
+#[test]
+fn test_limit_flags() {
+    let file = json_file(r#"{"items": [[1, 2], "abcdef", 12345]}"#);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["check", "--max-depth", "3", "--max-string-length", "6"])
+        .arg(file.path())
+        .assert()
+        .success();
+
+    for (flag, value, message) in [
+        ("--max-depth", "2", "Nesting exceeds the limit of 2 levels"),
+        (
+            "--max-string-length",
+            "5",
+            "String exceeds the limit of 5 bytes",
+        ),
+        (
+            "--max-object-members",
+            "0",
+            "Object exceeds the limit of 0 members",
+        ),
+        (
+            "--max-array-elements",
+            "2",
+            "Array exceeds the limit of 2 elements",
+        ),
+        (
+            "--max-input-size",
+            "10",
+            "Input exceeds the limit of 10 bytes",
+        ),
+        (
+            "--max-number-length",
+            "4",
+            "Number exceeds the limit of 4 characters",
+        ),
+    ] {
+        Command::cargo_bin("jsonp")
+            .unwrap()
+            .args(["check", flag, value])
+            .arg(file.path())
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains(message));
+    }
+}
+
+#[test]
+fn test_syntax_flag() {
+    let file = json_file("{'a': [1, NaN,], // note\n}");
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["check"])
+        .arg(file.path())
+        .assert()
+        .code(1);
+
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["minify", "--syntax", "json5"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("{\"a\":[1,null]}\n");
+
+    let keywords = json_file("[TRUE]");
+    for (syntax, code) in [("json5", 1), ("lenient", 0)] {
+        Command::cargo_bin("jsonp")
+            .unwrap()
+            .args(["check", "--syntax", syntax])
+            .arg(keywords.path())
+            .assert()
+            .code(code);
+    }
+}
+
+#[test]
+fn test_default_depth_limit() {
+    let deep = format!("{}{}", "[".repeat(200_000), "]".repeat(200_000));
+    let file = json_file(&deep);
+
+    for command in ["check", "fmt"] {
+        Command::cargo_bin("jsonp")
+            .unwrap()
+            .arg(command)
+            .arg(file.path())
+            .assert()
+            .code(1);
+    }
+    Command::cargo_bin("jsonp")
+        .unwrap()
+        .args(["check", "--max-depth", "600"])
+        .arg(json_file(&format!("{}{}", "[".repeat(600), "]".repeat(600))).path())
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_closed_stdout_is_not_an_error() {
+    let items: Vec<String> = (0..100_000).map(|i| i.to_string()).collect();
+    let file = json_file(&format!("[{}]", items.join(",")));
+    let path = file.path().to_str().unwrap();
+    let many_paths = vec![path; 10_000];
+
+    let runs: [Vec<&str>; 4] = [
+        vec!["eval", ".[]", path],
+        vec!["fmt", path],
+        vec!["query", path, "$[*]"],
+        [&["check"][..], &many_paths].concat(),
+    ];
+    for args in runs {
+        let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_jsonp"))
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // Read the start of the output, as `head -1` would, then hang up
+        let mut stdout = child.stdout.take().unwrap();
+        stdout.read_exact(&mut [0; 1]).unwrap();
+        drop(stdout);
+
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}: {:?}", args[0], output);
+        assert!(output.stderr.is_empty(), "{}: {:?}", args[0], output);
+    }
+}